@@ -0,0 +1,29 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use io_uring::opcode::{self, types};
+use io_uring::squeue;
+
+fn bench_build(c: &mut Criterion) {
+    let mut buf = [0u8; 4096];
+
+    c.bench_function("build_read", |b| {
+        b.iter(|| {
+            opcode::Read::new(types::Fd(black_box(0)), buf.as_mut_ptr(), buf.len() as _)
+                .offset(black_box(4096))
+                .build()
+                .user_data(black_box(0x42))
+        });
+    });
+
+    c.bench_function("build_nop", |b| {
+        b.iter(|| opcode::Nop::new().build().user_data(black_box(0x42)));
+    });
+
+    c.bench_function("const_nop", |b| {
+        const NOP: squeue::Entry = opcode::Nop::new().build();
+
+        b.iter(|| NOP.clone().user_data(black_box(0x42)));
+    });
+}
+
+criterion_group!(build, bench_build);
+criterion_main!(build);
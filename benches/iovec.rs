@@ -38,7 +38,6 @@ fn bench_iovec(c: &mut Criterion) {
                 ring.submission()
                     .available()
                     .push(entry.build())
-                    .ok()
                     .expect("queue is full");
             }
 
@@ -72,13 +71,11 @@ fn bench_iovec(c: &mut Criterion) {
                 let mut queue = ring.submission().available();
                 queue
                     .push(entry.build().flags(squeue::Flags::IO_LINK))
-                    .ok()
                     .expect("queue is full");
                 for _ in 0..4 {
                     let entry = opcode::Nop::new().build();
                     queue
                         .push(entry.flags(squeue::Flags::IO_LINK))
-                        .ok()
                         .expect("queue is full");
                 }
             }
@@ -105,7 +102,6 @@ fn bench_iovec(c: &mut Criterion) {
                 unsafe {
                     queue
                         .push(entry.build().flags(squeue::Flags::IO_LINK))
-                        .ok()
                         .expect("queue is full");
                 }
             }
@@ -134,7 +130,6 @@ fn bench_iovec(c: &mut Criterion) {
                     ring.submission()
                         .available()
                         .push(entry.build())
-                        .ok()
                         .expect("queue is full");
                 }
 
@@ -3,7 +3,8 @@ use std::os::unix::io::{AsRawFd, RawFd};
 use std::{io, ptr};
 
 use io_uring::opcode::{self, types};
-use io_uring::{squeue, IoUring};
+use io_uring::squeue::{self, PushError};
+use io_uring::{IoUring, SubmitError};
 use slab::Slab;
 
 #[derive(Clone, Debug)]
@@ -34,7 +35,7 @@ impl AcceptCount {
         AcceptCount {
             entry: opcode::Accept::new(types::Fd(fd), ptr::null_mut(), ptr::null_mut())
                 .build()
-                .user_data(token as _),
+                .user_data(token as u64),
             count,
         }
     }
@@ -71,7 +72,7 @@ fn main() -> anyhow::Result<()> {
     loop {
         match submitter.submit_and_wait(1) {
             Ok(_) => (),
-            Err(ref err) if err.raw_os_error() == Some(libc::EBUSY) => (),
+            Err(SubmitError::CompletionQueueOverflow) => (),
             Err(err) => return Err(err.into()),
         }
 
@@ -83,7 +84,7 @@ fn main() -> anyhow::Result<()> {
             if sq.is_full() {
                 match submitter.submit() {
                     Ok(_) => (),
-                    Err(ref err) if err.raw_os_error() == Some(libc::EBUSY) => break,
+                    Err(SubmitError::CompletionQueueOverflow) => break,
                     Err(err) => return Err(err.into()),
                 }
                 sq.sync();
@@ -126,10 +127,10 @@ fn main() -> anyhow::Result<()> {
 
                     let poll_e = opcode::PollAdd::new(types::Fd(fd), libc::POLLIN)
                         .build()
-                        .user_data(poll_token as _);
+                        .user_data(poll_token as u64);
 
                     unsafe {
-                        if let Err(entry) = sq.push(poll_e) {
+                        if let Err(PushError::Full(entry)) = sq.push(poll_e) {
                             backlog.push(entry);
                         }
                     }
@@ -149,10 +150,10 @@ fn main() -> anyhow::Result<()> {
 
                     let read_e = opcode::Read::new(types::Fd(fd), buf.as_mut_ptr(), buf.len() as _)
                         .build()
-                        .user_data(read_token as _);
+                        .user_data(read_token as u64);
 
                     unsafe {
-                        if let Err(entry) = sq.push(read_e) {
+                        if let Err(PushError::Full(entry)) = sq.push(read_e) {
                             backlog.push(entry);
                         }
                     }
@@ -180,10 +181,10 @@ fn main() -> anyhow::Result<()> {
 
                         let write_e = opcode::Write::new(types::Fd(fd), buf.as_ptr(), len as _)
                             .build()
-                            .user_data(token_index as _);
+                            .user_data(token_index as u64);
 
                         unsafe {
-                            if let Err(entry) = sq.push(write_e) {
+                            if let Err(PushError::Full(entry)) = sq.push(write_e) {
                                 backlog.push(entry);
                             }
                         }
@@ -204,7 +205,7 @@ fn main() -> anyhow::Result<()> {
 
                         opcode::PollAdd::new(types::Fd(fd), libc::POLLIN)
                             .build()
-                            .user_data(token_index as _)
+                            .user_data(token_index as u64)
                     } else {
                         let offset = offset + write_len;
                         let len = len - offset;
@@ -220,11 +221,11 @@ fn main() -> anyhow::Result<()> {
 
                         opcode::Write::new(types::Fd(fd), buf.as_ptr(), len as _)
                             .build()
-                            .user_data(token_index as _)
+                            .user_data(token_index as u64)
                     };
 
                     unsafe {
-                        if let Err(entry) = sq.push(entry) {
+                        if let Err(PushError::Full(entry)) = sq.push(entry) {
                             backlog.push(entry);
                         }
                     }
@@ -0,0 +1,88 @@
+//! Requests waited for on a ring private to the calling thread.
+
+use std::cell::RefCell;
+use std::io;
+
+use crate::{squeue, IoUring};
+
+const ENTRIES: u32 = 8;
+
+thread_local! {
+    static RING: RefCell<Option<IoUring>> = const { RefCell::new(None) };
+}
+
+/// Submit `entries` together, and wait for their results, in order.
+///
+/// # Safety
+///
+/// The parameters of the entries must be valid until this returns.
+pub unsafe fn run<const N: usize>(entries: [squeue::Entry; N]) -> io::Result<[i32; N]> {
+    RING.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        let ring = match &mut *cell {
+            Some(ring) => ring,
+            None => cell.insert(IoUring::new(ENTRIES)?),
+        };
+
+        let ret = submit(ring, entries);
+
+        // Entries that could not be submitted must never reach the kernel afterwards,
+        // so the ring is thrown away.
+        if ret.is_err() {
+            *cell = None;
+        }
+
+        ret
+    })
+}
+
+unsafe fn submit<const N: usize>(
+    ring: &mut IoUring,
+    entries: [squeue::Entry; N],
+) -> io::Result<[i32; N]> {
+    let mut sq = ring.submission().available();
+    for (i, entry) in IntoIterator::into_iter(entries).enumerate() {
+        if sq.push(entry.user_data(i as u64)).is_err() {
+            unreachable!("the ring has space for the entries");
+        }
+    }
+    drop(sq);
+
+    let mut results = [0; N];
+    let mut done = 0;
+
+    while done < N {
+        if let Err(err) = ring.submit_and_wait(N - done) {
+            // The kernel may still use the parameters of the entries it consumed,
+            // so they are waited for before the error is returned.
+            let submitted = N - ring.submission().len();
+            while done < submitted {
+                // Returning would free the parameters while they are in use,
+                // so a failed wait is retried, the ring is valid and its errors are transient.
+                let _ = ring.wait(submitted - done);
+                reap(ring, &mut results, &mut done);
+            }
+
+            return Err(err.into());
+        }
+
+        reap(ring, &mut results, &mut done);
+    }
+
+    Ok(results)
+}
+
+fn reap<const N: usize>(ring: &mut IoUring, results: &mut [i32; N], done: &mut usize) {
+    for entry in ring.completion().available() {
+        results[entry.user_data() as usize] = entry.result();
+        *done += 1;
+    }
+}
+
+pub fn cvt(ret: i32) -> io::Result<u32> {
+    if ret >= 0 {
+        Ok(ret as u32)
+    } else {
+        Err(io::Error::from_raw_os_error(-ret))
+    }
+}
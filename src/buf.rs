@@ -0,0 +1,545 @@
+//! Provided buffers.
+//!
+//! [BufGroup] manages a group of buffers given to the kernel with
+//! [ProvideBuffers](opcode::ProvideBuffers), from which requests with
+//! [BUFFER_SELECT](squeue::Flags::BUFFER_SELECT) pick a buffer when data is ready,
+//! instead of holding one for as long as they wait.
+//! [BufRing] does the same with a ring of buffers shared with the kernel,
+//! which is refilled without submitting a request.
+
+use std::cell::Cell;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::{io, slice};
+
+use crate::util::Mmap;
+use crate::{cqueue, opcode, squeue, sys, Submitter};
+
+/// A group of provided buffers of equal size, with their backing memory.
+///
+/// A buffer the kernel picked for a request is taken from the group with [BufGroup::take],
+/// and handed back to the kernel with [BufGroup::reprovide] once it has been consumed.
+///
+/// Dropping the group frees the memory only if none of its buffers is provided,
+/// otherwise it is leaked, because the kernel may still write to it.
+/// Use [BufGroup::remove] and [BufGroup::removed] to take the buffers back first.
+pub struct BufGroup {
+    memory: NonNull<u8>,
+    bgid: u16,
+    buf_len: usize,
+    provided: Vec<bool>,
+}
+
+/// A buffer of a [BufGroup] that the kernel has filled.
+#[derive(Debug)]
+pub struct ProvidedBuf {
+    bgid: u16,
+    bid: u16,
+    len: usize,
+}
+
+unsafe impl Send for BufGroup {}
+unsafe impl Sync for BufGroup {}
+
+impl BufGroup {
+    /// Allocate `count` buffers of `buf_len` bytes for the buffer group `bgid`.
+    ///
+    /// The buffers are not provided yet, see [BufGroup::provide].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf_len` does not fit in an `i32`.
+    pub fn new(bgid: u16, count: u16, buf_len: usize) -> BufGroup {
+        assert!(buf_len <= i32::MAX as usize, "buffer too large");
+
+        let memory = vec![0u8; count as usize * buf_len].into_boxed_slice();
+        let memory = unsafe { NonNull::new_unchecked(Box::into_raw(memory) as *mut u8) };
+
+        BufGroup {
+            memory,
+            bgid,
+            buf_len,
+            provided: vec![false; count as usize],
+        }
+    }
+
+    /// The buffer group id, to set on requests with [opcode::Recv::buf_group] and the like.
+    #[inline]
+    pub fn bgid(&self) -> u16 {
+        self.bgid
+    }
+
+    /// The size of each buffer.
+    #[inline]
+    pub fn buf_len(&self) -> usize {
+        self.buf_len
+    }
+
+    /// The number of buffers of the group.
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.provided.len()
+    }
+
+    /// Build an entry that provides all buffers of the group.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a buffer is already provided or taken.
+    pub fn provide(&mut self) -> squeue::Entry {
+        assert!(
+            !self.provided.contains(&true),
+            "a buffer of the group is already provided"
+        );
+
+        self.provided
+            .iter_mut()
+            .for_each(|provided| *provided = true);
+
+        opcode::ProvideBuffers::new(
+            self.memory.as_ptr(),
+            self.buf_len as _,
+            self.count() as _,
+            self.bgid,
+            0,
+        )
+        .build()
+    }
+
+    /// Take the buffer the kernel picked for a request from its completion event,
+    /// returns `None` if the request used no buffer.
+    ///
+    /// The result of the event is taken as the number of bytes filled in,
+    /// up to the size of the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is not provided, e.g. it has already been taken.
+    pub fn take(&mut self, entry: &cqueue::Entry) -> Option<ProvidedBuf> {
+        let bid = entry.buffer_id()?;
+        let provided = self
+            .provided
+            .get_mut(bid as usize)
+            .filter(|provided| **provided)
+            .expect("the buffer is not provided");
+
+        *provided = false;
+
+        Some(ProvidedBuf {
+            bgid: self.bgid,
+            bid,
+            len: (entry.result().max(0) as usize).min(self.buf_len),
+        })
+    }
+
+    /// The data the kernel filled in.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer was not taken from this group.
+    pub fn get(&self, buf: &ProvidedBuf) -> &[u8] {
+        self.check(buf);
+        unsafe { slice::from_raw_parts(self.buf_ptr(buf.bid), buf.len) }
+    }
+
+    /// The data the kernel filled in.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer was not taken from this group.
+    pub fn get_mut(&mut self, buf: &mut ProvidedBuf) -> &mut [u8] {
+        self.check(buf);
+        unsafe { slice::from_raw_parts_mut(self.buf_ptr(buf.bid), buf.len) }
+    }
+
+    /// Build an entry that provides a consumed buffer again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer was not taken from this group.
+    pub fn reprovide(&mut self, buf: ProvidedBuf) -> squeue::Entry {
+        self.check(&buf);
+        self.provided[buf.bid as usize] = true;
+
+        opcode::ProvideBuffers::new(
+            self.buf_ptr(buf.bid),
+            self.buf_len as _,
+            1,
+            self.bgid,
+            buf.bid,
+        )
+        .build()
+    }
+
+    /// Append the data the kernel filled in to `dst`,
+    /// and build an entry that provides the buffer again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer was not taken from this group.
+    #[cfg(feature = "bytes")]
+    pub fn reprovide_into(&mut self, buf: ProvidedBuf, dst: &mut bytes::BytesMut) -> squeue::Entry {
+        dst.extend_from_slice(self.get(&buf));
+        self.reprovide(buf)
+    }
+
+    /// Build an entry that removes the provided buffers from the kernel.
+    ///
+    /// Once its completion event has arrived, and the buffers picked before it taken,
+    /// call [BufGroup::removed].
+    pub fn remove(&self) -> squeue::Entry {
+        opcode::RemoveBuffers::new(self.count() as _, self.bgid).build()
+    }
+
+    /// Mark the buffers as no longer provided, after a [BufGroup::remove] completed.
+    ///
+    /// # Safety
+    ///
+    /// The kernel must no longer hold any buffer of the group.
+    pub unsafe fn removed(&mut self) {
+        self.provided
+            .iter_mut()
+            .for_each(|provided| *provided = false);
+    }
+
+    fn check(&self, buf: &ProvidedBuf) {
+        assert!(
+            buf.bgid == self.bgid && (buf.bid as usize) < self.count() && buf.len <= self.buf_len,
+            "the buffer belongs to another group"
+        );
+    }
+
+    fn buf_ptr(&self, bid: u16) -> *mut u8 {
+        unsafe { self.memory.as_ptr().add(bid as usize * self.buf_len) }
+    }
+}
+
+impl Drop for BufGroup {
+    fn drop(&mut self) {
+        if !self.provided.contains(&true) {
+            let len = self.count() * self.buf_len;
+            let memory = std::ptr::slice_from_raw_parts_mut(self.memory.as_ptr(), len);
+            unsafe { drop(Box::from_raw(memory)) };
+        }
+    }
+}
+
+impl ProvidedBuf {
+    /// The buffer id.
+    #[inline]
+    pub fn bid(&self) -> u16 {
+        self.bid
+    }
+
+    /// The number of bytes filled in.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// A registered ring of provided buffers of equal size, requires Linux 5.19.
+///
+/// A buffer the kernel picked for a request is resolved from its completion event
+/// with [BufRing::get], as a [BufRingBuf] guard that puts the buffer back into the ring
+/// when it is dropped, so a buffer is never lost from the ring.
+///
+/// If [BufRing::unregister] is not called, dropping the ring leaks its memory,
+/// because the kernel may still write to it.
+pub struct BufRing {
+    ring: ManuallyDrop<Mmap>,
+    memory: NonNull<u8>,
+    bgid: u16,
+    buf_len: usize,
+    mask: u16,
+    tail: Cell<u16>,
+    taken: Box<[Cell<bool>]>,
+}
+
+/// A buffer of a [BufRing] that the kernel has filled,
+/// it is put back into the ring when dropped.
+pub struct BufRingBuf<'a> {
+    ring: &'a BufRing,
+    bid: u16,
+    len: usize,
+}
+
+unsafe impl Send for BufRing {}
+
+impl BufRing {
+    /// Allocate and register a ring of `entries` buffers of `buf_len` bytes
+    /// for the buffer group `bgid`, all of them given to the kernel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `entries` is not a power of two, or `buf_len` does not fit in an `u32`.
+    pub fn new(
+        submitter: &Submitter<'_>,
+        bgid: u16,
+        entries: u16,
+        buf_len: usize,
+    ) -> io::Result<BufRing> {
+        assert!(entries.is_power_of_two(), "entries must be a power of two");
+        assert!(buf_len <= u32::MAX as usize, "buffer too large");
+
+        let ring = Mmap::new_anon(entries as usize * std::mem::size_of::<sys::io_uring_buf>())?;
+        let memory = vec![0u8; entries as usize * buf_len].into_boxed_slice();
+        let memory = unsafe { NonNull::new_unchecked(Box::into_raw(memory) as *mut u8) };
+
+        let buf_ring = BufRing {
+            ring: ManuallyDrop::new(ring),
+            memory,
+            bgid,
+            buf_len,
+            mask: entries - 1,
+            tail: Cell::new(0),
+            taken: (0..entries).map(|_| Cell::new(false)).collect(),
+        };
+
+        for bid in 0..entries {
+            buf_ring.push(bid);
+        }
+        buf_ring.publish();
+
+        let ring_addr = buf_ring.ring.as_mut_ptr() as u64;
+        match unsafe { submitter.register_buf_ring(ring_addr, entries, bgid) } {
+            Ok(()) => Ok(buf_ring),
+            Err(err) => {
+                // Not registered, so the memory can be freed.
+                unsafe { buf_ring.free_memory() };
+                Err(err)
+            }
+        }
+    }
+
+    /// The buffer group id, to set on requests with [opcode::Recv::buf_group] and the like.
+    #[inline]
+    pub fn bgid(&self) -> u16 {
+        self.bgid
+    }
+
+    /// The size of each buffer.
+    #[inline]
+    pub fn buf_len(&self) -> usize {
+        self.buf_len
+    }
+
+    /// Resolve the buffer the kernel picked for a request from its completion event,
+    /// returns `None` if the request used no buffer.
+    ///
+    /// The result of the event is taken as the number of bytes filled in,
+    /// up to the size of the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is already resolved, e.g. the same event is resolved twice.
+    pub fn get(&self, entry: &cqueue::Entry) -> Option<BufRingBuf<'_>> {
+        let bid = entry.buffer_id()?;
+        let taken = &self.taken[bid as usize];
+        assert!(!taken.replace(true), "the buffer is already resolved");
+
+        Some(BufRingBuf {
+            ring: self,
+            bid,
+            len: (entry.result().max(0) as usize).min(self.buf_len),
+        })
+    }
+
+    /// Unregister the ring and free its memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a buffer is still resolved, which can only be the case after
+    /// a [BufRingBuf] has been leaked.
+    pub fn unregister(self, submitter: &Submitter<'_>) -> io::Result<()> {
+        assert!(
+            self.taken.iter().all(|taken| !taken.get()),
+            "a buffer of the ring is resolved"
+        );
+
+        submitter.unregister_buf_ring(self.bgid)?;
+        unsafe { self.free_memory() };
+        Ok(())
+    }
+
+    fn bufs(&self) -> *mut sys::io_uring_buf {
+        self.ring.as_mut_ptr().cast()
+    }
+
+    fn push(&self, bid: u16) {
+        let tail = self.tail.get();
+
+        unsafe {
+            let buf = &mut *self.bufs().add((tail & self.mask) as usize);
+            buf.addr = self.buf_ptr(bid) as _;
+            buf.len = self.buf_len as _;
+            buf.bid = bid;
+        }
+
+        self.tail.set(tail.wrapping_add(1));
+    }
+
+    fn publish(&self) {
+        // The tail overlays the `resv` field of the first entry.
+        let tail = unsafe { &*(std::ptr::addr_of_mut!((*self.bufs()).resv) as *const AtomicU16) };
+        tail.store(self.tail.get(), Ordering::Release);
+    }
+
+    fn recycle(&self, bid: u16) {
+        self.taken[bid as usize].set(false);
+        self.push(bid);
+        self.publish();
+    }
+
+    fn buf_ptr(&self, bid: u16) -> *mut u8 {
+        unsafe { self.memory.as_ptr().add(bid as usize * self.buf_len) }
+    }
+
+    unsafe fn free_memory(mut self) {
+        let len = self.taken.len() * self.buf_len;
+        let memory = std::ptr::slice_from_raw_parts_mut(self.memory.as_ptr(), len);
+        drop(Box::from_raw(memory));
+        ManuallyDrop::drop(&mut self.ring);
+    }
+}
+
+impl BufRingBuf<'_> {
+    /// The buffer id.
+    #[inline]
+    pub fn bid(&self) -> u16 {
+        self.bid
+    }
+}
+
+impl Deref for BufRingBuf<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        let len = self.len.min(self.ring.buf_len);
+        unsafe { slice::from_raw_parts(self.ring.buf_ptr(self.bid), len) }
+    }
+}
+
+impl DerefMut for BufRingBuf<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let len = self.len.min(self.ring.buf_len);
+        unsafe { slice::from_raw_parts_mut(self.ring.buf_ptr(self.bid), len) }
+    }
+}
+
+impl Drop for BufRingBuf<'_> {
+    fn drop(&mut self) {
+        self.ring.recycle(self.bid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(res: i32, bid: u16) -> cqueue::Entry {
+        let cqe = sys::io_uring_cqe {
+            res,
+            flags: sys::IORING_CQE_F_BUFFER | (bid as u32) << sys::IORING_CQE_BUFFER_SHIFT,
+            ..Default::default()
+        };
+        cqe.into()
+    }
+
+    #[test]
+    fn test_group_take_clamps_len() {
+        let mut group = BufGroup::new(1, 4, 16);
+        group.provide();
+
+        let mut buf = group.take(&event(1 << 20, 3)).unwrap();
+        assert_eq!(buf.len(), 16);
+        assert_eq!(group.get(&buf).len(), 16);
+        assert_eq!(group.get_mut(&mut buf).len(), 16);
+
+        let buf = group.take(&event(-libc::ENOBUFS, 2)).unwrap();
+        assert!(buf.is_empty());
+        group.reprovide(buf);
+    }
+
+    #[test]
+    #[should_panic(expected = "the buffer belongs to another group")]
+    fn test_group_rejects_foreign_buf() {
+        let mut group = BufGroup::new(1, 4, 16);
+        let mut other = BufGroup::new(2, 4, 16);
+        group.provide();
+        other.provide();
+
+        let buf = other.take(&event(8, 0)).unwrap();
+        group.get(&buf);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_group_recycle() {
+        use crate::mock::{MockRing, Reply};
+        use crate::opcode::{types, OpCode};
+
+        let mut ring = MockRing::new(4);
+        let mut group = BufGroup::new(1, 2, 16);
+        ring.reply_opcode(OpCode::ProvideBuffers, Reply::new(0));
+
+        let recv = |user_data| {
+            opcode::Recv::new(types::Fd(3), std::ptr::null_mut(), 16)
+                .buf_group(1)
+                .build()
+                .flags(squeue::Flags::BUFFER_SELECT)
+                .user_data(user_data)
+        };
+        let submit = |ring: &mut MockRing, entry| {
+            unsafe { ring.submission().available().push(entry).unwrap() };
+            ring.submit().unwrap();
+            ring.completion().available().next()
+        };
+
+        assert_eq!(submit(&mut ring, group.provide()).unwrap().result(), 0);
+        ring.reply(1, Reply::new(5).buffer(0))
+            .reply(2, Reply::new(3).buffer(1))
+            .reply(3, Reply::new(-libc::ENOBUFS));
+
+        let first = group.take(&submit(&mut ring, recv(1)).unwrap()).unwrap();
+        let second = group.take(&submit(&mut ring, recv(2)).unwrap()).unwrap();
+        assert_eq!((first.bid(), first.len()), (0, 5));
+        assert_eq!((second.bid(), second.len()), (1, 3));
+
+        // Without a free buffer, the request fails and takes none.
+        let cqe = submit(&mut ring, recv(3)).unwrap();
+        assert_eq!(cqe.result(), -libc::ENOBUFS);
+        assert!(group.take(&cqe).is_none());
+
+        let entry = group.reprovide(first);
+        assert_eq!(submit(&mut ring, entry).unwrap().result(), 0);
+        ring.reply(4, Reply::new(7).buffer(0));
+        let again = group.take(&submit(&mut ring, recv(4)).unwrap()).unwrap();
+        assert_eq!((again.bid(), again.len()), (0, 7));
+
+        for buf in [second, again] {
+            submit(&mut ring, group.reprovide(buf));
+        }
+    }
+
+    #[test]
+    fn test_ring_get_clamps_len() {
+        let ring = crate::IoUring::new(4).unwrap();
+        let submitter = ring.submitter();
+        let buf_ring = BufRing::new(&submitter, 1, 4, 16).unwrap();
+
+        let mut buf = buf_ring.get(&event(1 << 20, 3)).unwrap();
+        assert_eq!(buf.len(), 16);
+        assert_eq!(buf.as_mut().len(), 16);
+        drop(buf);
+
+        buf_ring.unregister(&submitter).unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+//! Cancellation of groups of requests.
+
+use std::collections::HashMap;
+
+use crate::cqueue;
+use crate::opcode::{self, sealed, types};
+use crate::squeue;
+
+/// The requests of a task or a connection, to cancel them all at once.
+///
+/// Each request is recorded by its `user_data`, and optionally by the file it operates on.
+/// [CancelScope::close] returns the cancel requests to submit:
+/// one per file, which cancels every request issued on the file, including requests
+/// of other scopes, and one per remaining `user_data`. The completion events are then
+/// passed to [CancelScope::complete], until [CancelScope::is_terminated] confirms that
+/// every request of the scope has completed, so that the memory they use can be reused.
+pub struct CancelScope {
+    key: u64,
+    ops: HashMap<u64, Pending>,
+    cancels: usize,
+    closed: bool,
+}
+
+struct Pending {
+    count: usize,
+    fd: Option<sealed::Target>,
+}
+
+impl CancelScope {
+    /// Create a scope whose cancel requests have `key` as `user_data`,
+    /// which must not be used by other requests.
+    pub fn new(key: u64) -> CancelScope {
+        CancelScope {
+            key,
+            ops: HashMap::new(),
+            cancels: 0,
+            closed: false,
+        }
+    }
+
+    /// The `user_data` of the cancel requests of the scope.
+    #[inline]
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    /// The number of requests in flight.
+    pub fn len(&self) -> usize {
+        self.ops.values().map(|op| op.count).sum()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Record a request, it is canceled by its `user_data`.
+    ///
+    /// Requests can share a `user_data`, they are all canceled together.
+    pub fn track(&mut self, user_data: u64) {
+        self.insert(user_data, None);
+    }
+
+    /// Record a request issued on a file, it is canceled with the other requests
+    /// issued on the file.
+    pub fn track_fd(&mut self, user_data: u64, fd: impl sealed::UseFixed) {
+        self.insert(user_data, Some(sealed::UseFixed::into(fd)));
+    }
+
+    fn insert(&mut self, user_data: u64, fd: Option<sealed::Target>) {
+        assert_ne!(user_data, self.key, "the key of the scope is reserved");
+
+        let op = self
+            .ops
+            .entry(user_data)
+            .or_insert(Pending { count: 0, fd: None });
+        op.count += 1;
+        op.fd = op.fd.or(fd);
+    }
+
+    /// Record the completion event of a request,
+    /// returns `false` if it belongs to neither a request nor a cancel request of the scope.
+    ///
+    /// A multishot request is in flight until its final event.
+    pub fn complete(&mut self, entry: &cqueue::Entry) -> bool {
+        let user_data = entry.user_data();
+
+        if user_data == self.key {
+            // The result is the number of canceled requests, or the reason,
+            // e.g. every request has already completed, which is fine either way.
+            self.cancels = self.cancels.saturating_sub(1);
+            return true;
+        }
+
+        let op = match self.ops.get_mut(&user_data) {
+            Some(op) => op,
+            None => return false,
+        };
+
+        if !entry.is_more() {
+            op.count -= 1;
+            if op.count == 0 {
+                self.ops.remove(&user_data);
+            }
+        }
+
+        true
+    }
+
+    /// Close the scope, and return the cancel requests of its requests in flight.
+    ///
+    /// A request may still complete successfully if it was already done.
+    /// Closing the scope again cancels the requests still in flight,
+    /// e.g. the requests recorded after it was closed first.
+    pub fn close(&mut self) -> Vec<squeue::Entry> {
+        let mut fds = Vec::new();
+        let mut entries = Vec::new();
+
+        for (&user_data, op) in self.ops.iter() {
+            match op.fd {
+                Some(fd) if !fds.contains(&fd) => fds.push(fd),
+                Some(_) => (),
+                None => entries.push(
+                    opcode::AsyncCancel::new(user_data)
+                        .flags(types::AsyncCancelFlags::ALL)
+                        .build(),
+                ),
+            }
+        }
+
+        entries.extend(fds.into_iter().map(|fd| {
+            let cancel = match fd {
+                sealed::Target::Fd(fd) => opcode::AsyncCancelFd::new(types::Fd(fd)),
+                sealed::Target::Fixed(index) => opcode::AsyncCancelFd::new(types::Fixed(index)),
+            };
+            cancel.flags(types::AsyncCancelFlags::ALL).build()
+        }));
+
+        let entries: Vec<_> = entries
+            .into_iter()
+            .map(|entry| entry.user_data(self.key))
+            .collect();
+
+        self.cancels += entries.len();
+        self.closed = true;
+        entries
+    }
+
+    /// Whether the scope is closed, and all of its requests and cancel requests completed.
+    pub fn is_terminated(&self) -> bool {
+        self.closed && self.ops.is_empty() && self.cancels == 0
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::{MockRing, Reply};
+    use crate::opcode::OpCode;
+    use std::os::unix::io::RawFd;
+
+    const KEY: u64 = 0x100;
+
+    fn push(ring: &mut MockRing, entries: &[squeue::Entry]) {
+        unsafe {
+            ring.submission()
+                .available()
+                .push_multiple(entries)
+                .unwrap()
+        };
+        ring.submit().unwrap();
+    }
+
+    fn complete_all(ring: &mut MockRing, scope: &mut CancelScope) {
+        for cqe in ring.completion().available() {
+            assert!(scope.complete(&cqe));
+        }
+    }
+
+    #[test]
+    fn test_close() {
+        let mut ring = MockRing::new(8);
+        let mut scope = CancelScope::new(KEY);
+
+        const FD: RawFd = 5;
+        push(
+            &mut ring,
+            &[
+                opcode::Nop::new().build().user_data(1),
+                opcode::Read::new(types::Fd(FD), std::ptr::null_mut(), 0)
+                    .build()
+                    .user_data(2),
+                opcode::Write::new(types::Fd(FD), std::ptr::null(), 0)
+                    .build()
+                    .user_data(3),
+            ],
+        );
+        scope.track(1);
+        scope.track_fd(2, types::Fd(FD));
+        scope.track_fd(3, types::Fd(FD));
+        assert_eq!(scope.len(), 3);
+
+        // One cancel request by `user_data`, and one for both requests on the file.
+        let cancels = scope.close();
+        assert_eq!(cancels.len(), 2);
+        assert!(cancels.iter().all(|entry| entry.get_user_data() == KEY));
+        assert!(!scope.is_terminated());
+
+        ring.reply_opcode(OpCode::AsyncCancel, Reply::new(1));
+        push(&mut ring, &cancels);
+        for user_data in 1..4 {
+            assert!(ring.complete(user_data, Reply::new(-libc::ECANCELED)));
+        }
+        complete_all(&mut ring, &mut scope);
+        assert!(scope.is_empty());
+        assert!(scope.is_terminated());
+    }
+
+    #[test]
+    fn test_multishot_and_late_track() {
+        let mut ring = MockRing::new(8);
+        let mut scope = CancelScope::new(KEY);
+
+        push(&mut ring, &[opcode::Nop::new().build().user_data(1)]);
+        scope.track(1);
+        assert!(ring.complete(1, Reply::new(0).more()));
+        complete_all(&mut ring, &mut scope);
+        assert_eq!(scope.len(), 1);
+
+        let cancels = scope.close();
+        ring.reply_opcode(OpCode::AsyncCancel, Reply::new(1));
+        push(&mut ring, &cancels);
+
+        // A request recorded after the scope was closed needs another close.
+        push(&mut ring, &[opcode::Nop::new().build().user_data(2)]);
+        scope.track(2);
+        assert!(ring.complete(1, Reply::new(-libc::ECANCELED)));
+        complete_all(&mut ring, &mut scope);
+        assert!(!scope.is_terminated());
+
+        let cancels = scope.close();
+        assert_eq!(cancels.len(), 1);
+        push(&mut ring, &cancels);
+        assert!(ring.complete(2, Reply::new(-libc::ECANCELED)));
+        complete_all(&mut ring, &mut scope);
+        assert!(scope.is_terminated());
+
+        let other = cqueue::Entry::from(crate::sys::io_uring_cqe {
+            user_data: 3,
+            res: 0,
+            flags: 0,
+        });
+        assert!(!scope.complete(&other));
+    }
+}
@@ -1,6 +1,5 @@
-use std::sync::atomic;
-
 use crate::cqueue::{self, Entry};
+use crate::sync::atomic;
 
 pub struct CompletionQueue<'a> {
     pub(crate) queue: &'a cqueue::CompletionQueue,
@@ -49,7 +48,7 @@ impl CompletionQueue<'_> {
                     return None;
                 }
 
-                let entry = *self.queue.cqes.add((head & self.ring_mask) as usize);
+                let entry = *self.queue.cqe(head & self.ring_mask);
 
                 match (*self.queue.head).compare_exchange_weak(
                     head,
@@ -57,10 +56,68 @@ impl CompletionQueue<'_> {
                     atomic::Ordering::Release,
                     atomic::Ordering::Relaxed,
                 ) {
-                    Ok(_) => return Some(Entry(entry)),
+                    Ok(_) => {
+                        #[cfg(feature = "tracing")]
+                        crate::trace::reap(&entry);
+                        #[cfg(feature = "stats")]
+                        self.queue.stats.reap(&entry);
+
+                        return Some(Entry(entry));
+                    }
                     Err(_) => continue,
                 }
             }
         }
     }
+
+    /// Pop all ready entries into `entries`, and return the number of entries popped.
+    ///
+    /// The whole range is claimed with a single update of the head,
+    /// which reduces contention compared to calling [pop](Self::pop) repeatedly.
+    pub fn pop_batch(&self, entries: &mut Vec<Entry>) -> usize {
+        let start = entries.len();
+
+        unsafe {
+            loop {
+                let head = (*self.queue.head).load(atomic::Ordering::Acquire);
+                let tail = (*self.queue.tail).load(atomic::Ordering::Acquire);
+                let n = tail.wrapping_sub(head);
+
+                if n == 0 {
+                    return 0;
+                }
+                if n > self.ring_entries {
+                    // The head moved on after we loaded it.
+                    continue;
+                }
+
+                // The entries may be overwritten as soon as another thread pops them,
+                // so they only become part of `entries` if the head is still ours.
+                entries.reserve(n as usize);
+                self.queue.copy_to(head, n, entries.as_mut_ptr().add(start));
+
+                if (*self.queue.head)
+                    .compare_exchange_weak(
+                        head,
+                        tail,
+                        atomic::Ordering::Release,
+                        atomic::Ordering::Relaxed,
+                    )
+                    .is_ok()
+                {
+                    entries.set_len(start + n as usize);
+
+                    #[cfg(any(feature = "tracing", feature = "stats"))]
+                    for Entry(entry) in &entries[start..] {
+                        #[cfg(feature = "tracing")]
+                        crate::trace::reap(entry);
+                        #[cfg(feature = "stats")]
+                        self.queue.stats.reap(entry);
+                    }
+
+                    return n as usize;
+                }
+            }
+        }
+    }
 }
@@ -1,18 +1,29 @@
 //! Concurrent IoUring.
 
 mod cqueue;
+mod notifier;
 mod squeue;
 
+#[cfg(all(test, loom))]
+mod tests;
+
 use std::io;
+#[cfg(feature = "unstable")]
+use std::time::Duration;
 
 pub use cqueue::CompletionQueue;
-use parking_lot::Mutex;
+pub use notifier::Notifier;
 pub use squeue::SubmissionQueue;
 
+use crate::eventfd::EventFd;
+use crate::sync::atomic::AtomicU32;
+use crate::{SubmitError, Submitter};
+
 /// Concurrent IoUring instance
 pub struct IoUring {
     ring: crate::IoUring,
-    push_lock: Mutex<()>,
+    reserved: AtomicU32,
+    ready: Box<[AtomicU32]>,
 }
 
 unsafe impl Send for IoUring {}
@@ -20,9 +31,18 @@ unsafe impl Sync for IoUring {}
 
 impl IoUring {
     pub(crate) fn new(ring: crate::IoUring) -> IoUring {
+        let tail = ring.sq.tail();
+        let mask = ring.sq.ring_mask();
+
+        // Each slot starts out empty, that is holding the first index that maps to it.
+        let ready = (0..ring.sq.capacity() as u32)
+            .map(|i| AtomicU32::new(tail.wrapping_add(i.wrapping_sub(tail) & mask)))
+            .collect();
+
         IoUring {
             ring,
-            push_lock: Mutex::new(()),
+            reserved: AtomicU32::new(tail),
+            ready,
         }
     }
 
@@ -42,24 +62,63 @@ impl IoUring {
         self.ring.enter(to_submit, min_complete, flag, sig)
     }
 
+    /// Get a [Submitter] for the ring.
+    ///
+    /// It can be copied to every thread that needs to submit, wait or register resources.
+    #[inline]
+    pub fn submitter(&self) -> Submitter<'_> {
+        self.ring.submitter()
+    }
+
     /// Initiate asynchronous I/O.
+    ///
+    /// Like [Submitter::submit](crate::Submitter::submit), this wakes up a sleeping SQPOLL thread
+    /// and runs pending task work when the kernel asks for it.
     #[inline]
-    pub fn submit(&self) -> io::Result<usize> {
+    pub fn submit(&self) -> Result<usize, SubmitError> {
         self.ring.submit()
     }
 
     /// Initiate and/or complete asynchronous I/O
     #[inline]
-    pub fn submit_and_wait(&self, want: usize) -> io::Result<usize> {
+    pub fn submit_and_wait(&self, want: usize) -> Result<usize, SubmitError> {
         self.ring.submit_and_wait(want)
     }
 
+    /// Block until `want` completion events are available, without submitting any entry.
+    ///
+    /// Reaper threads can use this instead of spinning on [CompletionQueue::pop].
+    #[inline]
+    pub fn wait(&self, want: usize) -> Result<usize, SubmitError> {
+        self.ring.wait(want)
+    }
+
+    /// Like [IoUring::wait], but wait at most `timeout`.
+    ///
+    /// See [Submitter::wait_timeout](crate::Submitter::wait_timeout).
+    #[cfg(feature = "unstable")]
+    #[inline]
+    pub fn wait_timeout(&self, want: usize, timeout: Duration) -> Result<usize, SubmitError> {
+        self.ring.wait_timeout(want, timeout)
+    }
+
+    /// Create an eventfd and register it with the ring,
+    /// so that threads can block until completion events are posted.
+    ///
+    /// A ring supports only one registered eventfd, so calling this again fails with `EBUSY`.
+    pub fn notifier(&self) -> io::Result<Notifier> {
+        let eventfd = EventFd::new()?;
+        eventfd.register(&self.ring.submitter())?;
+        Ok(Notifier::new(eventfd))
+    }
+
     /// Get submission queue
     pub fn submission(&self) -> SubmissionQueue<'_> {
         unsafe {
             SubmissionQueue {
                 queue: &self.ring.sq,
-                push_lock: &self.push_lock,
+                reserved: &self.reserved,
+                ready: &self.ready,
                 ring_mask: self.ring.sq.ring_mask.read(),
                 ring_entries: self.ring.sq.ring_entries.read(),
             }
@@ -77,8 +136,19 @@ impl IoUring {
         }
     }
 
+    /// Get a snapshot of the counters of the ring.
+    #[cfg(feature = "stats")]
+    #[inline]
+    pub fn stats(&self) -> crate::Stats {
+        self.ring.stats()
+    }
+
     /// Get original IoUring instance
     pub fn into_inner(self) -> crate::IoUring {
-        self.ring
+        let mut ring = self.ring;
+
+        // The tail was advanced by the concurrent queue, past the head the ring cached.
+        ring.sq.sync_head();
+        ring
     }
 }
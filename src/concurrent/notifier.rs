@@ -0,0 +1,35 @@
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use crate::eventfd::EventFd;
+
+/// An eventfd registered with a concurrent ring, see [IoUring::notifier](super::IoUring::notifier).
+///
+/// It becomes readable when completion events are posted,
+/// so threads can block on it with [Notifier::wait] or register it with `epoll`.
+pub struct Notifier {
+    eventfd: EventFd,
+}
+
+impl Notifier {
+    pub(crate) fn new(eventfd: EventFd) -> Notifier {
+        Notifier { eventfd }
+    }
+
+    /// Block until completion events have been posted since the last wait,
+    /// and return the number of notifications.
+    ///
+    /// A notification does not guarantee that an entry is still available,
+    /// another thread may have popped it first.
+    #[inline]
+    pub fn wait(&self) -> io::Result<u64> {
+        self.eventfd.wait()
+    }
+}
+
+impl AsRawFd for Notifier {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.eventfd.as_raw_fd()
+    }
+}
@@ -1,13 +1,36 @@
-use std::sync::atomic;
-
-use crate::squeue::{self, Entry};
-use crate::util::unsync_load;
-
-use parking_lot::Mutex;
+//! Lock-free multi-producer pushing.
+//!
+//! A push first reserves a contiguous range of slots by advancing `reserved` with a CAS,
+//! which only fails if another producer reserved in the meantime.
+//! The entries are then written into the reserved slots,
+//! and the end index of the range is stored in `ready` at the slot of its first entry.
+//!
+//! Ranges can be written in any order, but they must be published in order.
+//! So after marking its range, each producer publishes every consecutive ready range
+//! starting at the kernel tail: it takes a range by advancing the tail from the start index
+//! to the end index with a CAS, which releases the whole range to the kernel at once.
+//! A producer whose range is not at the tail leaves it to the producer in front,
+//! so no producer ever waits for another, and a range is always published as a whole.
+//!
+//! The `ready` values are never reset, so a slot may still hold the end index of a range
+//! of an earlier lap, and a producer that has been preempted with an old tail may read
+//! the value a later lap left. Such values are told apart by the tail CAS:
+//! it only succeeds if the tail is still at the start of the range, in which case
+//! the slot holds the end index stored for this very range.
+//! A value is only taken for a range if it is at most `ring_entries` ahead of the tail,
+//! which rules out the values of earlier laps before the CAS is attempted.
+//!
+//! Every publishing attempt starts with a `SeqCst` fence, which orders it after the
+//! previous `ready` store or tail update of the same producer. So of two producers finishing
+//! at the same time, at least one sees the range of the other and publishes it.
+
+use crate::squeue::{self, Entry, PushError};
+use crate::sync::atomic::{self, AtomicU32};
 
 pub struct SubmissionQueue<'a> {
     pub(crate) queue: &'a squeue::SubmissionQueue,
-    pub(crate) push_lock: &'a Mutex<()>,
+    pub(crate) reserved: &'a AtomicU32,
+    pub(crate) ready: &'a [AtomicU32],
     pub(crate) ring_mask: u32,
     pub(crate) ring_entries: u32,
 }
@@ -18,6 +41,14 @@ impl SubmissionQueue<'_> {
         self.queue.need_wakeup()
     }
 
+    /// See [SubmissionQueue::taskrun](crate::squeue::SubmissionQueue::taskrun).
+    #[cfg(feature = "unstable")]
+    #[inline]
+    pub fn taskrun(&self) -> bool {
+        self.queue.taskrun()
+    }
+
+    /// See [SubmissionQueue::dropped](crate::squeue::SubmissionQueue::dropped).
     #[inline]
     pub fn dropped(&self) -> u32 {
         self.queue.dropped()
@@ -48,27 +79,151 @@ impl SubmissionQueue<'_> {
         self.len() == self.capacity()
     }
 
+    /// The number of entries that can still be pushed before the queue is full.
+    ///
+    /// Other threads may push concurrently, so this is only a hint.
+    #[inline]
+    pub fn space_left(&self) -> usize {
+        self.capacity().saturating_sub(self.len())
+    }
+
     /// Attempts to push an [Entry] into the queue.
-    /// If the queue is full, the element is returned back as an error.
+    /// If the queue is full, the element is returned back in [PushError::Full].
     ///
     /// # Safety
     ///
     /// Developers must ensure that parameters of the [Entry] (such as buffer) are valid,
     /// otherwise it may cause memory problems.
-    pub unsafe fn push(&self, Entry(entry): Entry) -> Result<(), Entry> {
-        let _lock = self.push_lock.lock();
+    pub unsafe fn push(&self, Entry(entry): Entry) -> Result<(), PushError<Entry>> {
+        let start = match self.reserve(1) {
+            Some(start) => start,
+            None => {
+                #[cfg(feature = "stats")]
+                self.queue.stats.full();
+                return Err(PushError::Full(Entry(entry)));
+            }
+        };
+
+        *self.queue.sqe(start & self.ring_mask) = entry;
 
-        let head = (*self.queue.head).load(atomic::Ordering::Acquire);
-        let tail = unsync_load(self.queue.tail);
+        #[cfg(feature = "tracing")]
+        crate::trace::push(&entry);
+        #[cfg(feature = "stats")]
+        self.queue.stats.push();
 
-        if tail.wrapping_sub(head) == self.ring_entries {
-            return Err(Entry(entry));
+        self.publish(start, 1);
+
+        Ok(())
+    }
+
+    /// Attempts to push several [Entry] into the queue.
+    /// If the queue does not have enough space for all of them, nothing is pushed.
+    ///
+    /// The entries are reserved as one contiguous range and published with a single tail update,
+    /// so entries pushed by other threads never interleave with them,
+    /// which makes this suitable for linked entries.
+    ///
+    /// # Safety
+    ///
+    /// Developers must ensure that parameters of all the [Entry] (such as buffer) are valid,
+    /// otherwise it may cause memory problems.
+    pub unsafe fn push_multiple(&self, entries: &[Entry]) -> Result<(), PushError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        if entries.len() > self.ring_entries as usize {
+            #[cfg(feature = "stats")]
+            self.queue.stats.full();
+            return Err(PushError::Full(()));
         }
 
-        *self.queue.sqes.add((tail & self.ring_mask) as usize) = entry;
+        let n = entries.len() as u32;
+        let start = match self.reserve(n) {
+            Some(start) => start,
+            None => {
+                #[cfg(feature = "stats")]
+                self.queue.stats.full();
+                return Err(PushError::Full(()));
+            }
+        };
+
+        for (i, Entry(entry)) in entries.iter().enumerate() {
+            *self
+                .queue
+                .sqe(start.wrapping_add(i as u32) & self.ring_mask) = *entry;
+
+            #[cfg(feature = "tracing")]
+            crate::trace::push(entry);
+            #[cfg(feature = "stats")]
+            self.queue.stats.push();
+        }
 
-        (*self.queue.tail).store(tail.wrapping_add(1), atomic::Ordering::Release);
+        self.publish(start, n);
 
         Ok(())
     }
+
+    /// Reserve `n` slots, returns the index of the first one.
+    fn reserve(&self, n: u32) -> Option<u32> {
+        // `reserved` is acquired, so the head is at least the one the last reservation saw,
+        // otherwise a stale head could be more than `ring_entries` behind.
+        let mut start = self.reserved.load(atomic::Ordering::Acquire);
+
+        loop {
+            // Reserved slots are at most `ring_entries` ahead of the head,
+            // so a reserved slot has always been published and consumed by the kernel.
+            let head = unsafe { (*self.queue.head).load(atomic::Ordering::Acquire) };
+            if self.ring_entries - start.wrapping_sub(head) < n {
+                return None;
+            }
+
+            match self.reserved.compare_exchange_weak(
+                start,
+                start.wrapping_add(n),
+                atomic::Ordering::AcqRel,
+                atomic::Ordering::Acquire,
+            ) {
+                Ok(_) => return Some(start),
+                Err(current) => start = current,
+            }
+        }
+    }
+
+    /// Get the `ready` slot of a ring index.
+    #[inline]
+    fn ready(&self, index: u32) -> &AtomicU32 {
+        // `ready` has a slot per ring entry, so a masked index is always in bounds.
+        unsafe { self.ready.get_unchecked((index & self.ring_mask) as usize) }
+    }
+
+    /// Mark the range of `n` slots at `start` as written,
+    /// and publish all ready ranges at the tail.
+    fn publish(&self, start: u32, n: u32) {
+        let end = start.wrapping_add(n);
+        self.ready(start).store(end, atomic::Ordering::Release);
+
+        // Every iteration either publishes a range, or fails because another producer did,
+        // so this never waits for a producer that is still writing its entries.
+        loop {
+            atomic::fence(atomic::Ordering::SeqCst);
+
+            let tail = unsafe { &*self.queue.tail };
+            let start = tail.load(atomic::Ordering::Acquire);
+
+            let end = self.ready(start).load(atomic::Ordering::Acquire);
+            let len = end.wrapping_sub(start);
+            if len == 0 || len > self.ring_entries {
+                return;
+            }
+
+            // A failure means the tail moved, the range was published by another producer,
+            // or `end` was a stale value left in the slot.
+            let _ = tail.compare_exchange(
+                start,
+                end,
+                atomic::Ordering::AcqRel,
+                atomic::Ordering::Relaxed,
+            );
+        }
+    }
 }
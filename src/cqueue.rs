@@ -1,10 +1,23 @@
 //! Completion Queue
 
-use std::sync::atomic;
+use std::cmp;
+use std::fmt;
+use std::io;
+use std::ptr;
+use std::slice;
+#[cfg(feature = "stats")]
+use std::sync::Arc;
 
+use crate::squeue::UserData;
+#[cfg(feature = "stats")]
+use crate::stats::CompletionCounters;
+use crate::sync::atomic;
 use crate::sys;
 use crate::util::{unsync_load, Mmap};
 
+/// The raw layout of a completion entry, see [CompletionQueue::raw_parts].
+pub use crate::sys::io_uring_cqe;
+
 pub struct CompletionQueue {
     pub(crate) head: *const atomic::AtomicU32,
     pub(crate) tail: *const atomic::AtomicU32,
@@ -14,16 +27,41 @@ pub struct CompletionQueue {
     overflow: *const atomic::AtomicU32,
 
     pub(crate) cqes: *const sys::io_uring_cqe,
+    cqe_shift: u32,
 
-    #[allow(dead_code)]
     flags: *const atomic::AtomicU32,
+
+    #[cfg(feature = "stats")]
+    pub(crate) stats: Arc<CompletionCounters>,
+}
+
+/// The raw pointers of a completion queue, see [CompletionQueue::raw_parts].
+///
+/// They are named after the fields of `struct io_uring_cq` of liburing.
+#[derive(Debug, Clone, Copy)]
+pub struct RawParts {
+    pub khead: *mut u32,
+    pub ktail: *mut u32,
+    pub kring_mask: *mut u32,
+    pub kring_entries: *mut u32,
+    pub kflags: *mut u32,
+    pub koverflow: *mut u32,
+    /// The entries, which are 32 bytes each for rings set up with `IORING_SETUP_CQE32`.
+    pub cqes: *mut io_uring_cqe,
 }
 
 /// Completion Entry
 #[repr(transparent)]
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Entry(pub(crate) sys::io_uring_cqe);
 
+/// A 32-byte Completion Entry,
+/// for rings created with [Builder::setup_cqe32](crate::Builder::setup_cqe32).
+#[cfg(feature = "unstable")]
+#[repr(C)]
+#[derive(Clone, Default)]
+pub struct Entry32(pub(crate) Entry, pub(crate) [u64; 2]);
+
 pub struct AvailableQueue<'a> {
     head: u32,
     tail: u32,
@@ -52,12 +90,110 @@ impl CompletionQueue {
             ring_entries,
             overflow,
             cqes,
+            cqe_shift: if p.flags & sys::IORING_SETUP_CQE32 != 0 {
+                1
+            } else {
+                0
+            },
             flags,
+            #[cfg(feature = "stats")]
+            stats: Default::default(),
+        }
+    }
+
+    /// Build a queue over an in-memory ring, for the loom tests and the mock ring.
+    #[cfg(any(all(test, loom), feature = "mock"))]
+    pub(crate) unsafe fn from_raw_parts(
+        head: *const atomic::AtomicU32,
+        tail: *const atomic::AtomicU32,
+        ring_mask: *const u32,
+        ring_entries: *const u32,
+        overflow: *const atomic::AtomicU32,
+        cqes: *const sys::io_uring_cqe,
+        flags: *const atomic::AtomicU32,
+    ) -> CompletionQueue {
+        CompletionQueue {
+            head,
+            tail,
+            ring_mask,
+            ring_entries,
+            overflow,
+            cqes,
+            cqe_shift: 0,
+            flags,
+            #[cfg(feature = "stats")]
+            stats: Default::default(),
+        }
+    }
+
+    /// Create another handle to the same queue.
+    ///
+    /// # Safety
+    ///
+    /// Only one of the handles may be used to consume entries,
+    /// and the handles must not outlive the ring.
+    pub(crate) unsafe fn alias(&self) -> CompletionQueue {
+        CompletionQueue {
+            head: self.head,
+            tail: self.tail,
+            ring_mask: self.ring_mask,
+            ring_entries: self.ring_entries,
+            overflow: self.overflow,
+            cqes: self.cqes,
+            cqe_shift: self.cqe_shift,
+            flags: self.flags,
+            #[cfg(feature = "stats")]
+            stats: self.stats.clone(),
+        }
+    }
+
+    /// Get the entry at a masked ring index, taking the entry size into account.
+    #[inline]
+    pub(crate) unsafe fn cqe(&self, index: u32) -> *const sys::io_uring_cqe {
+        debug_assert!(index <= *self.ring_mask, "unmasked ring index");
+        self.cqes.add((index as usize) << self.cqe_shift)
+    }
+
+    /// Copy `n` entries starting at the ring index `head` to `dst`.
+    ///
+    /// The entries are contiguous up to the end of the ring,
+    /// so they are copied with at most two copies, unless they are 32 bytes.
+    ///
+    /// # Safety
+    ///
+    /// The `n` entries must be ready, and `dst` must be valid for writing them.
+    pub(crate) unsafe fn copy_to(&self, head: u32, n: u32, dst: *mut Entry) {
+        let mask = self.ring_mask.read();
+
+        if self.cqe_shift != 0 {
+            for i in 0..n {
+                let entry = self.cqe(head.wrapping_add(i) & mask);
+                dst.add(i as usize).write(Entry(*entry));
+            }
+            return;
         }
+
+        let start = head & mask;
+        let first = cmp::min(n, mask + 1 - start);
+        let cqes = self.cqes as *const Entry;
+
+        ptr::copy_nonoverlapping(cqes.add(start as usize), dst, first as usize);
+        ptr::copy_nonoverlapping(cqes, dst.add(first as usize), (n - first) as usize);
+    }
+
+    /// Whether the entries of this queue are 32 bytes,
+    /// so that [AvailableQueue::next32] can be used.
+    #[cfg(feature = "unstable")]
+    #[inline]
+    pub fn is_cqe32(&self) -> bool {
+        self.cqe_shift != 0
     }
 
     /// If queue is full, the new event maybe dropped.
     /// This value records number of dropped events.
+    ///
+    /// With `IORING_FEAT_NODROP` events are not dropped but kept in the kernel instead,
+    /// see [Submitter::flush_overflow](crate::Submitter::flush_overflow).
     pub fn overflow(&self) -> u32 {
         unsafe { (*self.overflow).load(atomic::Ordering::Acquire) }
     }
@@ -74,6 +210,42 @@ impl CompletionQueue {
         unsafe { self.ring_entries.read() as usize }
     }
 
+    /// The mask applied to the head and tail to get an index into the ring.
+    #[inline]
+    pub fn ring_mask(&self) -> u32 {
+        unsafe { self.ring_mask.read() }
+    }
+
+    /// A snapshot of the head, which advances as entries are consumed.
+    #[inline]
+    pub fn head(&self) -> u32 {
+        unsafe { unsync_load(self.head) }
+    }
+
+    /// A snapshot of the tail, which the kernel advances as it posts entries.
+    #[inline]
+    pub fn tail(&self) -> u32 {
+        unsafe { (*self.tail).load(atomic::Ordering::Acquire) }
+    }
+
+    /// The pointers of the queue into the memory shared with the kernel,
+    /// so that it can be operated on by other code, such as C code using liburing.
+    ///
+    /// Entries consumed through an [AvailableQueue] are only released to the head
+    /// once it is synced or dropped, so the other code may only consume entries
+    /// while no [AvailableQueue] of this queue is alive.
+    pub fn raw_parts(&self) -> RawParts {
+        RawParts {
+            khead: self.head as *mut u32,
+            ktail: self.tail as *mut u32,
+            kring_mask: self.ring_mask as *mut u32,
+            kring_entries: self.ring_entries as *mut u32,
+            kflags: self.flags as *mut u32,
+            koverflow: self.overflow as *mut u32,
+            cqes: self.cqes as *mut sys::io_uring_cqe,
+        }
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         unsafe {
@@ -95,6 +267,20 @@ impl CompletionQueue {
     }
 
     /// Get currently available completion queue
+    ///
+    /// The returned [AvailableQueue] is an iterator over the ready entries,
+    /// the consumed entries are released to the kernel when it is synced or dropped.
+    ///
+    /// ```no_run
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut ring = io_uring::IoUring::new(8)?;
+    ///
+    /// for cqe in ring.completion().available() {
+    ///     println!("{} {}", cqe.user_data(), cqe.result());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn available(&mut self) -> AvailableQueue<'_> {
         unsafe {
             AvailableQueue {
@@ -108,15 +294,44 @@ impl CompletionQueue {
     }
 }
 
+impl<'a> IntoIterator for &'a mut CompletionQueue {
+    type Item = Entry;
+    type IntoIter = AvailableQueue<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.available()
+    }
+}
+
 impl AvailableQueue<'_> {
     /// Sync queue
     pub fn sync(&mut self) {
+        #[cfg(any(feature = "tracing", feature = "stats"))]
+        self.record_reap();
+
         unsafe {
             (*self.queue.head).store(self.head, atomic::Ordering::Release);
             self.tail = (*self.queue.tail).load(atomic::Ordering::Acquire);
         }
     }
 
+    /// Trace and count the entries consumed since the head was last published.
+    #[cfg(any(feature = "tracing", feature = "stats"))]
+    fn record_reap(&self) {
+        unsafe {
+            let mut head = unsync_load(self.queue.head);
+            while head != self.head {
+                let cqe = &*self.queue.cqe(head & self.ring_mask);
+                #[cfg(feature = "tracing")]
+                crate::trace::reap(cqe);
+                #[cfg(feature = "stats")]
+                self.queue.stats.reap(cqe);
+                head = head.wrapping_add(1);
+            }
+        }
+    }
+
     #[inline]
     pub fn capacity(&self) -> usize {
         self.ring_entries as usize
@@ -126,6 +341,113 @@ impl AvailableQueue<'_> {
     pub fn is_full(&self) -> bool {
         self.len() == self.capacity()
     }
+
+    /// Copy as many ready entries as fit into `entries`,
+    /// and return the number of entries copied.
+    ///
+    /// The head is only advanced once, when the queue is synced or dropped.
+    pub fn fill(&mut self, entries: &mut [Entry]) -> usize {
+        let n = cmp::min(self.len(), entries.len());
+
+        unsafe {
+            self.queue
+                .copy_to(self.head, n as u32, entries.as_mut_ptr());
+        }
+
+        self.head = self.head.wrapping_add(n as u32);
+        n
+    }
+
+    /// Get the next ready entry without consuming it.
+    #[inline]
+    pub fn peek(&self) -> Option<&Entry> {
+        if self.head != self.tail {
+            unsafe {
+                let entry = self.queue.cqe(self.head & self.ring_mask);
+                Some(&*(entry as *const Entry))
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Get the ready entry at `index` from the head without consuming it.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&Entry> {
+        if index < self.len() {
+            unsafe {
+                let head = self.head.wrapping_add(index as u32);
+                let entry = self.queue.cqe(head & self.ring_mask);
+                Some(&*(entry as *const Entry))
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Consume `n` ready entries without copying them out,
+    /// usually after processing them in place with [get](Self::get) or [peek_batch](Self::peek_batch).
+    ///
+    /// Like the iterator, the head is only committed when the queue is synced or dropped,
+    /// so a whole batch is released to the kernel with one store.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than the number of ready entries.
+    #[inline]
+    pub fn advance(&mut self, n: usize) {
+        assert!(n <= self.len(), "advance past the ready entries");
+        self.head = self.head.wrapping_add(n as u32);
+    }
+
+    /// Get all ready entries without consuming them.
+    ///
+    /// Ready entries may wrap around the end of the ring,
+    /// so they are returned as two slices in order, the second one may be empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ring uses 32-byte entries, which cannot be viewed as a slice of [Entry].
+    pub fn peek_batch(&self) -> (&[Entry], &[Entry]) {
+        assert_eq!(self.queue.cqe_shift, 0, "the ring uses 32-byte entries");
+
+        let len = self.len();
+        let start = (self.head & self.ring_mask) as usize;
+        let first = cmp::min(len, self.ring_entries as usize - start);
+
+        unsafe {
+            let cqes = self.queue.cqes as *const Entry;
+            (
+                slice::from_raw_parts(cqes.add(start), first),
+                slice::from_raw_parts(cqes, len - first),
+            )
+        }
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl AvailableQueue<'_> {
+    /// Consume the next ready entry together with its extra fields.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ring was not created with [Builder::setup_cqe32](crate::Builder::setup_cqe32).
+    pub fn next32(&mut self) -> Option<Entry32> {
+        assert!(
+            self.queue.is_cqe32(),
+            "the ring does not use 32-byte entries"
+        );
+
+        if self.head != self.tail {
+            unsafe {
+                let entry = self.queue.cqe(self.head & self.ring_mask) as *const Entry32;
+                self.head = self.head.wrapping_add(1);
+                Some((*entry).clone())
+            }
+        } else {
+            None
+        }
+    }
 }
 
 impl ExactSizeIterator for AvailableQueue<'_> {
@@ -141,7 +463,7 @@ impl Iterator for AvailableQueue<'_> {
     fn next(&mut self) -> Option<Self::Item> {
         if self.head != self.tail {
             unsafe {
-                let entry = self.queue.cqes.add((self.head & self.ring_mask) as usize);
+                let entry = self.queue.cqe(self.head & self.ring_mask);
                 self.head = self.head.wrapping_add(1);
                 Some(Entry(*entry))
             }
@@ -153,6 +475,9 @@ impl Iterator for AvailableQueue<'_> {
 
 impl Drop for AvailableQueue<'_> {
     fn drop(&mut self) {
+        #[cfg(any(feature = "tracing", feature = "stats"))]
+        self.record_reap();
+
         unsafe {
             (*self.queue.head).store(self.head, atomic::Ordering::Release);
         }
@@ -165,6 +490,16 @@ impl Entry {
         self.0.res
     }
 
+    /// Result value as an [io::Result],
+    /// a negative result is the negated errno of the failed request.
+    pub fn result_as_io(&self) -> io::Result<u32> {
+        if self.0.res >= 0 {
+            Ok(self.0.res as u32)
+        } else {
+            Err(io::Error::from_raw_os_error(-self.0.res))
+        }
+    }
+
     /// User Data
     ///
     /// See [Entry::user_data](crate::squeue::Entry::user_data).
@@ -172,8 +507,131 @@ impl Entry {
         self.0.user_data
     }
 
+    /// The user data as a [UserData], e.g. to recover a pointer with [UserData::as_ptr].
+    #[inline]
+    pub fn user_data_typed(&self) -> UserData {
+        UserData::from_u64(self.0.user_data)
+    }
+
     /// Flags
     pub fn flags(&self) -> u32 {
         self.0.flags
     }
+
+    /// The id of the selected buffer,
+    /// if the request used [BUFFER_SELECT](crate::squeue::Flags::BUFFER_SELECT) and a buffer was picked.
+    #[cfg(feature = "unstable")]
+    pub fn buffer_id(&self) -> Option<u16> {
+        if self.0.flags & sys::IORING_CQE_F_BUFFER != 0 {
+            Some((self.0.flags >> sys::IORING_CQE_BUFFER_SHIFT) as u16)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the multishot request will generate more completion events.
+    ///
+    /// Once an entry without this flag is seen, the request has terminated.
+    #[cfg(feature = "unstable")]
+    pub fn is_more(&self) -> bool {
+        self.0.flags & sys::IORING_CQE_F_MORE != 0
+    }
+
+    /// Whether there is more data to read from the socket after a receive.
+    #[cfg(feature = "unstable")]
+    pub fn is_sock_nonempty(&self) -> bool {
+        self.0.flags & sys::IORING_CQE_F_SOCK_NONEMPTY != 0
+    }
+
+    /// Whether this is a notification event of a zero-copy send,
+    /// posted once the kernel no longer uses the buffer.
+    #[cfg(feature = "unstable")]
+    pub fn is_notif(&self) -> bool {
+        self.0.flags & sys::IORING_CQE_F_NOTIF != 0
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl Entry32 {
+    /// Result value
+    pub fn result(&self) -> i32 {
+        self.0.result()
+    }
+
+    /// User Data
+    pub fn user_data(&self) -> u64 {
+        self.0.user_data()
+    }
+
+    /// The user data as a [UserData].
+    #[inline]
+    pub fn user_data_typed(&self) -> UserData {
+        self.0.user_data_typed()
+    }
+
+    /// Flags
+    pub fn flags(&self) -> u32 {
+        self.0.flags()
+    }
+
+    /// The two extra fields of a big completion entry,
+    /// their meaning depends on the request, e.g. the result of an NVMe passthrough command.
+    pub fn big_cqe(&self) -> &[u64; 2] {
+        &self.1
+    }
+}
+
+impl From<io_uring_cqe> for Entry {
+    /// Wrap a raw completion entry, e.g. one reaped by other code from the
+    /// [raw parts](CompletionQueue::raw_parts) of the queue.
+    fn from(entry: io_uring_cqe) -> Entry {
+        Entry(entry)
+    }
+}
+
+impl From<Entry> for io_uring_cqe {
+    fn from(entry: Entry) -> io_uring_cqe {
+        entry.0
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl From<Entry32> for Entry {
+    fn from(entry: Entry32) -> Entry {
+        entry.0
+    }
+}
+
+impl fmt::Debug for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Entry")
+            .field("result", &self.0.res)
+            .field("user_data", &self.0.user_data)
+            .field("flags", &self.0.flags)
+            .finish()
+    }
+}
+
+impl fmt::Debug for CompletionQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        unsafe {
+            f.debug_struct("CompletionQueue")
+                .field("head", &self.head())
+                .field("tail", &self.tail())
+                .field("entries", &self.capacity())
+                .field("flags", &(*self.flags).load(atomic::Ordering::Acquire))
+                .field("overflow", &self.overflow())
+                .finish()
+        }
+    }
+}
+
+impl fmt::Debug for AvailableQueue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AvailableQueue")
+            .field("head", &self.head)
+            .field("tail", &self.tail)
+            .field("entries", &self.ring_entries)
+            .finish()
+    }
 }
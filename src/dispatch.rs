@@ -0,0 +1,163 @@
+//! Completion handlers.
+
+use crate::cqueue::{self, CompletionQueue};
+use crate::squeue::{self, AvailableQueue, PushError};
+use crate::tracker::OpTracker;
+
+type Handler<'a> = Box<dyn FnMut(&cqueue::Entry) + 'a>;
+
+/// Runs the handler of each operation when its completion events arrive.
+///
+/// The handler of a multishot request is kept for as long as its events have
+/// [is_more](cqueue::Entry::is_more) set, and dropped after the final one.
+///
+/// Dropping the dispatcher while operations are in flight leaks their handlers,
+/// like [OpTracker].
+pub struct Dispatcher<'a> {
+    handlers: OpTracker<Handler<'a>>,
+}
+
+impl<'a> Dispatcher<'a> {
+    pub fn new() -> Dispatcher<'a> {
+        Dispatcher {
+            handlers: OpTracker::new(),
+        }
+    }
+
+    /// The number of operations in flight.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.handlers.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+
+    /// Push an entry into the submission queue, with a handler for its completion events.
+    ///
+    /// The `user_data` of the entry is set to the returned key.
+    ///
+    /// # Safety
+    ///
+    /// Developers must ensure that parameters of the entry (such as buffer) are valid,
+    /// otherwise it may cause memory problems.
+    pub unsafe fn push<F>(
+        &mut self,
+        sq: &mut AvailableQueue<'_>,
+        entry: squeue::Entry,
+        handler: F,
+    ) -> Result<u64, PushError<squeue::Entry>>
+    where
+        F: FnMut(&cqueue::Entry) + 'a,
+    {
+        self.handlers
+            .push(sq, entry, Box::new(handler))
+            .map_err(|PushError::Full((entry, _))| PushError::Full(entry))
+    }
+
+    /// Drop the handler of an operation, its later completion events are ignored.
+    ///
+    /// This does not cancel the request, see [AsyncCancel](crate::opcode::AsyncCancel).
+    pub fn forget(&mut self, key: u64) -> bool {
+        self.handlers.remove(key).is_some()
+    }
+
+    /// Run the handler of a completion event,
+    /// returns `false` if the event does not belong to an operation in flight.
+    pub fn dispatch(&mut self, entry: &cqueue::Entry) -> bool {
+        let key = entry.user_data();
+
+        if entry.is_more() {
+            match self.handlers.get_mut(key) {
+                Some(handler) => handler(entry),
+                None => return false,
+            }
+        } else {
+            match self.handlers.remove(key) {
+                Some(mut handler) => handler(entry),
+                None => return false,
+            }
+        }
+
+        true
+    }
+
+    /// Run the handlers of all the events in the completion queue,
+    /// returns the number of events that were dispatched.
+    pub fn dispatch_all(&mut self, cq: &mut CompletionQueue) -> usize {
+        cq.available().filter(|entry| self.dispatch(entry)).count()
+    }
+}
+
+impl Default for Dispatcher<'_> {
+    fn default() -> Self {
+        Dispatcher::new()
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::{MockRing, Reply};
+    use crate::opcode::{self, types};
+    use crate::sys;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_multishot() {
+        let mut ring = MockRing::new(4);
+        let events = RefCell::new(Vec::new());
+        let mut dispatcher = Dispatcher::new();
+
+        let entry = opcode::AcceptMulti::new(types::Fd(3)).build();
+        let key = unsafe {
+            dispatcher.push(&mut ring.submission().available(), entry, |cqe| {
+                events.borrow_mut().push(cqe.result())
+            })
+        }
+        .unwrap();
+        ring.submit().unwrap();
+
+        ring.complete(key, Reply::new(1).more());
+        ring.complete(key, Reply::new(2).more());
+        assert_eq!(dispatcher.dispatch_all(ring.completion()), 2);
+        assert_eq!(dispatcher.len(), 1);
+
+        // The final event drops the handler, later events of the key are not dispatched.
+        ring.complete(key, Reply::new(3));
+        assert_eq!(dispatcher.dispatch_all(ring.completion()), 1);
+        assert!(dispatcher.is_empty());
+
+        let stale = cqueue::Entry::from(sys::io_uring_cqe {
+            user_data: key,
+            res: 4,
+            flags: 0,
+        });
+        assert!(!dispatcher.dispatch(&stale));
+        drop(dispatcher);
+        assert_eq!(events.into_inner(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_push_full_and_forget() {
+        let mut ring = MockRing::new(1);
+        let mut dispatcher = Dispatcher::new();
+        let nop = || opcode::Nop::new().build();
+
+        let key =
+            unsafe { dispatcher.push(&mut ring.submission().available(), nop(), |_| ()) }.unwrap();
+        let ret = unsafe { dispatcher.push(&mut ring.submission().available(), nop(), |_| ()) };
+        assert!(matches!(ret, Err(PushError::Full(_))));
+        assert_eq!(dispatcher.len(), 1);
+
+        assert!(dispatcher.forget(key));
+        assert!(!dispatcher.forget(key));
+
+        ring.submit().unwrap();
+        assert!(ring.complete(key, Reply::new(0)));
+        assert_eq!(dispatcher.dispatch_all(ring.completion()), 0);
+        assert!(ring.completion().is_empty());
+    }
+}
@@ -0,0 +1,506 @@
+//! A reactor for async runtimes.
+//!
+//! [Driver] owns a ring, and resolves the [OpHandle] future of each submitted request
+//! when its completion event arrives, waking the task that polled it.
+
+use std::any::Any;
+use std::cell::{RefCell, RefMut};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use std::{io, mem};
+
+use crate::cqueue;
+use crate::opcode;
+use crate::squeue::{self, PushError};
+use crate::tracker::OpTracker;
+use crate::{IoUring, SubmitError};
+
+/// The `user_data` of the requests the driver submits for itself,
+/// their completion events are ignored.
+const INTERNAL: u64 = u64::MAX;
+
+/// A ring driving the requests of a thread.
+///
+/// The driver is not `Send`, and its handles refer to it, so it is meant to be
+/// owned by the executor of a single thread, which calls [Driver::park] when it has
+/// nothing left to run.
+#[derive(Clone)]
+pub struct Driver {
+    inner: Rc<RefCell<Inner>>,
+}
+
+struct Inner {
+    ring: IoUring,
+    ops: OpTracker<State>,
+}
+
+enum State {
+    /// Waiting for the completion event, and for the task to poll the handle.
+    Waiting(Option<Waker>),
+    /// The completion event arrived before the handle was polled again.
+    Completed(cqueue::Entry),
+    /// The events of a multishot request, and whether it has terminated.
+    Stream {
+        events: VecDeque<cqueue::Entry>,
+        waker: Option<Waker>,
+        finished: bool,
+    },
+    /// The handle was dropped, the request has been canceled,
+    /// with the resources to keep until it completes.
+    Ignored(Option<Box<dyn Any>>),
+}
+
+/// A submitted request, resolves to its completion event.
+///
+/// Dropping the handle before the request completed cancels the request
+/// with [AsyncCancel](opcode::AsyncCancel). The cancellation is asynchronous,
+/// so memory the request refers to must still be kept until it completes,
+/// for example by moving it into the handle's task until then, or by using the
+/// operations of [owned](crate::owned).
+pub struct OpHandle {
+    inner: Rc<RefCell<Inner>>,
+    key: u64,
+    done: bool,
+    resources: Option<Box<dyn Any>>,
+}
+
+/// A submitted multishot request, yields its completion events.
+///
+/// Dropping the handle before the request terminated cancels it, like [OpHandle].
+pub struct MultiHandle {
+    inner: Rc<RefCell<Inner>>,
+    key: u64,
+    done: bool,
+}
+
+impl Driver {
+    /// Create a driver with a ring of `entries` entries.
+    pub fn new(entries: u32) -> io::Result<Driver> {
+        IoUring::new(entries).map(Driver::with_ring)
+    }
+
+    /// Create a driver owning `ring`.
+    pub fn with_ring(ring: IoUring) -> Driver {
+        Driver {
+            inner: Rc::new(RefCell::new(Inner {
+                ring,
+                ops: OpTracker::new(),
+            })),
+        }
+    }
+
+    /// The ring of the driver, e.g. to register resources.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ring is already borrowed.
+    pub fn ring(&self) -> RefMut<'_, IoUring> {
+        RefMut::map(self.inner.borrow_mut(), |inner| &mut inner.ring)
+    }
+
+    /// The number of requests in flight.
+    pub fn len(&self) -> usize {
+        self.inner.borrow().ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Push an entry into the submission queue, submitting the queue first if it is full,
+    /// and return a handle that resolves to its completion event.
+    ///
+    /// The `user_data` of the entry is overwritten.
+    ///
+    /// # Safety
+    ///
+    /// Developers must ensure that parameters of the entry (such as buffer) are valid,
+    /// otherwise it may cause memory problems.
+    pub unsafe fn submit(&self, entry: squeue::Entry) -> io::Result<OpHandle> {
+        let key = self.inner.borrow_mut().push(entry, State::Waiting(None))?;
+
+        Ok(OpHandle {
+            inner: self.inner.clone(),
+            key,
+            done: false,
+            resources: None,
+        })
+    }
+
+    /// Like [Driver::submit], but for a multishot request,
+    /// the handle yields each completion event until the request terminates.
+    ///
+    /// # Safety
+    ///
+    /// Developers must ensure that parameters of the entry (such as buffer) are valid,
+    /// otherwise it may cause memory problems.
+    pub unsafe fn submit_multishot(&self, entry: squeue::Entry) -> io::Result<MultiHandle> {
+        let state = State::Stream {
+            events: VecDeque::new(),
+            waker: None,
+            finished: false,
+        };
+        let key = self.inner.borrow_mut().push(entry, state)?;
+
+        Ok(MultiHandle {
+            inner: self.inner.clone(),
+            key,
+            done: false,
+        })
+    }
+
+    /// Submit the queued requests, and wake the tasks whose requests have completed,
+    /// without waiting.
+    pub fn poll(&self) -> io::Result<()> {
+        self.turn(0)
+    }
+
+    /// Submit the queued requests, wait for at least one completion event
+    /// if any request is in flight, and wake the tasks whose requests have completed.
+    pub fn park(&self) -> io::Result<()> {
+        let want = if self.is_empty() { 0 } else { 1 };
+        self.turn(want)
+    }
+
+    fn turn(&self, want: usize) -> io::Result<()> {
+        let wakers = {
+            let mut inner = self.inner.borrow_mut();
+            let Inner { ring, ops } = &mut *inner;
+
+            match ring.submit_and_wait(want) {
+                // The completion events are reaped below.
+                Ok(_) | Err(SubmitError::CompletionQueueOverflow) => (),
+                Err(err) => return Err(err.into()),
+            }
+
+            ring.completion()
+                .available()
+                .filter_map(|entry| complete(ops, entry))
+                .collect::<Vec<_>>()
+        };
+
+        // The tasks may submit again, so the driver must not be borrowed.
+        wakers.into_iter().for_each(Waker::wake);
+
+        Ok(())
+    }
+}
+
+impl Inner {
+    unsafe fn push(&mut self, mut entry: squeue::Entry, mut state: State) -> io::Result<u64> {
+        loop {
+            match self
+                .ops
+                .push(&mut self.ring.submission().available(), entry, state)
+            {
+                Ok(key) => return Ok(key),
+                Err(PushError::Full((e, s))) => {
+                    entry = e;
+                    state = s;
+                }
+            }
+
+            self.ring.submit()?;
+        }
+    }
+
+    /// Forget a request whose handle is dropped, and cancel it.
+    fn cancel(&mut self, key: u64, resources: Option<Box<dyn Any>>) {
+        if let Some(state) = self.ops.get_mut(key) {
+            *state = State::Ignored(resources);
+        }
+
+        let cancel = opcode::AsyncCancel::new(key)
+            .build()
+            .flags(squeue::Flags::CQE_SKIP_SUCCESS)
+            .user_data(INTERNAL);
+
+        // Best effort, the request completes by itself if the queue is full.
+        let _ = unsafe { self.ring.submission().available().push(cancel) };
+    }
+}
+
+/// Record a completion event, returns the waker of the task to wake.
+fn complete(ops: &mut OpTracker<State>, entry: cqueue::Entry) -> Option<Waker> {
+    let key = entry.user_data();
+    let state = ops.get_mut(key)?;
+
+    match mem::replace(state, State::Ignored(None)) {
+        State::Waiting(waker) => {
+            *state = State::Completed(entry);
+            waker
+        }
+        State::Stream {
+            mut events,
+            mut waker,
+            finished,
+        } => {
+            let finished = finished || !entry.is_more();
+            events.push_back(entry);
+            let waker = waker.take();
+            *state = State::Stream {
+                events,
+                waker: None,
+                finished,
+            };
+            waker
+        }
+        State::Completed(first) => {
+            // A later event of a multishot request, the handle resolves to the first one.
+            *state = State::Completed(first);
+            None
+        }
+        State::Ignored(resources) => {
+            if entry.is_more() {
+                *state = State::Ignored(resources);
+            } else {
+                ops.remove(key);
+            }
+            None
+        }
+    }
+}
+
+impl OpHandle {
+    /// The `user_data` of the request.
+    #[inline]
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    /// Keep `resources` until the request completes, even if the handle is dropped before.
+    #[cfg(feature = "async")]
+    pub(crate) fn keep(&mut self, resources: Box<dyn Any>) {
+        self.resources = Some(resources);
+    }
+}
+
+impl Future for OpHandle {
+    type Output = cqueue::Entry;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<cqueue::Entry> {
+        let mut inner = self.inner.borrow_mut();
+        let state = inner
+            .ops
+            .get_mut(self.key)
+            .expect("the handle has already resolved");
+
+        match state {
+            State::Waiting(waker) => {
+                match waker {
+                    Some(waker) if waker.will_wake(cx.waker()) => (),
+                    _ => *waker = Some(cx.waker().clone()),
+                }
+                Poll::Pending
+            }
+            State::Completed(_) => match inner.ops.remove(self.key) {
+                Some(State::Completed(entry)) => {
+                    drop(inner);
+                    self.done = true;
+                    Poll::Ready(entry)
+                }
+                _ => unreachable!(),
+            },
+            State::Stream { .. } | State::Ignored(_) => unreachable!(),
+        }
+    }
+}
+
+impl Drop for OpHandle {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+
+        let mut inner = self.inner.borrow_mut();
+        let inner = &mut *inner;
+
+        match inner.ops.get_mut(self.key) {
+            Some(State::Completed(entry)) if !entry.is_more() => {
+                inner.ops.remove(self.key);
+            }
+            Some(_) => inner.cancel(self.key, self.resources.take()),
+            None => (),
+        }
+    }
+}
+
+impl MultiHandle {
+    /// The `user_data` of the request.
+    #[inline]
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    /// Poll for the next completion event,
+    /// returns `None` once the request has terminated and all its events were yielded.
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<cqueue::Entry>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        let mut inner = self.inner.borrow_mut();
+
+        let (entry, finished) = match inner.ops.get_mut(self.key) {
+            Some(State::Stream {
+                events,
+                waker,
+                finished,
+            }) => match events.pop_front() {
+                Some(entry) => (Some(entry), *finished && events.is_empty()),
+                None if *finished => (None, true),
+                None => {
+                    match waker {
+                        Some(waker) if waker.will_wake(cx.waker()) => (),
+                        _ => *waker = Some(cx.waker().clone()),
+                    }
+                    return Poll::Pending;
+                }
+            },
+            _ => unreachable!(),
+        };
+
+        if finished {
+            inner.ops.remove(self.key);
+            self.done = true;
+        }
+
+        Poll::Ready(entry)
+    }
+}
+
+#[cfg(feature = "async")]
+impl futures_core::Stream for MultiHandle {
+    type Item = cqueue::Entry;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<cqueue::Entry>> {
+        self.get_mut().poll_next(cx)
+    }
+}
+
+impl Drop for MultiHandle {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+
+        let mut inner = self.inner.borrow_mut();
+
+        match inner.ops.get_mut(self.key) {
+            Some(State::Stream { finished: true, .. }) => {
+                inner.ops.remove(self.key);
+            }
+            Some(_) => inner.cancel(self.key, None),
+            None => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opcode::types;
+    use std::net::{TcpListener, TcpStream};
+    use std::os::unix::io::AsRawFd;
+
+    struct Noop;
+
+    impl std::task::Wake for Noop {
+        fn wake(self: std::sync::Arc<Self>) {}
+    }
+
+    fn noop() -> Waker {
+        Waker::from(std::sync::Arc::new(Noop))
+    }
+
+    fn poll<F: Future + Unpin>(future: &mut F) -> Poll<F::Output> {
+        Pin::new(future).poll(&mut Context::from_waker(&noop()))
+    }
+
+    /// Park until no request is in flight.
+    fn drain(driver: &Driver) {
+        for _ in 0..16 {
+            if driver.is_empty() {
+                return;
+            }
+            driver.park().unwrap();
+        }
+        panic!("requests are still in flight");
+    }
+
+    #[test]
+    fn test_submit_full_queue() {
+        let driver = Driver::new(2).unwrap();
+
+        // The queue is submitted whenever it is full, so nothing waits for its turn.
+        let mut handles: Vec<_> = (0..8)
+            .map(|_| unsafe { driver.submit(opcode::Nop::new().build()) }.unwrap())
+            .collect();
+        assert_eq!(driver.len(), 8);
+
+        let mut done = 0;
+        while !handles.is_empty() {
+            driver.park().unwrap();
+            handles.retain_mut(|handle| match poll(handle) {
+                Poll::Ready(cqe) => {
+                    assert_eq!((cqe.user_data(), cqe.result()), (handle.key(), 0));
+                    done += 1;
+                    false
+                }
+                Poll::Pending => true,
+            });
+        }
+        assert_eq!(done, 8);
+        assert!(driver.is_empty());
+    }
+
+    #[test]
+    fn test_drop_cancels() {
+        let driver = Driver::new(4).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let fd = types::Fd(listener.as_raw_fd());
+        let mut handle =
+            unsafe { driver.submit(opcode::PollAdd::new(fd, libc::POLLIN).build()) }.unwrap();
+        driver.poll().unwrap();
+        assert!(poll(&mut handle).is_pending());
+
+        drop(handle);
+        drain(&driver);
+    }
+
+    #[test]
+    fn test_multishot() {
+        let driver = Driver::new(4).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let entry = opcode::AcceptMulti::new(types::Fd(listener.as_raw_fd())).build();
+        let mut handle = unsafe { driver.submit_multishot(entry) }.unwrap();
+        let _clients = [
+            TcpStream::connect(addr).unwrap(),
+            TcpStream::connect(addr).unwrap(),
+        ];
+
+        let mut accepted = Vec::new();
+        while accepted.len() < 2 {
+            driver.park().unwrap();
+            while let Poll::Ready(cqe) = handle.poll_next(&mut Context::from_waker(&noop())) {
+                let cqe = cqe.expect("the request terminated");
+                assert!(cqe.is_more());
+                accepted.push(cqe.result());
+            }
+        }
+        for fd in accepted {
+            assert!(fd >= 0);
+            unsafe { libc::close(fd) };
+        }
+
+        // The request is still armed, dropping the handle cancels it.
+        drop(handle);
+        drain(&driver);
+    }
+}
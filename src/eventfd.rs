@@ -0,0 +1,176 @@
+//! Notification of completion events through an eventfd.
+
+use std::convert::TryInto;
+use std::io;
+use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
+
+use crate::util::Fd;
+use crate::Submitter;
+
+/// A non-blocking eventfd, which can be registered to be notified of completion events,
+/// see `eventfd(2)`.
+///
+/// The counter of the eventfd is incremented whenever completion events are posted,
+/// so it can be waited for alongside other file descriptors, e.g. with `epoll`.
+/// With the `mio` feature, it can be registered with a `mio::Poll` as an event source.
+/// A ring keeps its registered eventfd open, unless it is unregistered with
+/// [Submitter::unregister_eventfd], so dropping it only closes the file descriptor
+/// of the process.
+pub struct EventFd {
+    fd: Fd,
+}
+
+impl EventFd {
+    pub fn new() -> io::Result<EventFd> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) }
+            .try_into()
+            .map_err(|_| io::Error::last_os_error())?;
+
+        Ok(EventFd { fd })
+    }
+
+    /// Register the eventfd with the ring of `submitter`.
+    ///
+    /// Only the completion events posted after the registration are notified,
+    /// so it should be registered before the requests of interest are submitted.
+    /// A ring has a single eventfd, this fails with `EBUSY` if one is registered.
+    pub fn register(&self, submitter: &Submitter<'_>) -> io::Result<()> {
+        submitter.register_eventfd(self.fd.as_raw_fd())
+    }
+
+    /// Register the eventfd with the ring of `submitter`, to be notified only
+    /// of the requests that completed asynchronously,
+    /// see [Submitter::register_eventfd_async].
+    pub fn register_async(&self, submitter: &Submitter<'_>) -> io::Result<()> {
+        submitter.register_eventfd_async(self.fd.as_raw_fd())
+    }
+
+    /// Read and reset the counter, returns the number of notifications
+    /// since the last read, 0 if there was none.
+    pub fn read(&self) -> io::Result<u64> {
+        let mut count = 0u64;
+
+        loop {
+            let ret = unsafe {
+                libc::read(
+                    self.fd.as_raw_fd(),
+                    &mut count as *mut u64 as *mut libc::c_void,
+                    8,
+                )
+            };
+
+            if ret >= 0 {
+                return Ok(count);
+            }
+
+            let err = io::Error::last_os_error();
+            match err.kind() {
+                io::ErrorKind::WouldBlock => return Ok(0),
+                io::ErrorKind::Interrupted => (),
+                _ => return Err(err),
+            }
+        }
+    }
+
+    /// Block until a notification arrives, then read and reset the counter.
+    pub fn wait(&self) -> io::Result<u64> {
+        loop {
+            let count = self.read()?;
+            if count != 0 {
+                return Ok(count);
+            }
+
+            let mut pollfd = libc::pollfd {
+                fd: self.fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            if unsafe { libc::poll(&mut pollfd, 1, -1) } < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+impl AsRawFd for EventFd {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for EventFd {
+    #[inline]
+    fn into_raw_fd(self) -> RawFd {
+        self.fd.into_raw_fd()
+    }
+}
+
+/// The eventfd becomes readable when completion events are posted.
+/// Readiness is edge triggered, so the counter should be reset with [EventFd::read]
+/// before the completion queue is drained, otherwise later events may be missed.
+#[cfg(feature = "mio")]
+impl mio::event::Source for EventFd {
+    fn register(
+        &mut self,
+        registry: &mio::Registry,
+        token: mio::Token,
+        interests: mio::Interest,
+    ) -> io::Result<()> {
+        mio::unix::SourceFd(&self.fd.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &mio::Registry,
+        token: mio::Token,
+        interests: mio::Interest,
+    ) -> io::Result<()> {
+        mio::unix::SourceFd(&self.fd.as_raw_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &mio::Registry) -> io::Result<()> {
+        mio::unix::SourceFd(&self.fd.as_raw_fd()).deregister(registry)
+    }
+}
+
+#[cfg(all(test, feature = "mio"))]
+mod tests {
+    use super::*;
+    use crate::{opcode, IoUring};
+    use std::time::Duration;
+
+    #[test]
+    fn test_mio_source() -> io::Result<()> {
+        let mut ring = IoUring::new(8)?;
+        let mut poll = mio::Poll::new()?;
+        let mut events = mio::Events::with_capacity(8);
+
+        let mut eventfd = EventFd::new()?;
+        eventfd.register(&ring.submitter())?;
+        poll.registry()
+            .register(&mut eventfd, mio::Token(7), mio::Interest::READABLE)?;
+
+        poll.poll(&mut events, Some(Duration::from_millis(0)))?;
+        assert!(events.is_empty());
+
+        unsafe {
+            ring.submission()
+                .available()
+                .push(opcode::Nop::new().build().user_data(0x42))
+                .expect("queue is full");
+        }
+        ring.submit()?;
+
+        poll.poll(&mut events, Some(Duration::from_secs(1)))?;
+        let tokens: Vec<_> = events.iter().map(|event| event.token()).collect();
+        assert_eq!(tokens, [mio::Token(7)]);
+        assert_eq!(eventfd.read()?, 1);
+
+        poll.registry().deregister(&mut eventfd)?;
+        Ok(())
+    }
+}
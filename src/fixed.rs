@@ -0,0 +1,502 @@
+//! Registered resources.
+//!
+//! [FixedFileSet] manages a table of registered files, so that requests can refer
+//! to a file by its slot and skip the file descriptor lookup.
+//! [DirectFd] owns a single slot of the table, such as the one of an accepted connection.
+//! [FixedBufPool] manages a set of registered buffers, which the kernel keeps mapped
+//! instead of mapping them for every request.
+
+use std::io;
+use std::os::unix::io::RawFd;
+use std::ptr::NonNull;
+use std::slice;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::opcode::{self, sealed, types};
+use crate::squeue;
+use crate::Submitter;
+
+/// A registered file table, with allocation of its slots.
+///
+/// The table is registered sparse, every slot starts empty.
+/// A slot is handed out as a [types::Fixed], which can be passed
+/// to the opcode builders in place of a file descriptor.
+///
+/// The kernel keeps its own reference to a registered file,
+/// so the file descriptor can be closed once it has been inserted.
+pub struct FixedFileSet {
+    free: Vec<u32>,
+    used: Vec<bool>,
+}
+
+impl FixedFileSet {
+    /// Register a table of `capacity` empty slots, requires Linux 5.19.
+    ///
+    /// A ring has a single file table, so this fails with `EBUSY`
+    /// if files are already registered.
+    pub fn new(submitter: &Submitter<'_>, capacity: u32) -> io::Result<FixedFileSet> {
+        submitter.register_files_sparse(capacity)?;
+
+        Ok(FixedFileSet {
+            free: (0..capacity).rev().collect(),
+            used: vec![false; capacity as usize],
+        })
+    }
+
+    /// The number of slots of the table.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.used.len()
+    }
+
+    /// The number of allocated slots.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.used.len() - self.free.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Allocate an empty slot, to be filled in with [FixedFileSet::update_entry].
+    /// Returns `None` if every slot is in use.
+    pub fn alloc(&mut self) -> Option<types::Fixed> {
+        let slot = self.free.pop()?;
+        self.used[slot as usize] = true;
+        Some(types::Fixed(slot))
+    }
+
+    /// Return a slot to the free list, without touching the registered table.
+    ///
+    /// This is for slots that have been emptied with [FixedFileSet::update_entry].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slot is not allocated.
+    pub fn free(&mut self, slot: types::Fixed) {
+        let used = self
+            .used
+            .get_mut(slot.0 as usize)
+            .filter(|used| **used)
+            .expect("the slot is not allocated");
+
+        *used = false;
+        self.free.push(slot.0);
+    }
+
+    /// Register `fd` in a free slot.
+    ///
+    /// If `tag` is not zero, the kernel posts a completion event with `tag` as its `user_data`
+    /// once the file has been removed and no request uses it anymore.
+    pub fn insert(
+        &mut self,
+        submitter: &Submitter<'_>,
+        fd: RawFd,
+        tag: u64,
+    ) -> io::Result<types::Fixed> {
+        let slot = self
+            .alloc()
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ENFILE))?;
+
+        match submitter.register_files_update_tag(slot.0, &[fd], &[tag]) {
+            Ok(_) => Ok(slot),
+            Err(err) => {
+                self.free(slot);
+                Err(err)
+            }
+        }
+    }
+
+    /// Remove the file of a slot and free the slot.
+    ///
+    /// Requests already using the file keep it alive until they complete,
+    /// the slot can be allocated again right away.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slot is not allocated.
+    pub fn remove(&mut self, submitter: &Submitter<'_>, slot: types::Fixed) -> io::Result<()> {
+        assert!(
+            self.used.get(slot.0 as usize).copied().unwrap_or(false),
+            "the slot is not allocated"
+        );
+
+        submitter.register_files_update_tag(slot.0, &[-1], &[0])?;
+        self.free(slot);
+        Ok(())
+    }
+
+    /// Build an entry that puts `*fd` into a slot asynchronously, or empties it if `*fd` is `-1`.
+    ///
+    /// The completion event has a `result` of 1 on success.
+    /// Files updated this way have no tag.
+    ///
+    /// # Safety
+    ///
+    /// `fd` must stay valid until the entry completes.
+    pub unsafe fn update_entry(&self, slot: types::Fixed, fd: *const RawFd) -> squeue::Entry {
+        opcode::FilesUpdate::new(fd, 1).offset(slot.0 as _).build()
+    }
+}
+
+/// A direct descriptor, the slot of the registered file table holding a file,
+/// for files opened or accepted straight into the table.
+///
+/// Closing the file consumes the handle, so its slot cannot be used afterwards.
+/// Dropping the handle without closing it leaves the file in the table
+/// until the table is unregistered, like [DirectFd::into_slot].
+#[must_use = "dropping a direct descriptor leaves its file in the table"]
+#[derive(Debug, PartialEq, Eq)]
+pub struct DirectFd {
+    slot: types::Fixed,
+}
+
+impl DirectFd {
+    /// Take ownership of a slot holding a file,
+    /// e.g. the `result` of an [Accept](opcode::Accept) into an allocated slot.
+    #[inline]
+    pub fn new(slot: types::Fixed) -> DirectFd {
+        DirectFd { slot }
+    }
+
+    /// The slot, to pass to the opcode builders in place of a file descriptor.
+    #[inline]
+    pub fn slot(&self) -> types::Fixed {
+        self.slot
+    }
+
+    /// Give up the ownership of the slot, leaving the file in the table.
+    #[inline]
+    pub fn into_slot(self) -> types::Fixed {
+        self.slot
+    }
+
+    /// Remove the file from the table right away.
+    ///
+    /// Requests already using the file keep it alive until they complete.
+    pub fn close(self, submitter: &Submitter<'_>) -> io::Result<()> {
+        submitter.register_files_update(self.slot.0, &[-1])?;
+        Ok(())
+    }
+
+    /// Build an entry that removes the file from the table asynchronously,
+    /// requires Linux 5.15.
+    pub fn close_entry(self) -> squeue::Entry {
+        opcode::Close::new(self.slot).build()
+    }
+
+    /// Build an entry that installs the file as a regular file descriptor of the process,
+    /// which is the result of the request, requires Linux 6.8.
+    ///
+    /// The file also stays in the table, and its new descriptor is owned by the caller.
+    pub fn install(&self) -> opcode::FixedFdInstall {
+        opcode::FixedFdInstall::new(self.slot)
+    }
+}
+
+/// A pool of registered buffers of equal size.
+///
+/// Buffers are handed out as [FixedBuf] handles, which are moved into
+/// [FixedBufPool::read_fixed] and [FixedBufPool::write_fixed] while the request is in flight,
+/// and handed back by [FixedBufPool::complete] once its completion event arrives.
+/// So a buffer can neither be used by two requests at the same time,
+/// nor be accessed while the kernel uses it.
+pub struct FixedBufPool {
+    id: u64,
+    memory: NonNull<u8>,
+    buf_len: usize,
+    free: Vec<u16>,
+    in_flight: Vec<bool>,
+}
+
+/// A buffer of a [FixedBufPool].
+#[derive(Debug)]
+pub struct FixedBuf {
+    pool: u64,
+    index: u16,
+}
+
+/// The id of the next pool, which ties the buffers to their pool.
+static NEXT_POOL_ID: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl Send for FixedBufPool {}
+unsafe impl Sync for FixedBufPool {}
+
+impl FixedBufPool {
+    /// Allocate `count` buffers of `buf_len` bytes and register them.
+    ///
+    /// A ring has a single buffer table, so this fails with `EBUSY`
+    /// if buffers are already registered.
+    pub fn new(submitter: &Submitter<'_>, count: u16, buf_len: usize) -> io::Result<FixedBufPool> {
+        let memory = vec![0u8; count as usize * buf_len].into_boxed_slice();
+        let memory = unsafe { NonNull::new_unchecked(Box::into_raw(memory) as *mut u8) };
+
+        let pool = FixedBufPool {
+            id: NEXT_POOL_ID.fetch_add(1, Ordering::Relaxed),
+            memory,
+            buf_len,
+            free: (0..count).rev().collect(),
+            in_flight: vec![false; count as usize],
+        };
+
+        let iovecs = (0..count)
+            .map(|index| libc::iovec {
+                iov_base: pool.buf_ptr(index) as *mut _,
+                iov_len: buf_len,
+            })
+            .collect::<Vec<_>>();
+
+        match submitter.register_buffers(&iovecs) {
+            Ok(()) => Ok(pool),
+            Err(err) => {
+                // Not registered, so the memory can be freed.
+                unsafe { pool.free_memory() };
+                Err(err)
+            }
+        }
+    }
+
+    /// The size of each buffer.
+    #[inline]
+    pub fn buf_len(&self) -> usize {
+        self.buf_len
+    }
+
+    /// Take a free buffer, returns `None` if they are all in use.
+    pub fn alloc(&mut self) -> Option<FixedBuf> {
+        let pool = self.id;
+        self.free.pop().map(|index| FixedBuf { pool, index })
+    }
+
+    /// Return a buffer to the pool.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer belongs to another pool.
+    pub fn release(&mut self, buf: FixedBuf) {
+        self.check(&buf);
+        self.free.push(buf.index);
+    }
+
+    /// The contents of a buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer belongs to another pool.
+    pub fn get(&self, buf: &FixedBuf) -> &[u8] {
+        self.check(buf);
+        unsafe { slice::from_raw_parts(self.buf_ptr(buf.index), self.buf_len) }
+    }
+
+    /// The contents of a buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer belongs to another pool.
+    pub fn get_mut(&mut self, buf: &mut FixedBuf) -> &mut [u8] {
+        self.check(buf);
+        unsafe { slice::from_raw_parts_mut(self.buf_ptr(buf.index), self.buf_len) }
+    }
+
+    /// Start reading into a whole buffer, which is in flight until [FixedBufPool::complete].
+    ///
+    /// The offset and other options can still be set on the returned builder.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer belongs to another pool.
+    pub fn read_fixed(&mut self, buf: FixedBuf, fd: impl sealed::UseFixed) -> opcode::ReadFixed {
+        let index = self.start(buf);
+        opcode::ReadFixed::new(fd, self.buf_ptr(index), self.buf_len as _, index)
+    }
+
+    /// Start writing the first `len` bytes of a buffer,
+    /// which is in flight until [FixedBufPool::complete].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is larger than the buffers, or the buffer belongs to another pool.
+    pub fn write_fixed(
+        &mut self,
+        buf: FixedBuf,
+        len: usize,
+        fd: impl sealed::UseFixed,
+    ) -> opcode::WriteFixed {
+        assert!(len <= self.buf_len, "write past the end of the buffer");
+
+        let index = self.start(buf);
+        opcode::WriteFixed::new(fd, self.buf_ptr(index), len as _, index)
+    }
+
+    /// Get back the buffer of a request that has completed,
+    /// `index` is [FixedBuf::index] of the buffer the request was started with.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no request using this buffer is in flight.
+    pub fn complete(&mut self, index: u16) -> FixedBuf {
+        let in_flight = self
+            .in_flight
+            .get_mut(index as usize)
+            .filter(|in_flight| **in_flight)
+            .expect("the buffer is not in flight");
+
+        *in_flight = false;
+        FixedBuf {
+            pool: self.id,
+            index,
+        }
+    }
+
+    /// Unregister the buffers and free their memory.
+    ///
+    /// If this is not called, dropping the pool leaks the memory,
+    /// because the kernel may still write to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a request is still in flight.
+    pub fn unregister(self, submitter: &Submitter<'_>) -> io::Result<()> {
+        assert!(
+            !self.in_flight.contains(&true),
+            "a request using the buffers is in flight"
+        );
+
+        submitter.unregister_buffers()?;
+        unsafe { self.free_memory() };
+        Ok(())
+    }
+
+    fn start(&mut self, buf: FixedBuf) -> u16 {
+        self.check(&buf);
+        self.in_flight[buf.index as usize] = true;
+        buf.index
+    }
+
+    fn check(&self, buf: &FixedBuf) {
+        assert_eq!(buf.pool, self.id, "the buffer belongs to another pool");
+    }
+
+    fn buf_ptr(&self, index: u16) -> *mut u8 {
+        unsafe { self.memory.as_ptr().add(index as usize * self.buf_len) }
+    }
+
+    unsafe fn free_memory(self) {
+        let len = self.in_flight.len() * self.buf_len;
+        let memory = std::ptr::slice_from_raw_parts_mut(self.memory.as_ptr(), len);
+        drop(Box::from_raw(memory));
+    }
+}
+
+impl FixedBuf {
+    /// The index of the buffer in the registered buffers.
+    #[inline]
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IoUring;
+
+    fn pipe() -> [RawFd; 2] {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        fds
+    }
+
+    fn close(fds: [RawFd; 2]) {
+        for fd in fds {
+            unsafe { libc::close(fd) };
+        }
+    }
+
+    fn run(ring: &mut IoUring, entry: squeue::Entry) -> i32 {
+        unsafe { ring.submission().available().push(entry).unwrap() };
+        ring.submit_and_wait(1).unwrap();
+        ring.completion().available().next().unwrap().result()
+    }
+
+    #[test]
+    fn test_pool_write_read() {
+        let mut ring = IoUring::new(4).unwrap();
+        let mut pool = FixedBufPool::new(&ring.submitter(), 2, 16).unwrap();
+        let [rx, tx] = pipe();
+
+        let mut a = pool.alloc().unwrap();
+        let b = pool.alloc().unwrap();
+        assert!(pool.alloc().is_none());
+
+        pool.get_mut(&mut a)[..5].copy_from_slice(b"hello");
+        let index = a.index();
+        let entry = pool.write_fixed(a, 5, types::Fd(tx)).build();
+        assert_eq!(run(&mut ring, entry), 5);
+        let a = pool.complete(index);
+
+        let index = b.index();
+        let entry = pool.read_fixed(b, types::Fd(rx)).build();
+        assert_eq!(run(&mut ring, entry), 5);
+        let b = pool.complete(index);
+        assert_eq!(&pool.get(&b)[..5], b"hello");
+
+        // Released buffers are handed out again.
+        pool.release(a);
+        pool.release(b);
+        assert!(pool.alloc().is_some());
+        pool.unregister(&ring.submitter()).unwrap();
+        close([rx, tx]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the buffer is not in flight")]
+    fn test_pool_complete_twice() {
+        let ring = IoUring::new(2).unwrap();
+        let mut pool = FixedBufPool::new(&ring.submitter(), 1, 16).unwrap();
+
+        let buf = pool.alloc().unwrap();
+        let index = buf.index();
+        let _ = pool.read_fixed(buf, types::Fd(0));
+        pool.complete(index);
+        pool.complete(index);
+    }
+
+    #[test]
+    fn test_file_set() {
+        let ring = IoUring::new(2).unwrap();
+        let submitter = ring.submitter();
+        let mut files = FixedFileSet::new(&submitter, 2).unwrap();
+        let fds = pipe();
+
+        let slot = files.insert(&submitter, fds[0], 0).unwrap();
+        let empty = files.alloc().unwrap();
+        assert_eq!(files.len(), 2);
+
+        let err = files.insert(&submitter, fds[1], 0).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENFILE));
+
+        files.free(empty);
+        files.remove(&submitter, slot).unwrap();
+        assert!(files.is_empty());
+        assert_eq!(files.insert(&submitter, fds[1], 0).unwrap().0, slot.0);
+        close(fds);
+    }
+
+    #[test]
+    #[should_panic(expected = "the buffer belongs to another pool")]
+    fn test_pool_rejects_foreign_buf() {
+        let (ring, other) = (
+            crate::IoUring::new(2).unwrap(),
+            crate::IoUring::new(2).unwrap(),
+        );
+        let mut pool = FixedBufPool::new(&ring.submitter(), 4, 16).unwrap();
+        let mut other = FixedBufPool::new(&other.submitter(), 1, 16).unwrap();
+
+        let mut buf = pool.alloc().unwrap();
+        assert_eq!(buf.index(), 0);
+        other.get_mut(&mut buf);
+    }
+}
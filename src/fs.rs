@@ -0,0 +1,187 @@
+//! Blocking file system helpers.
+//!
+//! Each function submits its requests to a ring private to the calling thread,
+//! and waits for them to complete before it returns, so they are safe to call.
+//! Requests that do not depend on each other are submitted together.
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::Path;
+use std::{cmp, fs, io, mem};
+
+use crate::blocking::{cvt, run};
+use crate::opcode::{self, types};
+
+/// The size of the first read of [read_to_vec], submitted before the file size is known.
+const FIRST_READ: usize = 16 * 1024;
+
+fn cstr(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a nul byte"))
+}
+
+/// Open a file, like `openat(2)` relative to the current directory.
+///
+/// `O_CLOEXEC` is always added to `flags`.
+pub fn open<P: AsRef<Path>>(path: P, flags: i32, mode: libc::mode_t) -> io::Result<fs::File> {
+    let path = cstr(path.as_ref())?;
+    let entry = opcode::Openat::new(types::Fd(libc::AT_FDCWD), path.as_ptr())
+        .flags(flags | libc::O_CLOEXEC)
+        .mode(mode)
+        .build();
+
+    let [ret] = unsafe { run([entry])? };
+    let fd = cvt(ret)?;
+    Ok(unsafe { fs::File::from_raw_fd(fd as _) })
+}
+
+/// Query the metadata of a file, like `statx(2)`, following symbolic links.
+pub fn statx<P: AsRef<Path>>(path: P) -> io::Result<libc::statx> {
+    let path = cstr(path.as_ref())?;
+    let mut statx = unsafe { mem::zeroed::<libc::statx>() };
+    let entry = opcode::Statx::new(
+        types::Fd(libc::AT_FDCWD),
+        path.as_ptr(),
+        &mut statx as *mut libc::statx as *mut types::statx,
+    )
+    .mask(libc::STATX_BASIC_STATS)
+    .build();
+
+    let [ret] = unsafe { run([entry])? };
+    cvt(ret)?;
+    Ok(statx)
+}
+
+/// Read a file from its start to its end.
+///
+/// The size of the file and its first bytes are requested together,
+/// then the file is read until a read returns no bytes.
+pub fn read_to_vec(file: &fs::File) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0; FIRST_READ];
+    let mut statx = unsafe { mem::zeroed::<libc::statx>() };
+
+    let entries = [
+        opcode::Statx::new(
+            types::Fd(file.as_raw_fd()),
+            b"\0".as_ptr().cast(),
+            &mut statx as *mut libc::statx as *mut types::statx,
+        )
+        .flags(libc::AT_EMPTY_PATH)
+        .mask(libc::STATX_SIZE)
+        .build(),
+        opcode::Read::new(
+            types::Fd(file.as_raw_fd()),
+            buf.as_mut_ptr(),
+            buf.len() as _,
+        )
+        .build(),
+    ];
+
+    let [stat, read] = unsafe { run(entries)? };
+    cvt(stat)?;
+    let mut len = cvt(read)? as usize;
+
+    // A short read is not the end of the file, and files in `/proc` and the like
+    // report no size, so the file is read until a read returns no bytes.
+    if len != 0 {
+        let size = cmp::max(statx.stx_size as usize, len) + 1;
+        if size > buf.len() {
+            buf.resize(size, 0);
+        }
+
+        loop {
+            if len == buf.len() {
+                buf.resize(len * 2, 0);
+            }
+
+            let spare = &mut buf[len..];
+            let entry = opcode::Read::new(
+                types::Fd(file.as_raw_fd()),
+                spare.as_mut_ptr(),
+                spare.len() as _,
+            )
+            .offset(len as _)
+            .build();
+
+            let [ret] = unsafe { run([entry])? };
+            match cvt(ret)? {
+                0 => break,
+                n => len += n as usize,
+            }
+        }
+    }
+
+    buf.truncate(len);
+    Ok(buf)
+}
+
+/// Write all of `buf` to a file at `offset`.
+pub fn write_all(file: &fs::File, mut buf: &[u8], mut offset: u64) -> io::Result<()> {
+    while !buf.is_empty() {
+        let len = cmp::min(buf.len(), u32::MAX as usize);
+        let entry = opcode::Write::new(types::Fd(file.as_raw_fd()), buf.as_ptr(), len as _)
+            .offset(offset as _)
+            .build();
+
+        let [ret] = unsafe { run([entry])? };
+        match cvt(ret)? {
+            0 => return Err(io::ErrorKind::WriteZero.into()),
+            n => {
+                buf = &buf[n as usize..];
+                offset += n as u64;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Flush the data and metadata of a file to the disk, like `fsync(2)`.
+pub fn fsync(file: &fs::File) -> io::Result<()> {
+    let entry = opcode::Fsync::new(types::Fd(file.as_raw_fd())).build();
+
+    let [ret] = unsafe { run([entry])? };
+    cvt(ret).map(drop)
+}
+
+/// Flush the data of a file to the disk, like `fdatasync(2)`.
+pub fn fdatasync(file: &fs::File) -> io::Result<()> {
+    let entry = opcode::Fsync::new(types::Fd(file.as_raw_fd()))
+        .flags(types::FsyncFlags::DATASYNC)
+        .build();
+
+    let [ret] = unsafe { run([entry])? };
+    cvt(ret).map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_to_vec_large() {
+        let file = tempfile::tempfile().unwrap();
+        let data = (0..3 * FIRST_READ + 7).map(|i| i as u8).collect::<Vec<_>>();
+        write_all(&file, &data, 0).unwrap();
+
+        assert_eq!(read_to_vec(&file).unwrap(), data);
+    }
+
+    #[test]
+    fn test_read_to_vec_empty() {
+        let file = tempfile::tempfile().unwrap();
+        assert!(read_to_vec(&file).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_to_vec_proc() {
+        let path = "/proc/self/cmdline";
+        assert_eq!(statx(path).unwrap().stx_size, 0);
+
+        let file = open(path, libc::O_RDONLY, 0).unwrap();
+        let data = read_to_vec(&file).unwrap();
+        assert!(!data.is_empty());
+        assert_eq!(data, fs::read(path).unwrap());
+    }
+}
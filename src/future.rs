@@ -0,0 +1,668 @@
+//! Futures of requests that own their resources.
+//!
+//! The requests of this module are submitted to a [Driver], and resolve to their result
+//! along with the resources they were given, such as buffers,
+//! so they can be awaited without a runtime integration written by hand.
+//! [File] does positional reads and writes, with the registered buffers of a [BufPool]
+//! or with plain buffers.
+//! [AcceptStream] yields the connections of a listening socket.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::net::{TcpListener, TcpStream};
+use std::ops::{Deref, DerefMut};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::{fs, io, slice};
+
+use crate::driver::{Driver, MultiHandle, OpHandle};
+use crate::fixed::{FixedBuf, FixedBufPool};
+use crate::opcode::{self, types};
+use crate::squeue;
+
+/// A request in flight, resolves to its result and its resources.
+///
+/// The resources are handed back even if the request failed, so a buffer can be reused.
+/// Dropping the future cancels the request, and its resources are kept
+/// by the driver until the request has completed.
+pub struct Op<R: 'static> {
+    handle: OpHandle,
+    resources: Option<R>,
+}
+
+impl<R: Unpin + 'static> Future for Op<R> {
+    type Output = (io::Result<u32>, R);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        match Pin::new(&mut this.handle).poll(cx) {
+            Poll::Ready(entry) => {
+                let resources = this
+                    .resources
+                    .take()
+                    .expect("the future has already resolved");
+                Poll::Ready((entry.result_as_io(), resources))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<R: 'static> Drop for Op<R> {
+    fn drop(&mut self) {
+        if let Some(resources) = self.resources.take() {
+            self.handle.keep(Box::new(resources));
+        }
+    }
+}
+
+impl Driver {
+    /// Submit an entry, and keep `resources` until it completes.
+    ///
+    /// # Safety
+    ///
+    /// Developers must ensure that parameters of the entry (such as buffer) are valid,
+    /// otherwise it may cause memory problems.
+    /// Memory owned by `resources` that does not move with it,
+    /// like the contents of a `Vec`, stays valid until the request completes.
+    pub unsafe fn submit_with<R: 'static>(
+        &self,
+        entry: squeue::Entry,
+        resources: R,
+    ) -> io::Result<Op<R>> {
+        let handle = self.submit(entry)?;
+
+        Ok(Op {
+            handle,
+            resources: Some(resources),
+        })
+    }
+
+    /// Read into `buf` at `offset`, like `pread(2)`,
+    /// resolves to the number of bytes read and the buffer.
+    ///
+    /// A registered buffer is read into with [ReadFixed](opcode::ReadFixed).
+    pub fn read<B: IoBufMut>(
+        &self,
+        fd: RawFd,
+        mut buf: B,
+        offset: libc::off_t,
+    ) -> io::Result<Op<B>> {
+        let (ptr, len) = (buf.stable_mut_ptr(), buf.bytes_total() as _);
+        let entry = match buf.buf_index() {
+            Some(index) => opcode::ReadFixed::new(types::Fd(fd), ptr, len, index)
+                .offset(offset)
+                .build(),
+            None => opcode::Read::new(types::Fd(fd), ptr, len)
+                .offset(offset)
+                .build(),
+        };
+
+        unsafe { self.submit_with(entry, buf) }
+    }
+
+    /// Write `buf` at `offset`, like `pwrite(2)`,
+    /// resolves to the number of bytes written and the buffer.
+    ///
+    /// A registered buffer is written with [WriteFixed](opcode::WriteFixed).
+    pub fn write<B: IoBuf>(&self, fd: RawFd, buf: B, offset: libc::off_t) -> io::Result<Op<B>> {
+        let (ptr, len) = (buf.stable_ptr(), buf.bytes_init() as _);
+        let entry = match buf.buf_index() {
+            Some(index) => opcode::WriteFixed::new(types::Fd(fd), ptr, len, index)
+                .offset(offset)
+                .build(),
+            None => opcode::Write::new(types::Fd(fd), ptr, len)
+                .offset(offset)
+                .build(),
+        };
+
+        unsafe { self.submit_with(entry, buf) }
+    }
+
+    /// Receive into `buf`, like `recv(2)`,
+    /// resolves to the number of bytes received and the buffer.
+    pub fn recv<B: IoBufMut>(&self, fd: RawFd, mut buf: B) -> io::Result<Op<B>> {
+        let (ptr, len) = (buf.stable_mut_ptr(), buf.bytes_total() as _);
+        let entry = opcode::Recv::new(types::Fd(fd), ptr, len).build();
+
+        unsafe { self.submit_with(entry, buf) }
+    }
+
+    /// Send `buf`, like `send(2)`,
+    /// resolves to the number of bytes sent and the buffer.
+    pub fn send<B: IoBuf>(&self, fd: RawFd, buf: B) -> io::Result<Op<B>> {
+        let (ptr, len) = (buf.stable_ptr(), buf.bytes_init() as _);
+        let entry = opcode::Send::new(types::Fd(fd), ptr, len).build();
+
+        unsafe { self.submit_with(entry, buf) }
+    }
+
+    /// Run a future to completion on the current thread,
+    /// parking on the driver whenever the future is pending.
+    pub fn block_on<F: Future>(&self, future: F) -> io::Result<F::Output> {
+        let woken = Arc::new(Woken(AtomicBool::new(false)));
+        let waker = Waker::from(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut future = Box::pin(future);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return Ok(output);
+            }
+
+            if !woken.0.swap(false, Ordering::Acquire) {
+                self.park()?;
+            }
+        }
+    }
+}
+
+struct Woken(AtomicBool);
+
+impl Wake for Woken {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+/// A buffer that can be written from while a request is in flight.
+///
+/// # Safety
+///
+/// The memory behind [IoBuf::stable_ptr] must not move with the buffer,
+/// and stay valid until the buffer is dropped.
+pub unsafe trait IoBuf: Unpin + 'static {
+    /// A pointer to the start of the buffer.
+    fn stable_ptr(&self) -> *const u8;
+
+    /// The number of initialized bytes, which are written.
+    fn bytes_init(&self) -> usize;
+
+    /// The index of the buffer in the registered buffers, if it is one of them.
+    fn buf_index(&self) -> Option<u16> {
+        None
+    }
+}
+
+/// A buffer that can be read into while a request is in flight.
+///
+/// # Safety
+///
+/// The memory behind [IoBufMut::stable_mut_ptr] must not move with the buffer,
+/// stay valid until the buffer is dropped, and have room for [IoBufMut::bytes_total] bytes.
+pub unsafe trait IoBufMut: IoBuf {
+    /// A pointer to the start of the buffer.
+    fn stable_mut_ptr(&mut self) -> *mut u8;
+
+    /// The number of bytes that are read into.
+    fn bytes_total(&self) -> usize;
+}
+
+unsafe impl IoBuf for Vec<u8> {
+    fn stable_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len()
+    }
+}
+
+/// A `Vec` is read into up to its length, not its capacity.
+unsafe impl IoBufMut for Vec<u8> {
+    fn stable_mut_ptr(&mut self) -> *mut u8 {
+        self.as_mut_ptr()
+    }
+
+    fn bytes_total(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(feature = "bytes")]
+unsafe impl IoBuf for bytes::Bytes {
+    fn stable_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(feature = "bytes")]
+unsafe impl IoBuf for bytes::BytesMut {
+    fn stable_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Like a `Vec`, a `BytesMut` is read into up to its length, not its capacity.
+#[cfg(feature = "bytes")]
+unsafe impl IoBufMut for bytes::BytesMut {
+    fn stable_mut_ptr(&mut self) -> *mut u8 {
+        self.as_mut_ptr()
+    }
+
+    fn bytes_total(&self) -> usize {
+        self.len()
+    }
+}
+
+/// A pool of registered buffers, shared by the requests of a driver.
+///
+/// Dropping the pool leaks the memory, like [FixedBufPool].
+#[derive(Clone)]
+pub struct BufPool {
+    pool: Rc<RefCell<FixedBufPool>>,
+}
+
+/// A buffer of a [BufPool], it returns to the pool when dropped.
+///
+/// Like a `Vec`, the buffer is read into and written up to its length,
+/// which starts as the size of the buffers of the pool.
+pub struct PooledBuf {
+    pool: Rc<RefCell<FixedBufPool>>,
+    buf: Option<FixedBuf>,
+    ptr: *mut u8,
+    len: usize,
+    capacity: usize,
+}
+
+impl BufPool {
+    /// Allocate `count` buffers of `buf_len` bytes and register them on the ring of `driver`.
+    pub fn new(driver: &Driver, count: u16, buf_len: usize) -> io::Result<BufPool> {
+        let pool = FixedBufPool::new(&driver.ring().submitter(), count, buf_len)?;
+
+        Ok(BufPool {
+            pool: Rc::new(RefCell::new(pool)),
+        })
+    }
+
+    /// Take a free buffer, returns `None` if they are all in use.
+    pub fn get(&self) -> Option<PooledBuf> {
+        let mut pool = self.pool.borrow_mut();
+        let mut buf = pool.alloc()?;
+        let bytes = pool.get_mut(&mut buf);
+        let (ptr, len) = (bytes.as_mut_ptr(), bytes.len());
+        drop(pool);
+
+        Some(PooledBuf {
+            pool: self.pool.clone(),
+            buf: Some(buf),
+            ptr,
+            len,
+            capacity: len,
+        })
+    }
+}
+
+impl PooledBuf {
+    /// The size of the buffer.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Set the length of the buffer, its bytes are always initialized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is larger than the capacity.
+    pub fn set_len(&mut self, len: usize) {
+        assert!(len <= self.capacity, "length past the end of the buffer");
+        self.len = len;
+    }
+}
+
+impl Deref for PooledBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl DerefMut for PooledBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+unsafe impl IoBuf for PooledBuf {
+    fn stable_ptr(&self) -> *const u8 {
+        self.ptr
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len
+    }
+
+    fn buf_index(&self) -> Option<u16> {
+        self.buf.as_ref().map(FixedBuf::index)
+    }
+}
+
+unsafe impl IoBufMut for PooledBuf {
+    fn stable_mut_ptr(&mut self) -> *mut u8 {
+        self.ptr
+    }
+
+    fn bytes_total(&self) -> usize {
+        self.len
+    }
+}
+
+impl Drop for PooledBuf {
+    fn drop(&mut self) {
+        if let Some(buf) = self.buf.take() {
+            self.pool.borrow_mut().release(buf);
+        }
+    }
+}
+
+/// A file whose positional reads and writes go through a driver.
+pub struct File {
+    driver: Driver,
+    file: fs::File,
+}
+
+impl File {
+    pub fn from_std(driver: &Driver, file: fs::File) -> File {
+        File {
+            driver: driver.clone(),
+            file,
+        }
+    }
+
+    /// Read into `buf` at `offset`, see [Driver::read].
+    ///
+    /// With a [PooledBuf], the read uses the registered buffer.
+    pub fn read_at<B: IoBufMut>(&self, buf: B, offset: u64) -> io::Result<Op<B>> {
+        self.driver.read(self.file.as_raw_fd(), buf, offset as _)
+    }
+
+    /// Write `buf` at `offset`, see [Driver::write].
+    ///
+    /// With a [PooledBuf], the write uses the registered buffer.
+    pub fn write_at<B: IoBuf>(&self, buf: B, offset: u64) -> io::Result<Op<B>> {
+        self.driver.write(self.file.as_raw_fd(), buf, offset as _)
+    }
+
+    pub fn into_std(self) -> fs::File {
+        self.file
+    }
+}
+
+/// A connection accepted by an [AcceptStream].
+#[derive(Debug)]
+pub enum Accepted {
+    /// The socket of the connection.
+    Stream(TcpStream),
+    /// The slot of the registered files table the socket was installed in.
+    Direct(types::Fixed),
+}
+
+/// The connections of a listening socket, accepted with a multishot [AcceptMulti](opcode::AcceptMulti).
+///
+/// When the kernel terminates the multishot request, e.g. after an error,
+/// it is issued again on the next poll. The stream only ends if the request is canceled,
+/// and yields `None` from then on.
+pub struct AcceptStream {
+    driver: Driver,
+    fd: RawFd,
+    direct: bool,
+    handle: Option<MultiHandle>,
+    ended: bool,
+}
+
+impl AcceptStream {
+    /// Accept the connections of `listener`, which must outlive the stream.
+    pub fn new(driver: &Driver, listener: &TcpListener) -> AcceptStream {
+        AcceptStream {
+            driver: driver.clone(),
+            fd: listener.as_raw_fd(),
+            direct: false,
+            handle: None,
+            ended: false,
+        }
+    }
+
+    /// Like [AcceptStream::new], but install the sockets as direct descriptors,
+    /// in free slots of the registered files table. Requires Linux 5.19.
+    pub fn direct(driver: &Driver, listener: &TcpListener) -> AcceptStream {
+        AcceptStream {
+            direct: true,
+            ..AcceptStream::new(driver, listener)
+        }
+    }
+
+    /// Poll for the next connection.
+    pub fn poll_accept(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Accepted>>> {
+        loop {
+            if self.ended {
+                return Poll::Ready(None);
+            }
+
+            let handle = match &mut self.handle {
+                Some(handle) => handle,
+                None => {
+                    // Direct descriptors have no close-on-exec flag to set.
+                    let flags = if self.direct { 0 } else { libc::SOCK_CLOEXEC };
+                    let entry = opcode::AcceptMulti::new(types::Fd(self.fd))
+                        .flags(flags as _)
+                        .allocate_file_index(self.direct)
+                        .build();
+
+                    // The entry only refers to the listening socket.
+                    match unsafe { self.driver.submit_multishot(entry) } {
+                        Ok(handle) => self.handle.insert(handle),
+                        Err(err) => return Poll::Ready(Some(Err(err))),
+                    }
+                }
+            };
+
+            let entry = match handle.poll_next(cx) {
+                Poll::Ready(Some(entry)) => entry,
+                Poll::Ready(None) => {
+                    self.handle = None;
+                    continue;
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if !entry.is_more() {
+                self.handle = None;
+            }
+
+            return Poll::Ready(match entry.result_as_io() {
+                Ok(fd) if self.direct => Some(Ok(Accepted::Direct(types::Fixed(fd)))),
+                Ok(fd) => Some(Ok(Accepted::Stream(unsafe {
+                    TcpStream::from_raw_fd(fd as _)
+                }))),
+                Err(err) if err.raw_os_error() == Some(libc::ECANCELED) => {
+                    self.ended = true;
+                    None
+                }
+                Err(err) => Some(Err(err)),
+            });
+        }
+    }
+
+    /// Accept the next connection, resolves to `None` once the stream has ended.
+    pub async fn accept(&mut self) -> Option<io::Result<Accepted>> {
+        std::future::poll_fn(|cx| self.poll_accept(cx)).await
+    }
+}
+
+impl futures_core::Stream for AcceptStream {
+    type Item = io::Result<Accepted>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_accept(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixed::FixedFileSet;
+
+    #[test]
+    fn test_op_returns_buf_on_failure() {
+        let driver = Driver::new(4).unwrap();
+
+        let op = driver.read(-1, vec![0; 8], 0).unwrap();
+        let (ret, buf) = driver.block_on(op).unwrap();
+        assert_eq!(ret.unwrap_err().raw_os_error(), Some(libc::EBADF));
+        assert_eq!(buf.len(), 8);
+    }
+
+    #[test]
+    fn test_file_plain_read_write() {
+        let driver = Driver::new(4).unwrap();
+        let file = File::from_std(&driver, tempfile::tempfile().unwrap());
+
+        let op = file.write_at(b"hello world".to_vec(), 0).unwrap();
+        let (ret, _) = driver.block_on(op).unwrap();
+        assert_eq!(ret.unwrap(), 11);
+
+        let op = file.read_at(vec![0; 5], 6).unwrap();
+        let (ret, buf) = driver.block_on(op).unwrap();
+        assert_eq!(ret.unwrap(), 5);
+        assert_eq!(buf, b"world");
+    }
+
+    #[test]
+    fn test_file_pooled_read_write() {
+        let driver = Driver::new(4).unwrap();
+        let pool = BufPool::new(&driver, 1, 16).unwrap();
+        let file = File::from_std(&driver, tempfile::tempfile().unwrap());
+
+        let mut buf = pool.get().unwrap();
+        assert_eq!(buf.buf_index(), Some(0));
+        assert_eq!(buf.len(), 16);
+        buf[..5].copy_from_slice(b"hello");
+        buf.set_len(5);
+
+        let op = file.write_at(buf, 0).unwrap();
+        let (ret, buf) = driver.block_on(op).unwrap();
+        assert_eq!(ret.unwrap(), 5);
+
+        // The only buffer is still held.
+        assert!(pool.get().is_none());
+        drop(buf);
+
+        let mut buf = pool.get().unwrap();
+        assert_eq!(buf.buf_index(), Some(0));
+        buf.set_len(3);
+        let op = file.read_at(buf, 1).unwrap();
+        let (ret, buf) = driver.block_on(op).unwrap();
+        assert_eq!(ret.unwrap(), 3);
+        assert_eq!(&buf[..], b"ell");
+
+        // Without registered buffers, only a fixed read fails,
+        // which shows that a pooled buffer is read into with ReadFixed.
+        driver.ring().submitter().unregister_buffers().unwrap();
+        let op = file.read_at(buf, 0).unwrap();
+        let (ret, buf) = driver.block_on(op).unwrap();
+        assert_eq!(ret.unwrap_err().raw_os_error(), Some(libc::EFAULT));
+        let op = file.write_at(buf, 0).unwrap();
+        let (ret, _) = driver.block_on(op).unwrap();
+        assert_eq!(ret.unwrap_err().raw_os_error(), Some(libc::EFAULT));
+
+        let op = file.read_at(vec![0; 5], 0).unwrap();
+        let (ret, _) = driver.block_on(op).unwrap();
+        assert_eq!(ret.unwrap(), 5);
+    }
+
+    fn listen() -> (TcpListener, std::net::SocketAddr) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        (listener, addr)
+    }
+
+    /// Cancel the multishot request of the stream.
+    fn cancel(driver: &Driver, stream: &AcceptStream) {
+        let key = stream.handle.as_ref().expect("no request in flight").key();
+        let entry = opcode::AsyncCancel::new(key).build();
+        let op = unsafe { driver.submit(entry) }.unwrap();
+        driver.block_on(op).unwrap();
+    }
+
+    #[test]
+    fn test_accept_ends_on_cancel() {
+        let driver = Driver::new(8).unwrap();
+        let (listener, addr) = listen();
+        let mut stream = AcceptStream::new(&driver, &listener);
+
+        let _clients = [
+            TcpStream::connect(addr).unwrap(),
+            TcpStream::connect(addr).unwrap(),
+        ];
+        for _ in 0..2 {
+            match driver.block_on(stream.accept()).unwrap() {
+                Some(Ok(Accepted::Stream(socket))) => {
+                    assert_eq!(socket.local_addr().unwrap(), addr)
+                }
+                other => panic!("unexpected accept: {:?}", other),
+            }
+        }
+
+        // Once canceled, the stream stays ended instead of issuing a new request.
+        cancel(&driver, &stream);
+        assert!(driver.block_on(stream.accept()).unwrap().is_none());
+        assert!(driver.block_on(stream.accept()).unwrap().is_none());
+        assert!(stream.handle.is_none());
+        assert!(driver.is_empty());
+    }
+
+    #[test]
+    fn test_accept_direct_rearms() {
+        let driver = Driver::new(8).unwrap();
+        FixedFileSet::new(&driver.ring().submitter(), 1).unwrap();
+        let (listener, addr) = listen();
+        let mut stream = AcceptStream::direct(&driver, &listener);
+
+        let _first = TcpStream::connect(addr).unwrap();
+        let slot = match driver.block_on(stream.accept()).unwrap() {
+            Some(Ok(Accepted::Direct(slot))) => slot,
+            other => panic!("unexpected accept: {:?}", other),
+        };
+        assert_eq!(slot.0, 0);
+
+        // The table is full, so the kernel fails the accept and terminates the request.
+        let _second = TcpStream::connect(addr).unwrap();
+        match driver.block_on(stream.accept()).unwrap() {
+            Some(Err(err)) => assert_eq!(err.raw_os_error(), Some(libc::ENFILE)),
+            other => panic!("unexpected accept: {:?}", other),
+        }
+        assert!(stream.handle.is_none());
+
+        // The next poll issues the request again, which accepts into the freed slot.
+        driver
+            .ring()
+            .submitter()
+            .register_files_update(0, &[-1])
+            .unwrap();
+        let _third = TcpStream::connect(addr).unwrap();
+        match driver.block_on(stream.accept()).unwrap() {
+            Some(Ok(Accepted::Direct(slot))) => assert_eq!(slot.0, 0),
+            other => panic!("unexpected accept: {:?}", other),
+        }
+
+        cancel(&driver, &stream);
+        assert!(driver.block_on(stream.accept()).unwrap().is_none());
+        assert!(driver.block_on(stream.accept()).unwrap().is_none());
+    }
+}
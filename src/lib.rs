@@ -2,37 +2,128 @@
 //!
 //! The crate only provides a summary of the parameters.
 //! For more detailed documentation, see manpage.
+//!
+//! # Safety
+//!
+//! Pushing an entry into the submission queue is unsafe, because the kernel uses the memory
+//! and the file descriptors the entry refers to until the request completes,
+//! which may be long after it was pushed or submitted.
+//! The entry must stay valid until its completion event has been posted,
+//! which the kernel may still do after the ring is dropped.
+//!
+//! With the `unstable` feature, the operations of the `owned` module own the memory
+//! of their requests, and are pushed safely with `owned::Ops`, which keeps them
+//! until they complete.
 
 #[macro_use]
 mod util;
+#[cfg(feature = "unstable")]
+mod blocking;
+#[cfg(feature = "unstable")]
+pub mod buf;
+#[cfg(feature = "unstable")]
+pub mod cancel;
 pub mod cqueue;
+#[cfg(feature = "unstable")]
+pub mod dispatch;
+#[cfg(feature = "unstable")]
+pub mod driver;
+#[cfg(any(
+    feature = "unstable",
+    feature = "concurrent",
+    feature = "tokio",
+    feature = "mio"
+))]
+pub mod eventfd;
+#[cfg(feature = "unstable")]
+pub mod fixed;
+#[cfg(feature = "unstable")]
+pub mod fs;
+#[cfg(feature = "async")]
+pub mod future;
+#[cfg(feature = "unstable")]
+pub mod mailbox;
+#[cfg(feature = "mock")]
+pub mod mock;
+#[cfg(feature = "unstable")]
+pub mod net;
 pub mod opcode;
+#[cfg(feature = "unstable")]
+pub mod owned;
+#[cfg(feature = "unstable")]
+pub mod personality;
+#[cfg(feature = "unstable")]
+pub mod process;
 mod register;
+mod split;
 pub mod squeue;
+#[cfg(feature = "stats")]
+mod stats;
 mod submit;
-mod sys;
+mod sync;
+pub mod sys;
+#[cfg(feature = "unstable")]
+pub mod timer;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+#[cfg(feature = "tracing")]
+mod trace;
+#[cfg(feature = "unstable")]
+pub mod tracker;
+#[cfg(feature = "unstable")]
+pub mod ublk;
+#[cfg(feature = "unstable")]
+pub mod zerocopy;
 
 #[cfg(feature = "concurrent")]
 pub mod concurrent;
 
-use std::convert::TryInto;
+#[cfg(feature = "shards")]
+pub mod shards;
+
 use std::mem::ManuallyDrop;
-use std::os::unix::io::{AsRawFd, RawFd};
-use std::{cmp, io, mem};
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::{cmp, io, mem, ptr};
 
 pub use cqueue::CompletionQueue;
+#[cfg(feature = "unstable")]
+pub use register::Capabilities;
 pub use register::Probe;
-pub use squeue::SubmissionQueue;
-pub use submit::Submitter;
+pub use split::{CompletionHalf, SubmissionHalf};
+pub use squeue::{SubmissionQueue, UserData};
+#[cfg(feature = "stats")]
+pub use stats::Stats;
+pub use submit::{BatchedSubmitter, SubmitError, Submitter};
 use util::{Fd, Mmap};
 
+use std::time::Duration;
+
+#[cfg(feature = "unstable")]
+use util::cast_ptr;
+
 /// IoUring instance
+///
+/// Dropping the ring closes its file descriptor, then unmaps the queues.
+/// The kernel cancels the requests still in flight once the ring is closed,
+/// but they may complete after the drop returned, so their memory must outlive the ring.
+/// With the `unstable` feature, `IoUring::shutdown`, or `Builder::shutdown_on_drop`
+/// when the ring is dropped, waits for them to complete first.
 pub struct IoUring {
-    fd: Fd,
+    fd: ManuallyDrop<Fd>,
     params: Parameters,
     memory: ManuallyDrop<MemoryMap>,
     sq: SubmissionQueue,
     cq: CompletionQueue,
+    ring_fd_index: Option<u32>,
+    retry_eintr: bool,
+    #[cfg(feature = "unstable")]
+    shutdown_on_drop: Option<Duration>,
+}
+
+/// Ring memory allocated by us instead of the kernel.
+struct UserMemory {
+    rings: Mmap,
+    sqes: Mmap,
 }
 
 #[allow(dead_code)]
@@ -46,6 +137,15 @@ struct MemoryMap {
 #[derive(Clone, Default)]
 pub struct Builder {
     dontfork: bool,
+    #[cfg(feature = "unstable")]
+    numa_node: Option<u32>,
+    #[cfg(feature = "unstable")]
+    iowq_max_workers: Option<[u32; 2]>,
+    #[cfg(feature = "unstable")]
+    iowq_aff: Option<libc::cpu_set_t>,
+    dont_retry_eintr: bool,
+    #[cfg(feature = "unstable")]
+    shutdown_on_drop: Option<Duration>,
     params: sys::io_uring_params,
 }
 
@@ -58,78 +158,164 @@ unsafe impl Sync for IoUring {}
 impl IoUring {
     /// Create a IoUring instance
     ///
-    /// The `entries` sets the size of queue,
-    /// and it value should be the power of two.
+    /// The `entries` sets the size of queue, which the kernel rounds up to a power of two.
+    /// It must not be zero, nor larger than 32768 unless the ring is built with
+    /// [Builder::setup_clamp], an [InvalidInput](io::ErrorKind::InvalidInput) error
+    /// is returned otherwise.
     #[inline]
     pub fn new(entries: u32) -> io::Result<IoUring> {
-        IoUring::with_params(entries, Default::default())
-    }
-
-    fn with_params(entries: u32, mut p: sys::io_uring_params) -> io::Result<IoUring> {
-        // NOTE: The `SubmissionQueue` and `CompletionQueue` are references,
-        // and their lifetime can never exceed `MemoryMap`.
-        //
-        // I really hope that Rust can safely use self-reference types.
-        #[inline]
-        unsafe fn setup_queue(
-            fd: &Fd,
-            p: &sys::io_uring_params,
-        ) -> io::Result<(MemoryMap, SubmissionQueue, CompletionQueue)> {
-            let sq_len = p.sq_off.array as usize + p.sq_entries as usize * mem::size_of::<u32>();
-            let cq_len = p.cq_off.cqes as usize
-                + p.cq_entries as usize * mem::size_of::<sys::io_uring_cqe>();
-            let sqe_len = p.sq_entries as usize * mem::size_of::<sys::io_uring_sqe>();
-            let sqe_mmap = Mmap::new(fd, sys::IORING_OFF_SQES as _, sqe_len)?;
-
-            if p.features & sys::IORING_FEAT_SINGLE_MMAP != 0 {
-                let scq_mmap =
-                    Mmap::new(fd, sys::IORING_OFF_SQ_RING as _, cmp::max(sq_len, cq_len))?;
-
-                let sq = SubmissionQueue::new(&scq_mmap, &sqe_mmap, p);
-                let cq = CompletionQueue::new(&scq_mmap, p);
-                let mm = MemoryMap {
-                    sq_mmap: scq_mmap,
-                    cq_mmap: None,
-                    sqe_mmap,
-                };
+        IoUring::with_params(entries, Default::default(), None)
+    }
 
-                Ok((mm, sq, cq))
-            } else {
-                let sq_mmap = Mmap::new(fd, sys::IORING_OFF_SQ_RING as _, sq_len)?;
-                let cq_mmap = Mmap::new(fd, sys::IORING_OFF_CQ_RING as _, cq_len)?;
+    /// Set up a ring, in `memory` if the rings are allocated by us,
+    /// which is the case with `IORING_SETUP_NO_MMAP`.
+    fn with_params(
+        entries: u32,
+        mut p: sys::io_uring_params,
+        memory: Option<UserMemory>,
+    ) -> io::Result<IoUring> {
+        check_entries(entries, &p)?;
+
+        if let Some(memory) = memory.as_ref() {
+            p.cq_off.user_addr = memory.rings.as_mut_ptr() as _;
+            p.sq_off.user_addr = memory.sqes.as_mut_ptr() as _;
+        }
 
-                let sq = SubmissionQueue::new(&sq_mmap, &sqe_mmap, p);
-                let cq = CompletionQueue::new(&cq_mmap, p);
+        let fd = unsafe { Fd(sys::io_uring_setup(entries, &mut p)?) };
+
+        let (mm, sq, cq) = match memory {
+            Some(UserMemory {
+                rings: sq_mmap,
+                sqes: sqe_mmap,
+            }) => unsafe {
+                let sq = SubmissionQueue::new(&sq_mmap, &sqe_mmap, &p);
+                let cq = CompletionQueue::new(&sq_mmap, &p);
                 let mm = MemoryMap {
-                    cq_mmap: Some(cq_mmap),
                     sq_mmap,
+                    cq_mmap: None,
                     sqe_mmap,
                 };
 
-                Ok((mm, sq, cq))
-            }
+                (mm, sq, cq)
+            },
+            None => unsafe { IoUring::setup_queue(&fd, &p)? },
+        };
+
+        Ok(IoUring {
+            fd: ManuallyDrop::new(fd),
+            sq,
+            cq,
+            params: Parameters(p),
+            memory: ManuallyDrop::new(mm),
+            ring_fd_index: None,
+            retry_eintr: true,
+            #[cfg(feature = "unstable")]
+            shutdown_on_drop: None,
+        })
+    }
+
+    // NOTE: The `SubmissionQueue` and `CompletionQueue` are references,
+    // and their lifetime can never exceed `MemoryMap`.
+    //
+    // I really hope that Rust can safely use self-reference types.
+    unsafe fn setup_queue(
+        fd: &Fd,
+        p: &sys::io_uring_params,
+    ) -> io::Result<(MemoryMap, SubmissionQueue, CompletionQueue)> {
+        let sq_len = p.sq_off.array as usize + p.sq_entries as usize * mem::size_of::<u32>();
+        let mut cqe_len = mem::size_of::<sys::io_uring_cqe>();
+        if p.flags & sys::IORING_SETUP_CQE32 != 0 {
+            cqe_len *= 2;
+        }
+        let cq_len = p.cq_off.cqes as usize + p.cq_entries as usize * cqe_len;
+        let mut sqe_len = p.sq_entries as usize * mem::size_of::<sys::io_uring_sqe>();
+        if p.flags & sys::IORING_SETUP_SQE128 != 0 {
+            sqe_len *= 2;
         }
+        let sqe_mmap = Mmap::new(fd, sys::IORING_OFF_SQES as _, sqe_len)?;
 
-        let fd: Fd = unsafe {
-            sys::io_uring_setup(entries, &mut p)
-                .try_into()
-                .map_err(|_| io::Error::last_os_error())?
-        };
+        if p.features & sys::IORING_FEAT_SINGLE_MMAP != 0 {
+            let scq_mmap = Mmap::new(fd, sys::IORING_OFF_SQ_RING as _, cmp::max(sq_len, cq_len))?;
 
-        let (mm, sq, cq) = unsafe { setup_queue(&fd, &p)? };
+            let sq = SubmissionQueue::new(&scq_mmap, &sqe_mmap, p);
+            let cq = CompletionQueue::new(&scq_mmap, p);
+            let mm = MemoryMap {
+                sq_mmap: scq_mmap,
+                cq_mmap: None,
+                sqe_mmap,
+            };
+
+            Ok((mm, sq, cq))
+        } else {
+            let sq_mmap = Mmap::new(fd, sys::IORING_OFF_SQ_RING as _, sq_len)?;
+            let cq_mmap = Mmap::new(fd, sys::IORING_OFF_CQ_RING as _, cq_len)?;
+
+            let sq = SubmissionQueue::new(&sq_mmap, &sqe_mmap, p);
+            let cq = CompletionQueue::new(&cq_mmap, p);
+            let mm = MemoryMap {
+                cq_mmap: Some(cq_mmap),
+                sq_mmap,
+                sqe_mmap,
+            };
+
+            Ok((mm, sq, cq))
+        }
+    }
+
+    /// Take over a ring from its file descriptor and parameters,
+    /// such as those released by [IoUring::into_raw_parts], e.g. before an `exec`.
+    ///
+    /// The rings are mapped again, and the entries already in the queues are kept.
+    ///
+    /// # Safety
+    ///
+    /// `fd` must be an io_uring instance set up with `params`, which is owned by the returned ring.
+    /// Rings set up with `IORING_SETUP_NO_MMAP` cannot be mapped again.
+    pub unsafe fn from_raw_parts(fd: RawFd, params: Parameters) -> io::Result<IoUring> {
+        let fd = Fd(fd);
+        let (mm, sq, cq) = IoUring::setup_queue(&fd, &params.0)?;
 
         Ok(IoUring {
-            fd,
+            fd: ManuallyDrop::new(fd),
             sq,
             cq,
-            params: Parameters(p),
+            params,
             memory: ManuallyDrop::new(mm),
+            ring_fd_index: None,
+            retry_eintr: true,
+            #[cfg(feature = "unstable")]
+            shutdown_on_drop: None,
         })
     }
 
+    /// Release the file descriptor of the ring, together with its parameters,
+    /// to take the ring over later with [IoUring::from_raw_parts].
+    ///
+    /// The rings are unmapped, but the instance lives on with its requests in flight
+    /// until the file descriptor is closed.
+    pub fn into_raw_parts(self) -> (RawFd, Parameters) {
+        let mut ring = ManuallyDrop::new(self);
+
+        // Everything but the file descriptor is dropped, without the shutdown of `drop`.
+        unsafe {
+            ManuallyDrop::drop(&mut ring.memory);
+            ptr::drop_in_place(&mut ring.sq);
+            ptr::drop_in_place(&mut ring.cq);
+            let fd = ManuallyDrop::into_inner(ptr::read(&ring.fd));
+            (fd.into_raw_fd(), ring.params.clone())
+        }
+    }
+
     #[inline]
     pub fn submitter(&self) -> Submitter<'_> {
-        Submitter::new(&self.fd, self.params.0.flags, &self.sq)
+        Submitter::new(
+            &self.fd,
+            &self.params,
+            self.ring_fd_index,
+            self.retry_eintr,
+            &self.sq,
+            &self.cq,
+        )
     }
 
     #[inline]
@@ -137,6 +323,43 @@ impl IoUring {
         &self.params
     }
 
+    /// Report what the running kernel supports, from the parameters of the ring,
+    /// a probe of the operations, and trial registrations,
+    /// so that the code paths can be chosen once.
+    #[cfg(feature = "unstable")]
+    pub fn capabilities(&self) -> Capabilities {
+        let submitter = self.submitter();
+
+        // The probe is left empty if it is not supported.
+        let mut probe = Probe::new();
+        let _ = submitter.register_probe(&mut probe);
+
+        // A group that is already registered is supported too.
+        const TRIAL_BGID: u16 = u16::MAX;
+        let buf_ring = Mmap::new_anon(4096)
+            .map(|ring| unsafe {
+                match submitter.register_buf_ring(ring.as_mut_ptr() as _, 1, TRIAL_BGID) {
+                    Ok(()) => submitter.unregister_buf_ring(TRIAL_BGID).is_ok(),
+                    Err(err) => err.raw_os_error() == Some(libc::EEXIST),
+                }
+            })
+            .unwrap_or(false);
+
+        Capabilities {
+            params: self.params.clone(),
+            probe,
+            buf_ring,
+        }
+    }
+
+    /// Get a snapshot of the counters of the ring, e.g. to export them as metrics.
+    ///
+    /// This includes the activity through the halves and the concurrent ring made from it.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> Stats {
+        Stats::new(&self.sq, &self.cq)
+    }
+
     /// Initiate and/or complete asynchronous I/O
     ///
     /// # Safety
@@ -155,19 +378,183 @@ impl IoUring {
 
     /// Initiate asynchronous I/O.
     #[inline]
-    pub fn submit(&self) -> io::Result<usize> {
+    pub fn submit(&self) -> Result<usize, SubmitError> {
         self.submitter().submit()
     }
 
     /// Initiate and/or complete asynchronous I/O
     #[inline]
-    pub fn submit_and_wait(&self, want: usize) -> io::Result<usize> {
+    pub fn submit_and_wait(&self, want: usize) -> Result<usize, SubmitError> {
         self.submitter().submit_and_wait(want)
     }
 
+    /// Initiate asynchronous I/O and block until the completion event with `user_data` arrives.
+    ///
+    /// Unrelated completion events reaped while waiting are appended to `backlog`,
+    /// which is also searched first, so that they are not lost.
+    pub fn submit_and_wait_for(
+        &mut self,
+        user_data: u64,
+        backlog: &mut Vec<cqueue::Entry>,
+    ) -> Result<cqueue::Entry, SubmitError> {
+        if let Some(pos) = backlog.iter().position(|cqe| cqe.user_data() == user_data) {
+            return Ok(backlog.remove(pos));
+        }
+
+        loop {
+            self.submitter().submit_and_wait(1)?;
+
+            for cqe in self.cq.available() {
+                if cqe.user_data() == user_data {
+                    return Ok(cqe);
+                }
+
+                backlog.push(cqe);
+            }
+        }
+    }
+
+    /// Initiate and/or complete asynchronous I/O,
+    /// with the signal mask replaced by `sigmask` while waiting.
+    ///
+    /// See [Submitter::submit_and_wait_with_sigmask].
+    #[inline]
+    pub fn submit_and_wait_with_sigmask(
+        &self,
+        want: usize,
+        sigmask: &libc::sigset_t,
+    ) -> Result<usize, SubmitError> {
+        self.submitter().submit_and_wait_with_sigmask(want, sigmask)
+    }
+
+    /// See [Submitter::submit_and_spin_wait].
+    #[inline]
+    pub fn submit_and_spin_wait(&self, want: usize, spin: Duration) -> Result<usize, SubmitError> {
+        self.submitter().submit_and_spin_wait(want, spin)
+    }
+
+    /// Wait for completion events, without submitting any entry.
+    #[inline]
+    pub fn wait(&self, want: usize) -> Result<usize, SubmitError> {
+        self.submitter().wait(want)
+    }
+
+    /// Initiate and/or complete asynchronous I/O,
+    /// waiting at most `timeout` for completions.
+    ///
+    /// See [Submitter::submit_with_timeout].
+    #[cfg(feature = "unstable")]
+    #[inline]
+    pub fn submit_with_timeout(
+        &self,
+        want: usize,
+        timeout: Duration,
+    ) -> Result<usize, SubmitError> {
+        self.submitter().submit_with_timeout(want, timeout)
+    }
+
+    /// Wait at most `timeout` for completion events, without submitting any entry.
+    ///
+    /// See [Submitter::wait_timeout].
+    #[cfg(feature = "unstable")]
+    #[inline]
+    pub fn wait_timeout(&self, want: usize, timeout: Duration) -> Result<usize, SubmitError> {
+        self.submitter().wait_timeout(want, timeout)
+    }
+
+    /// Wait for completion events, returning early once `min` has elapsed
+    /// and at least one event is available.
+    ///
+    /// See [Submitter::wait_min_timeout].
+    #[cfg(feature = "unstable")]
+    #[inline]
+    pub fn wait_min_timeout(
+        &self,
+        want: usize,
+        min: Duration,
+        max: Duration,
+    ) -> Result<usize, SubmitError> {
+        self.submitter().wait_min_timeout(want, min, max)
+    }
+
+    /// Initiate and/or complete asynchronous I/O, waiting as configured by `args`.
+    ///
+    /// See [Submitter::submit_with_args].
+    #[cfg(feature = "unstable")]
+    #[inline]
+    pub fn submit_with_args(
+        &self,
+        want: usize,
+        args: &opcode::types::ExtArg<'_>,
+    ) -> Result<usize, SubmitError> {
+        self.submitter().submit_with_args(want, args)
+    }
+
+    /// Wait for completion events as configured by `args`, without submitting any entry.
+    ///
+    /// See [Submitter::wait_with_args].
+    #[cfg(feature = "unstable")]
+    #[inline]
+    pub fn wait_with_args(
+        &self,
+        want: usize,
+        args: &opcode::types::ExtArg<'_>,
+    ) -> Result<usize, SubmitError> {
+        self.submitter().wait_with_args(want, args)
+    }
+
+    /// Like [IoUring::submit_and_wait], but when the submission fails because the completion
+    /// queue overflowed, the completion queue is reaped into `reap`, the events the kernel kept
+    /// with `IORING_FEAT_NODROP` are flushed into the room made, and the submission is retried.
+    /// The flushed events are reaped on the next round, if the submission fails again.
+    ///
+    /// Without this, an application that only reaps after its submissions succeeded
+    /// would be stuck between a full completion queue and a full submission queue.
+    /// The events handed to `reap` do not count towards `want`, and the events flushed
+    /// are left in the completion queue once the submission succeeds.
+    ///
+    /// [SubmitError::CompletionQueueOverflow] is still returned
+    /// if no event could be reaped to make room.
+    #[cfg(feature = "unstable")]
+    pub fn submit_and_wait_reaping<F>(
+        &mut self,
+        want: usize,
+        mut reap: F,
+    ) -> Result<usize, SubmitError>
+    where
+        F: FnMut(cqueue::Entry),
+    {
+        loop {
+            match self.submitter().submit_and_wait(want) {
+                Err(SubmitError::CompletionQueueOverflow) => (),
+                ret => return ret,
+            }
+
+            let mut reaped = 0;
+            for entry in self.cq.available() {
+                reap(entry);
+                reaped += 1;
+            }
+            if reaped > 0 && self.sq.cq_overflow() {
+                self.submitter().flush_overflow()?;
+            }
+
+            if reaped == 0 {
+                return Err(SubmitError::CompletionQueueOverflow);
+            }
+        }
+    }
+
     /// Get submitter and submission queue and completion queue
     pub fn split(&mut self) -> (Submitter<'_>, &mut SubmissionQueue, &mut CompletionQueue) {
-        let submit = Submitter::new(&self.fd, self.params.0.flags, &self.sq);
+        let submit = Submitter::new(
+            &self.fd,
+            &self.params,
+            self.ring_fd_index,
+            self.retry_eintr,
+            &self.sq,
+            &self.cq,
+        );
         (submit, &mut self.sq, &mut self.cq)
     }
 
@@ -181,27 +568,335 @@ impl IoUring {
         &mut self.cq
     }
 
+    /// The file descriptor to wait on for completion events,
+    /// so that the ring can be a source of an existing event loop, e.g. in an `epoll` set.
+    ///
+    /// The ring is readable while its completion queue has events, so a level-triggered
+    /// poll keeps reporting it until they are reaped. With an edge-triggered poll,
+    /// [IoUring::has_completions] should be checked before waiting again,
+    /// since only the events posted after the wait started wake it up.
+    ///
+    /// Submissions are not driven by the poll, entries still need to be submitted.
+    #[cfg(feature = "unstable")]
+    #[inline]
+    pub fn readiness_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+
+    /// Whether completion events are ready to be reaped, or kept by the kernel
+    /// after the completion queue overflowed, which are flushed by the next submission.
+    #[cfg(feature = "unstable")]
+    #[inline]
+    pub fn has_completions(&self) -> bool {
+        !self.cq.is_empty() || self.sq.cq_overflow()
+    }
+
+    /// Register the ring file descriptor with the ring itself,
+    /// the [Submitter] will then refer to the ring by its registered index
+    /// and avoid the file descriptor lookup on every `io_uring_enter(2)`.
+    ///
+    /// If the kernel supports `IORING_FEAT_REG_REG_RING`,
+    /// register operations will also go through the registered index.
+    ///
+    /// Note that the registered index is only valid for the thread that registered it,
+    /// using the ring from other threads will fail with `EBADF`.
+    #[cfg(feature = "unstable")]
+    pub fn register_ring_fd(&mut self) -> io::Result<u32> {
+        if let Some(index) = self.ring_fd_index {
+            return Ok(index);
+        }
+
+        let mut update = sys::io_uring_rsrc_update {
+            offset: u32::MAX,
+            resv: 0,
+            data: self.fd.as_raw_fd() as _,
+        };
+
+        register::execute(
+            self.fd.as_raw_fd(),
+            sys::IORING_REGISTER_RING_FDS,
+            &mut update as *mut sys::io_uring_rsrc_update as *const _,
+            1,
+        )?;
+
+        self.ring_fd_index = Some(update.offset);
+        Ok(update.offset)
+    }
+
+    /// Unregister the ring file descriptor registered by [IoUring::register_ring_fd].
+    #[cfg(feature = "unstable")]
+    pub fn unregister_ring_fd(&mut self) -> io::Result<()> {
+        if let Some(index) = self.ring_fd_index {
+            let update = sys::io_uring_rsrc_update {
+                offset: index,
+                resv: 0,
+                data: 0,
+            };
+
+            register::execute(
+                self.fd.as_raw_fd(),
+                sys::IORING_UNREGISTER_RING_FDS,
+                cast_ptr::<sys::io_uring_rsrc_update>(&update) as *const _,
+                1,
+            )?;
+
+            self.ring_fd_index = None;
+        }
+
+        Ok(())
+    }
+
+    /// Split the ring into owned submission and completion halves,
+    /// which can be moved to different threads or tasks.
+    ///
+    /// The halves share the ring through an [Arc](std::sync::Arc),
+    /// it is closed once both of them are dropped.
+    pub fn split_owned(self) -> (SubmissionHalf, CompletionHalf) {
+        split::split(self)
+    }
+
     /// Make a concurrent IoUring.
     #[cfg(feature = "concurrent")]
     pub fn concurrent(self) -> concurrent::IoUring {
         concurrent::IoUring::new(self)
     }
+
+    /// Cancel all the requests in flight, and wait at most `timeout` for them to complete,
+    /// so that the memory they use can be freed once the ring is dropped.
+    ///
+    /// Entries still in the submission queue are submitted first, and are canceled too.
+    /// The completion events of the requests are returned, including those that
+    /// overflowed the completion queue. Requests which cannot be canceled,
+    /// such as reads of regular files, still run to completion.
+    ///
+    /// Once the cancel request (which requires Linux 5.19) completed, an
+    /// [IO_DRAIN](squeue::Flags::IO_DRAIN) request marks the end of the shutdown.
+    /// Both use `u64::MAX` as `user_data`, which must not be used by other requests.
+    ///
+    /// If the timeout expires, the ring is handed back with the events reaped so far,
+    /// requests may still be in flight, so their memory must not be freed.
+    ///
+    /// This requires `IORING_FEAT_EXT_ARG` (Linux 5.11).
+    #[cfg(feature = "unstable")]
+    pub fn shutdown(mut self, timeout: Duration) -> Result<Vec<cqueue::Entry>, ShutdownError> {
+        let mut events = Vec::new();
+
+        match self.cancel_all(timeout, &mut events) {
+            Ok(()) => {
+                // Nothing is left in flight for `drop` to wait for.
+                self.shutdown_on_drop = None;
+                Ok(events)
+            }
+            Err(err) => Err(ShutdownError::new(self, events, err)),
+        }
+    }
+
+    /// The body of [IoUring::shutdown], which reaps the completion events into `events`.
+    #[cfg(feature = "unstable")]
+    fn cancel_all(&mut self, timeout: Duration, events: &mut Vec<cqueue::Entry>) -> io::Result<()> {
+        const SHUTDOWN: u64 = u64::MAX;
+
+        let deadline = std::time::Instant::now() + timeout;
+
+        let cancel = opcode::AsyncCancel::new(0)
+            .flags(opcode::types::AsyncCancelFlags::ANY | opcode::types::AsyncCancelFlags::ALL)
+            .build()
+            .user_data(SHUTDOWN);
+        let drain = opcode::Nop::new()
+            .build()
+            .flags(squeue::Flags::IO_DRAIN)
+            .user_data(SHUTDOWN);
+
+        // The tail may have been advanced through a split half or a concurrent queue,
+        // past the head this queue cached.
+        self.sq.sync_head();
+
+        // The drain request is only pushed once the cancel request completed,
+        // otherwise it would be canceled too.
+        for mut entry in IntoIterator::into_iter([cancel, drain]) {
+            loop {
+                let ret = unsafe { self.sq.available().push(entry) };
+                match ret {
+                    Ok(()) => break,
+                    Err(squeue::PushError::Full(e)) => entry = e,
+                }
+
+                match self.submit() {
+                    Ok(_) | Err(SubmitError::CompletionQueueOverflow) => (),
+                    Err(err) => return Err(err.into()),
+                }
+                events.extend(self.cq.available());
+            }
+
+            let mut done = false;
+
+            while !done {
+                let timeout = deadline.saturating_duration_since(std::time::Instant::now());
+
+                match self.submit_with_timeout(1, timeout) {
+                    Ok(_) | Err(SubmitError::CompletionQueueOverflow) => (),
+                    Err(SubmitError::Other(err)) if err.raw_os_error() == Some(libc::ETIME) => (),
+                    Err(err) => return Err(err.into()),
+                }
+
+                for cqe in self.cq.available() {
+                    match cqe.user_data() {
+                        SHUTDOWN => done = true,
+                        _ => events.push(cqe),
+                    }
+                }
+
+                if !done && std::time::Instant::now() >= deadline {
+                    return Err(io::Error::from(io::ErrorKind::TimedOut));
+                }
+            }
+        }
+
+        while self.sq.cq_overflow() {
+            self.submitter().flush_overflow()?;
+            events.extend(self.cq.available());
+        }
+
+        Ok(())
+    }
+}
+
+/// Error returned by [IoUring::shutdown].
+#[cfg(feature = "unstable")]
+pub struct ShutdownError {
+    ring: Box<IoUring>,
+    events: Vec<cqueue::Entry>,
+    error: io::Error,
+}
+
+#[cfg(feature = "unstable")]
+impl ShutdownError {
+    fn new(ring: IoUring, events: Vec<cqueue::Entry>, error: io::Error) -> ShutdownError {
+        ShutdownError {
+            ring: Box::new(ring),
+            events,
+            error,
+        }
+    }
+
+    /// The reason of the failure, [TimedOut](io::ErrorKind::TimedOut) if the timeout expired.
+    pub fn error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// The ring, whose requests may still be in flight,
+    /// and the completion events that were reaped.
+    pub fn into_inner(self) -> (IoUring, Vec<cqueue::Entry>) {
+        (*self.ring, self.events)
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl std::fmt::Debug for ShutdownError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShutdownError")
+            .field("events", &self.events.len())
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl std::fmt::Display for ShutdownError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "shutdown failed: {}", self.error)
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl std::error::Error for ShutdownError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
 }
 
 impl Drop for IoUring {
     fn drop(&mut self) {
+        // The events are discarded, and the requests still in flight after the timeout
+        // are left to the kernel, like without the shutdown.
+        #[cfg(feature = "unstable")]
+        if let Some(timeout) = self.shutdown_on_drop {
+            let _ = self.cancel_all(timeout, &mut Vec::new());
+        }
+
+        // The ring is closed first, so that nothing can enter it while it is unmapped.
         unsafe {
+            ManuallyDrop::drop(&mut self.fd);
             ManuallyDrop::drop(&mut self.memory);
         }
     }
 }
 
+/// The most entries of a submission queue, twice as many for a completion queue.
+const MAX_ENTRIES: u32 = 32768;
+
+/// Check the sizes of the queues, which the kernel rejects with a bare `EINVAL`.
+///
+/// Sizes that are too large are not clamped for the caller, who may size the number of
+/// requests in flight after them, so a smaller queue has to be asked for with
+/// [`Builder::setup_clamp`].
+fn check_entries(entries: u32, p: &sys::io_uring_params) -> io::Result<()> {
+    let invalid = |msg| Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+    let clamp = p.flags & sys::IORING_SETUP_CLAMP != 0;
+
+    if entries == 0 {
+        return invalid("the number of entries must not be zero");
+    }
+    if entries > MAX_ENTRIES && !clamp {
+        return invalid("the number of entries must be at most 32768, unless clamped");
+    }
+
+    if p.flags & sys::IORING_SETUP_CQSIZE != 0 {
+        let cq_entries = p.cq_entries;
+
+        if cq_entries == 0 {
+            return invalid("the completion queue size must not be zero");
+        }
+        if cq_entries > 2 * MAX_ENTRIES && !clamp {
+            return invalid("the completion queue size must be at most 65536, unless clamped");
+        }
+
+        // Both sizes are clamped before they are rounded up and compared.
+        let sq_entries = cmp::min(entries, MAX_ENTRIES).next_power_of_two();
+        let cq_entries = cmp::min(cq_entries, 2 * MAX_ENTRIES).next_power_of_two();
+        if cq_entries < sq_entries {
+            return invalid("the completion queue must not be smaller than the submission queue");
+        }
+    }
+
+    Ok(())
+}
+
 impl Builder {
     pub fn dontfork(&mut self) -> &mut Self {
         self.dontfork = true;
         self
     }
 
+    /// By default, [Submitter] retries submitting and waiting when interrupted by a signal.
+    /// With this option, `EINTR` is returned to the caller instead.
+    pub fn dont_retry_eintr(&mut self) -> &mut Self {
+        self.dont_retry_eintr = true;
+        self
+    }
+
+    /// When the ring is dropped, cancel the requests in flight and wait at most `timeout`
+    /// for them to complete, like [IoUring::shutdown], whose completion events are discarded.
+    ///
+    /// This is a safety net for rings dropped early, such as on a panic, since dropping
+    /// blocks the thread. The requests that did not complete in time may still use
+    /// their memory after the drop.
+    #[cfg(feature = "unstable")]
+    pub fn shutdown_on_drop(&mut self, timeout: Duration) -> &mut Self {
+        self.shutdown_on_drop = Some(timeout);
+        self
+    }
+
     /// Perform busy-waiting for an I/O completion,
     /// as opposed to getting notifications via an asynchronous IRQ (Interrupt Request).
     pub fn setup_iopoll(&mut self) -> &mut Self {
@@ -246,10 +941,125 @@ impl Builder {
         self
     }
 
+    /// Don't interrupt the application with an inter-processor interrupt to run task work
+    /// when a request completes, the work is run on the next entry into the kernel instead.
+    ///
+    /// This is incompatible with [Builder::setup_sqpoll].
+    #[cfg(feature = "unstable")]
+    pub fn setup_coop_taskrun(&mut self) -> &mut Self {
+        self.params.flags |= sys::IORING_SETUP_COOP_TASKRUN;
+        self
+    }
+
+    /// Let the kernel flag pending task work in the submission queue,
+    /// see [SubmissionQueue::taskrun].
+    ///
+    /// This is meant to be used with [Builder::setup_coop_taskrun].
+    #[cfg(feature = "unstable")]
+    pub fn setup_taskrun_flag(&mut self) -> &mut Self {
+        self.params.flags |= sys::IORING_SETUP_TASKRUN_FLAG;
+        self
+    }
+
+    /// Use 128-byte submission entries, so that [Entry128](squeue::Entry128) can be pushed,
+    /// as required by passthrough commands.
+    #[cfg(feature = "unstable")]
+    pub fn setup_sqe128(&mut self) -> &mut Self {
+        self.params.flags |= sys::IORING_SETUP_SQE128;
+        self
+    }
+
+    /// Use 32-byte completion entries, which carry two extra fields,
+    /// see [Entry32](cqueue::Entry32).
+    #[cfg(feature = "unstable")]
+    pub fn setup_cqe32(&mut self) -> &mut Self {
+        self.params.flags |= sys::IORING_SETUP_CQE32;
+        self
+    }
+
+    /// Do not allocate the submission queue indirection array,
+    /// the kernel then reads entries in ring order, which is how this crate always fills them.
+    ///
+    /// This saves a memory access per submitted entry. It requires Linux 6.6,
+    /// older kernels fail to set up the ring with `EINVAL`.
+    #[cfg(feature = "unstable")]
+    pub fn setup_no_sqarray(&mut self) -> &mut Self {
+        self.params.flags |= sys::IORING_SETUP_NO_SQARRAY;
+        self
+    }
+
+    /// Allocate the rings in huge pages instead of letting the kernel allocate them,
+    /// which reduces TLB pressure for large rings.
+    ///
+    /// The submission entries and the rings each take one huge page,
+    /// setup fails with `EINVAL` if they do not fit.
+    /// Huge pages must be reserved on the system (see `vm.nr_hugepages`),
+    /// and this requires Linux 6.5.
+    #[cfg(feature = "unstable")]
+    pub fn setup_huge_pages(&mut self) -> &mut Self {
+        self.params.flags |= sys::IORING_SETUP_NO_MMAP;
+        self
+    }
+
+    /// Set raw setup flags of [sys], for those without a method yet.
+    ///
+    /// Flags that change how the rings are mapped, like `IORING_SETUP_NO_MMAP`,
+    /// must be set with their methods, the crate would map the rings wrongly otherwise.
+    #[cfg(feature = "unstable")]
+    pub fn setup_flags(&mut self, flags: u32) -> &mut Self {
+        self.params.flags |= flags;
+        self
+    }
+
+    /// Allocate the rings on the given NUMA node,
+    /// so that a ring used by a single core does not live in remote memory.
+    ///
+    /// This only applies to rings allocated with [Builder::setup_huge_pages],
+    /// which fail to set up if the node has no free huge pages.
+    #[cfg(feature = "unstable")]
+    pub fn numa_node(&mut self, node: u32) -> &mut Self {
+        self.numa_node = Some(node);
+        self
+    }
+
+    /// Limit the number of io-wq workers of the ring once it is created,
+    /// see [Submitter::register_iowq_max_workers].
+    #[cfg(feature = "unstable")]
+    pub fn iowq_max_workers(&mut self, bounded: u32, unbounded: u32) -> &mut Self {
+        self.iowq_max_workers = Some([bounded, unbounded]);
+        self
+    }
+
+    /// Bind the io-wq workers of the ring to the CPUs of `cpus` once it is created,
+    /// see [Submitter::register_iowq_aff].
+    #[cfg(feature = "unstable")]
+    pub fn iowq_affinity(&mut self, cpus: libc::cpu_set_t) -> &mut Self {
+        self.iowq_aff = Some(cpus);
+        self
+    }
+
     /// Build a [IoUring].
     #[inline]
     pub fn build(&self, entries: u32) -> io::Result<IoUring> {
-        let ring = IoUring::with_params(entries, self.params)?;
+        // With `IORING_SETUP_NO_MMAP`, the rings live in huge pages we allocate up front.
+        #[cfg(feature = "unstable")]
+        let memory = if self.params.flags & sys::IORING_SETUP_NO_MMAP != 0 {
+            Some(UserMemory {
+                rings: Mmap::new_huge_page(self.numa_node)?,
+                sqes: Mmap::new_huge_page(self.numa_node)?,
+            })
+        } else {
+            None
+        };
+        #[cfg(not(feature = "unstable"))]
+        let memory = None;
+
+        let mut ring = IoUring::with_params(entries, self.params, memory)?;
+        ring.retry_eintr = !self.dont_retry_eintr;
+        #[cfg(feature = "unstable")]
+        {
+            ring.shutdown_on_drop = self.shutdown_on_drop;
+        }
 
         if self.dontfork {
             ring.memory.sq_mmap.dontfork()?;
@@ -259,11 +1069,66 @@ impl Builder {
             }
         }
 
+        #[cfg(feature = "unstable")]
+        {
+            let submitter = ring.submitter();
+
+            if let Some(mut max) = self.iowq_max_workers {
+                submitter.register_iowq_max_workers(&mut max)?;
+            }
+            if let Some(cpus) = self.iowq_aff.as_ref() {
+                submitter.register_iowq_aff(cpus)?;
+            }
+        }
+
         Ok(ring)
     }
 }
 
 impl Parameters {
+    /// Recover the parameters of the ring of `fd`, which the kernel does not report.
+    ///
+    /// The sizes of the queues and the poll thread are read from `/proc/self/fdinfo`,
+    /// and the offsets and features are those of a ring of the same sizes, set up to probe them.
+    /// Other setup flags cannot be recovered, so this is wrong for rings set up with
+    /// `IORING_SETUP_SQE128`, `IORING_SETUP_CQE32` or `IORING_SETUP_NO_SQARRAY`,
+    /// whose parameters must be passed along with their file descriptor instead.
+    pub fn probe(fd: RawFd) -> io::Result<Parameters> {
+        let fdinfo = std::fs::read_to_string(format!("/proc/self/fdinfo/{}", fd))?;
+        let field = |name: &str| {
+            fdinfo
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+                .map(str::trim)
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "not an io_uring instance")
+                })
+        };
+        let entries = |name: &str| {
+            let mask = field(name)?;
+            u32::from_str_radix(mask.trim_start_matches("0x"), 16)
+                .map(|mask| mask + 1)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        };
+
+        let mut p = sys::io_uring_params {
+            flags: sys::IORING_SETUP_CQSIZE,
+            cq_entries: entries("CqMask")?,
+            ..Default::default()
+        };
+
+        // The layout of the rings only depends on their sizes.
+        unsafe { Fd(sys::io_uring_setup(entries("SqMask")?, &mut p)?) };
+
+        // Kernels that do not report the poll thread are taken to have none.
+        p.flags = 0;
+        if matches!(field("SqThread"), Ok(thread) if thread != "-1") {
+            p.flags |= sys::IORING_SETUP_SQPOLL;
+        }
+
+        Ok(Parameters(p))
+    }
+
     pub fn is_setup_sqpoll(&self) -> bool {
         self.0.flags & sys::IORING_SETUP_SQPOLL != 0
     }
@@ -272,6 +1137,16 @@ impl Parameters {
         self.0.flags & sys::IORING_SETUP_IOPOLL != 0
     }
 
+    #[cfg(feature = "unstable")]
+    pub fn is_setup_sqe128(&self) -> bool {
+        self.0.flags & sys::IORING_SETUP_SQE128 != 0
+    }
+
+    #[cfg(feature = "unstable")]
+    pub fn is_setup_cqe32(&self) -> bool {
+        self.0.flags & sys::IORING_SETUP_CQE32 != 0
+    }
+
     /// If this flag is set, the two SQ and CQ rings can be mapped with a single `mmap(2)` call.
     /// The SQEs must still be allocated separately.
     /// This brings the necessary `mmap(2)` calls down from three to two.
@@ -320,6 +1195,26 @@ impl Parameters {
         self.0.features & sys::IORING_FEAT_FAST_POLL != 0
     }
 
+    /// If this flag is set, `io_uring_enter(2)` accepts an extended argument,
+    /// which makes [Submitter::submit_with_timeout] available.
+    #[cfg(feature = "unstable")]
+    pub fn is_feature_ext_arg(&self) -> bool {
+        self.0.features & sys::IORING_FEAT_EXT_ARG != 0
+    }
+
+    /// If this flag is set, [Submitter::wait_min_timeout] is supported.
+    #[cfg(feature = "unstable")]
+    pub fn is_feature_min_timeout(&self) -> bool {
+        self.0.features & sys::IORING_FEAT_MIN_TIMEOUT != 0
+    }
+
+    /// If this flag is set, register operations can be issued
+    /// through a ring file descriptor registered by [IoUring::register_ring_fd].
+    #[cfg(feature = "unstable")]
+    pub fn is_feature_reg_reg_ring(&self) -> bool {
+        self.0.features & sys::IORING_FEAT_REG_REG_RING != 0
+    }
+
     pub fn sq_entries(&self) -> u32 {
         self.0.sq_entries
     }
@@ -327,6 +1222,59 @@ impl Parameters {
     pub fn cq_entries(&self) -> u32 {
         self.0.cq_entries
     }
+
+    /// The setup flags of the ring, as set by the [Builder].
+    #[cfg(feature = "unstable")]
+    pub fn flags(&self) -> u32 {
+        self.0.flags
+    }
+
+    /// The features supported by the kernel, as reported when the ring was set up,
+    /// including those that the `is_feature_*` methods do not cover yet.
+    #[cfg(feature = "unstable")]
+    pub fn features(&self) -> u32 {
+        self.0.features
+    }
+
+    /// The CPU of the poll thread, if it was set with [Builder::setup_sqpoll_cpu].
+    #[cfg(feature = "unstable")]
+    pub fn sq_thread_cpu(&self) -> Option<u32> {
+        if self.0.flags & sys::IORING_SETUP_SQ_AFF != 0 {
+            Some(self.0.sq_thread_cpu)
+        } else {
+            None
+        }
+    }
+
+    /// The idle time of the poll thread in milliseconds, see [Builder::setup_sqpoll].
+    #[cfg(feature = "unstable")]
+    pub fn sq_thread_idle(&self) -> u32 {
+        self.0.sq_thread_idle
+    }
+
+    /// The ring whose io-wq workers are shared, if set with [Builder::setup_attach_wq].
+    #[cfg(feature = "unstable")]
+    pub fn wq_fd(&self) -> Option<RawFd> {
+        if self.0.flags & sys::IORING_SETUP_ATTACH_WQ != 0 {
+            Some(self.0.wq_fd as _)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<sys::io_uring_params> for Parameters {
+    /// Wrap the raw parameters filled in by `io_uring_setup(2)`,
+    /// e.g. for a ring set up by other code, see [IoUring::from_raw_parts].
+    fn from(params: sys::io_uring_params) -> Parameters {
+        Parameters(params)
+    }
+}
+
+impl From<Parameters> for sys::io_uring_params {
+    fn from(params: Parameters) -> sys::io_uring_params {
+        params.0
+    }
 }
 
 impl AsRawFd for IoUring {
@@ -334,3 +1282,174 @@ impl AsRawFd for IoUring {
         self.fd.as_raw_fd()
     }
 }
+
+impl FromRawFd for IoUring {
+    /// Take over a ring from its file descriptor, see [Parameters::probe].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parameters cannot be probed or the rings cannot be mapped,
+    /// e.g. if `fd` is not a ring. [Parameters::probe] and [IoUring::from_raw_parts]
+    /// report these errors instead.
+    unsafe fn from_raw_fd(fd: RawFd) -> IoUring {
+        let params = Parameters::probe(fd).expect("failed to probe the ring parameters");
+        IoUring::from_raw_parts(fd, params).expect("failed to map the rings")
+    }
+}
+
+impl IntoRawFd for IoUring {
+    /// Release the file descriptor of the ring, see [IoUring::into_raw_parts].
+    fn into_raw_fd(self) -> RawFd {
+        self.into_raw_parts().0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "unstable")]
+    use std::time::Duration;
+
+    use super::*;
+
+    fn params(flags: u32, cq_entries: u32) -> sys::io_uring_params {
+        sys::io_uring_params {
+            flags,
+            cq_entries,
+            ..Default::default()
+        }
+    }
+
+    fn check(entries: u32, p: sys::io_uring_params) -> Option<io::ErrorKind> {
+        check_entries(entries, &p).err().map(|err| err.kind())
+    }
+
+    #[test]
+    fn test_check_entries() {
+        use io::ErrorKind::InvalidInput;
+
+        assert_eq!(check(0, params(0, 0)), Some(InvalidInput));
+        assert_eq!(check(3, params(0, 0)), None);
+        assert_eq!(check(MAX_ENTRIES, params(0, 0)), None);
+        assert_eq!(check(MAX_ENTRIES + 1, params(0, 0)), Some(InvalidInput));
+        assert_eq!(
+            check(MAX_ENTRIES + 1, params(sys::IORING_SETUP_CLAMP, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_check_cq_entries() {
+        use io::ErrorKind::InvalidInput;
+
+        let cqsize = sys::IORING_SETUP_CQSIZE;
+        let clamp = sys::IORING_SETUP_CLAMP;
+
+        assert_eq!(check(8, params(cqsize, 0)), Some(InvalidInput));
+        assert_eq!(check(8, params(cqsize, 4)), Some(InvalidInput));
+        assert_eq!(check(8, params(cqsize, 8)), None);
+        // The queues are rounded up to a power of two before they are compared.
+        assert_eq!(check(7, params(cqsize, 5)), None);
+        assert_eq!(check(9, params(cqsize, 8)), Some(InvalidInput));
+
+        let max = 2 * MAX_ENTRIES;
+        assert_eq!(check(8, params(cqsize, max + 1)), Some(InvalidInput));
+        assert_eq!(check(8, params(cqsize | clamp, max + 1)), None);
+        assert_eq!(
+            check(4 * MAX_ENTRIES, params(cqsize | clamp, MAX_ENTRIES)),
+            None
+        );
+        assert_eq!(
+            check(4 * MAX_ENTRIES, params(cqsize | clamp, MAX_ENTRIES / 2)),
+            Some(InvalidInput)
+        );
+    }
+
+    #[test]
+    fn test_build_rejects_entries() {
+        let err = IoUring::new(MAX_ENTRIES + 1).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let err = Builder::default().setup_cqsize(4).build(8).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        Builder::default()
+            .setup_clamp()
+            .build(MAX_ENTRIES + 1)
+            .unwrap();
+    }
+
+    #[cfg(feature = "unstable")]
+    #[test]
+    fn test_capabilities() {
+        use crate::opcode::OpCode;
+
+        let caps = IoUring::new(4).unwrap().capabilities();
+        assert!(caps.is_supported(OpCode::Nop));
+
+        // The heuristics follow the releases of the operations they are inferred from.
+        if caps.is_supported(OpCode::SendZc) {
+            assert!(caps.likely_supports_timeout_update());
+            assert!(caps.likely_supports_close_fixed());
+            assert!(caps.likely_supports_multishot_accept());
+            assert!(caps.likely_supports_cancel_flags());
+            assert!(caps.likely_supports_multishot_recv());
+            assert!(caps.likely_supports_msg_ring_fd());
+        }
+    }
+
+    #[cfg(feature = "unstable")]
+    fn builder() -> Builder {
+        let mut builder = Builder::default();
+        builder.shutdown_on_drop(Duration::from_secs(1));
+        builder
+    }
+
+    #[cfg(feature = "unstable")]
+    #[test]
+    fn test_shutdown_on_drop_split() {
+        let (mut sq, mut cq) = builder().build(4).unwrap().split_owned();
+
+        for i in 0..20 {
+            unsafe {
+                let mut available = sq.submission().available();
+                for _ in 0..4 {
+                    available
+                        .push(opcode::Nop::new().build().user_data(i))
+                        .unwrap();
+                }
+            }
+            sq.submitter().submit_and_wait(4).unwrap();
+            assert_eq!(cq.completion().available().count(), 4);
+        }
+
+        drop(cq);
+        drop(sq);
+    }
+
+    #[cfg(all(feature = "unstable", feature = "concurrent"))]
+    #[test]
+    fn test_shutdown_on_drop_concurrent() {
+        for into_inner in [false, true] {
+            let ring = builder().build(4).unwrap().concurrent();
+
+            for i in 0..20 {
+                unsafe {
+                    for _ in 0..4 {
+                        ring.submission()
+                            .push(opcode::Nop::new().build().user_data(i))
+                            .unwrap();
+                    }
+                }
+                ring.submit_and_wait(4).unwrap();
+                let mut events = Vec::new();
+                assert_eq!(ring.completion().pop_batch(&mut events), 4);
+            }
+
+            if into_inner {
+                drop(ring.into_inner());
+            } else {
+                drop(ring);
+            }
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! Messages and file descriptors between rings.
+
+use std::os::unix::io::AsRawFd;
+use std::sync::Arc;
+use std::{error, fmt};
+
+use crate::cqueue;
+use crate::opcode::{self, types};
+use crate::squeue::{self, AvailableQueue};
+use crate::sync::atomic;
+use crate::{CompletionHalf, IoUring};
+
+/// Posts messages to the completion queue of another ring with [MsgRing](opcode::MsgRing),
+/// e.g. to hand work over to the thread of another core.
+///
+/// A message is a completion event with the `user_data` and `result` of the sender's choice,
+/// which the target ring reaps like the events of its own requests.
+///
+/// The mailbox keeps the number of events in the target completion queue,
+/// including the messages still in flight, below a limit,
+/// so that the target queue does not overflow when its thread is lagging behind.
+/// The sender passes the completion events of its ring to [RingMailbox::complete],
+/// which confirms the delivery of the messages.
+pub struct RingMailbox {
+    target: Arc<IoUring>,
+    key: u64,
+    limit: usize,
+    in_flight: usize,
+}
+
+/// Moves direct descriptors into the fixed file table of another ring with
+/// [MsgRingSendFd](opcode::MsgRingSendFd), e.g. to balance the connections accepted
+/// by one ring across the rings of other threads.
+///
+/// Each descriptor is installed into a free slot of the target table, which must have
+/// been registered, and a completion event with the `user_data` of the sender's choice
+/// and the slot as `result` notifies the target. A [Close](opcode::Close) linked to the
+/// handoff then frees the slot of the descriptor in the sender table.
+///
+/// Like [RingMailbox], the handoffs are limited so that the target completion queue
+/// does not overflow, and they are confirmed by [FdHandoff::complete].
+pub struct FdHandoff {
+    target: Arc<IoUring>,
+    key: u64,
+    limit: usize,
+    in_flight: usize,
+}
+
+/// Error returned by [RingMailbox::post] and [FdHandoff::send].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostError {
+    /// The submission queue of the sender is full.
+    SubmissionQueueFull,
+
+    /// The completion queue of the target is near its limit,
+    /// until its thread reaps some events.
+    TargetBusy,
+}
+
+impl RingMailbox {
+    /// Create a mailbox posting to the ring of `target`, whose requests on the sender ring
+    /// have `key` as `user_data`, which must not be used by other requests.
+    ///
+    /// The limit defaults to three quarters of the target completion queue.
+    pub fn new(target: &CompletionHalf, key: u64) -> RingMailbox {
+        let target = target.ring().clone();
+        let limit = target.cq.capacity() / 4 * 3;
+
+        RingMailbox {
+            target,
+            key,
+            limit,
+            in_flight: 0,
+        }
+    }
+
+    /// Set the number of events the target completion queue may hold
+    /// before [RingMailbox::post] fails with [PostError::TargetBusy].
+    pub fn set_limit(&mut self, limit: usize) -> &mut Self {
+        self.limit = limit;
+        self
+    }
+
+    /// The `user_data` of the requests of the mailbox on the sender ring.
+    #[inline]
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    /// The number of messages whose delivery is not confirmed yet.
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    /// The number of messages that can be posted before the limit is reached.
+    #[inline]
+    pub fn room(&self) -> usize {
+        room(&self.target, self.limit, self.in_flight)
+    }
+
+    /// Push a message with `user_data` and `result` for the target ring.
+    pub fn post(
+        &mut self,
+        sq: &mut AvailableQueue<'_>,
+        user_data: u64,
+        result: i32,
+    ) -> Result<(), PostError> {
+        if self.room() == 0 {
+            return Err(PostError::TargetBusy);
+        }
+
+        let entry = opcode::MsgRing::new(types::Fd(self.target.as_raw_fd()), result, user_data)
+            .build()
+            .user_data(self.key);
+
+        // The entry refers to no memory, and the target ring is kept open by the mailbox.
+        unsafe { sq.push(entry) }.map_err(|_| PostError::SubmissionQueueFull)?;
+
+        self.in_flight += 1;
+        Ok(())
+    }
+
+    /// Record a completion event of the sender ring, returns `None` if it does not belong
+    /// to the mailbox, or the result of the delivery of a message,
+    /// such as `-libc::EOVERFLOW` if the target completion queue was full.
+    pub fn complete(&mut self, entry: &cqueue::Entry) -> Option<i32> {
+        if entry.user_data() != self.key {
+            return None;
+        }
+
+        self.in_flight = self.in_flight.saturating_sub(1);
+        Some(entry.result())
+    }
+}
+
+impl FdHandoff {
+    /// Create a handoff to the ring of `target`, whose requests on the sender ring
+    /// have `key` as `user_data`, and the closes linked to them `key + 1`.
+    /// Neither may be used by other requests.
+    ///
+    /// The limit defaults to three quarters of the target completion queue.
+    pub fn new(target: &CompletionHalf, key: u64) -> FdHandoff {
+        let target = target.ring().clone();
+        let limit = target.cq.capacity() / 4 * 3;
+
+        FdHandoff {
+            target,
+            key,
+            limit,
+            in_flight: 0,
+        }
+    }
+
+    /// Set the number of events the target completion queue may hold
+    /// before [FdHandoff::send] fails with [PostError::TargetBusy].
+    pub fn set_limit(&mut self, limit: usize) -> &mut Self {
+        self.limit = limit;
+        self
+    }
+
+    /// The `user_data` of the requests of the handoff on the sender ring.
+    #[inline]
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    /// The `user_data` of the closes linked to the handoffs.
+    #[inline]
+    fn close_key(&self) -> u64 {
+        self.key.wrapping_add(1)
+    }
+
+    /// The number of handoffs that are not confirmed yet.
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    /// The number of handoffs that can be sent before the limit is reached.
+    #[inline]
+    pub fn room(&self) -> usize {
+        room(&self.target, self.limit, self.in_flight)
+    }
+
+    /// Push the handoff of the fixed file `fd` to the target ring,
+    /// which is notified with `user_data`.
+    ///
+    /// Two entries are pushed, the handoff and the close of `fd`.
+    pub fn send(
+        &mut self,
+        sq: &mut AvailableQueue<'_>,
+        fd: types::Fixed,
+        user_data: u64,
+    ) -> Result<(), PostError> {
+        if self.room() == 0 {
+            return Err(PostError::TargetBusy);
+        }
+
+        let entries = [
+            opcode::MsgRingSendFd::new(types::Fd(self.target.as_raw_fd()), fd, user_data)
+                .build()
+                .flags(squeue::Flags::IO_LINK)
+                .user_data(self.key),
+            opcode::Close::new(fd)
+                .build()
+                .flags(squeue::Flags::CQE_SKIP_SUCCESS)
+                .user_data(self.close_key()),
+        ];
+
+        // The entries refer to no memory, and the target ring is kept open by the handoff.
+        unsafe { sq.push_multiple(&entries) }.map_err(|_| PostError::SubmissionQueueFull)?;
+
+        self.in_flight += 1;
+        Ok(())
+    }
+
+    /// Record a completion event of the sender ring, returns the result of a handoff,
+    /// 0 on success, or `None` if the event does not report one.
+    ///
+    /// On failure, the file is left in the sender table, and the close linked
+    /// to the handoff is canceled. The events of the closes, with `key + 1` as `user_data`,
+    /// never count as results of handoffs, a close only fails if the slot was emptied
+    /// by another request.
+    pub fn complete(&mut self, entry: &cqueue::Entry) -> Option<i32> {
+        if entry.user_data() != self.key {
+            return None;
+        }
+
+        self.in_flight = self.in_flight.saturating_sub(1);
+        Some(entry.result())
+    }
+}
+
+fn room(target: &IoUring, limit: usize, in_flight: usize) -> usize {
+    if target.sq.cq_overflow() {
+        return 0;
+    }
+
+    let cq = &target.cq;
+
+    // The target thread consumes its queue concurrently, so its head is loaded atomically.
+    let (head, tail) = unsafe {
+        (
+            (*cq.head).load(atomic::Ordering::Acquire),
+            (*cq.tail).load(atomic::Ordering::Acquire),
+        )
+    };
+    let len = tail.wrapping_sub(head) as usize;
+
+    limit.saturating_sub(len + in_flight)
+}
+
+impl fmt::Display for PostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PostError::SubmissionQueueFull => f.write_str("submission queue is full"),
+            PostError::TargetBusy => f.write_str("target completion queue is busy"),
+        }
+    }
+}
+
+impl error::Error for PostError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixed::FixedFileSet;
+
+    #[test]
+    fn test_failed_handoff() {
+        let mut sender = IoUring::new(8).unwrap();
+        let (target_sq, target_cq) = IoUring::new(8).unwrap().split_owned();
+        FixedFileSet::new(&sender.submitter(), 2).unwrap();
+        FixedFileSet::new(&target_sq.submitter(), 2).unwrap();
+
+        // The slot holds no file, so the handoff fails and its close is canceled.
+        let mut handoff = FdHandoff::new(&target_cq, 0x10);
+        handoff
+            .send(&mut sender.submission().available(), types::Fixed(1), 7)
+            .unwrap();
+        assert_eq!(handoff.in_flight(), 1);
+        sender.submit_and_wait(2).unwrap();
+
+        let results: Vec<_> = sender
+            .completion()
+            .available()
+            .map(|cqe| (cqe.user_data(), handoff.complete(&cqe)))
+            .collect();
+        assert_eq!(results, [(0x10, Some(-libc::EBADF)), (0x11, None)]);
+        assert_eq!(handoff.in_flight(), 0);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_post_limit() {
+        use crate::mock::{MockRing, Reply};
+        use crate::opcode::OpCode;
+
+        let mut sender = MockRing::new(2);
+        let (_target_sq, target_cq) = IoUring::new(8).unwrap().split_owned();
+        let mut mailbox = RingMailbox::new(&target_cq, 0x10);
+        // Three quarters of the completion queue, which has twice as many entries.
+        assert_eq!(mailbox.room(), 12);
+
+        mailbox.set_limit(3);
+        for user_data in 0..2 {
+            let mut sq = sender.submission().available();
+            mailbox.post(&mut sq, user_data, 0).unwrap();
+        }
+        let ret = mailbox.post(&mut sender.submission().available(), 2, 0);
+        assert_eq!(ret, Err(PostError::SubmissionQueueFull));
+        assert_eq!(mailbox.in_flight(), 2);
+
+        // The messages in flight count against the limit until they are confirmed.
+        sender.submit().unwrap();
+        mailbox
+            .post(&mut sender.submission().available(), 2, 0)
+            .unwrap();
+        let ret = mailbox.post(&mut sender.submission().available(), 3, 0);
+        assert_eq!(ret, Err(PostError::TargetBusy));
+
+        sender.reply_opcode(OpCode::MsgRing, Reply::new(0));
+        assert!(sender.complete(0x10, Reply::new(-libc::EOVERFLOW)));
+        sender.submit().unwrap();
+        let results: Vec<_> = sender
+            .completion()
+            .available()
+            .map(|cqe| mailbox.complete(&cqe))
+            .collect();
+        assert_eq!(results, [Some(-libc::EOVERFLOW), Some(0)]);
+        assert_eq!(mailbox.in_flight(), 1);
+        assert_eq!(mailbox.room(), 2);
+    }
+}
@@ -0,0 +1,365 @@
+//! A ring without a kernel, for unit tests.
+//!
+//! [MockRing] has real [SubmissionQueue] and [CompletionQueue] over memory of its own,
+//! so code pushing and reaping entries through them runs unchanged against it,
+//! while the test scripts the completion event of each request.
+//!
+//! ```
+//! use io_uring::mock::{MockRing, Reply};
+//! use io_uring::opcode::{self, OpCode};
+//!
+//! let mut ring = MockRing::new(8);
+//! ring.reply_opcode(OpCode::Nop, Reply::new(0));
+//! ring.reply(0x42, Reply::new(-libc::EBADF));
+//!
+//! unsafe {
+//!     let mut sq = ring.submission().available();
+//!     sq.push(opcode::Nop::new().build().user_data(1)).unwrap();
+//!     sq.push(opcode::Nop::new().build().user_data(0x42)).unwrap();
+//!     sq.push(opcode::Fsync::new(opcode::types::Fd(3)).build().user_data(2)).unwrap();
+//! }
+//! ring.submit_and_wait(2).unwrap();
+//!
+//! let events: Vec<_> = ring
+//!     .completion()
+//!     .available()
+//!     .map(|cqe| (cqe.user_data(), cqe.result()))
+//!     .collect();
+//! assert_eq!(events, [(1, 0), (0x42, -libc::EBADF)]);
+//!
+//! // The fsync has no reply yet, it stays in flight until the test completes it.
+//! assert_eq!(ring.in_flight(), 1);
+//! assert!(ring.complete(2, Reply::new(0)));
+//! assert_eq!(ring.completion().available().count(), 1);
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::{io, mem};
+
+use crate::opcode::OpCode;
+use crate::squeue::{self, Flags};
+use crate::sync::atomic::{AtomicU32, Ordering};
+use crate::sys;
+use crate::{CompletionQueue, SubmissionQueue, SubmitError};
+
+/// The scripted completion event of a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reply {
+    result: i32,
+    flags: u32,
+}
+
+impl Reply {
+    /// A completion event with `result`, such as `-libc::EAGAIN` for a failure.
+    #[inline]
+    pub const fn new(result: i32) -> Reply {
+        Reply { result, flags: 0 }
+    }
+
+    /// Flag the event as not being the last one of a multishot request.
+    #[inline]
+    pub const fn more(mut self) -> Reply {
+        self.flags |= sys::IORING_CQE_F_MORE;
+        self
+    }
+
+    /// Report the provided buffer `bid` as selected by the request.
+    #[inline]
+    pub const fn buffer(mut self, bid: u16) -> Reply {
+        self.flags |= sys::IORING_CQE_F_BUFFER | (bid as u32) << sys::IORING_CQE_BUFFER_SHIFT;
+        self
+    }
+
+    /// Flag the event as the notification of a zero-copy send.
+    #[inline]
+    pub const fn notif(mut self) -> Reply {
+        self.flags |= sys::IORING_CQE_F_NOTIF;
+        self
+    }
+
+    #[inline]
+    fn is_more(&self) -> bool {
+        self.flags & sys::IORING_CQE_F_MORE != 0
+    }
+}
+
+/// An in-memory ring, which plays the kernel when entries are submitted.
+///
+/// When a request is submitted, it is answered by the replies queued for its `user_data`
+/// with [MockRing::reply], in order, until one without [Reply::more].
+/// Without such replies, the reply set for its opcode with [MockRing::reply_opcode] is used.
+/// Otherwise, the request stays in flight until [MockRing::complete] is called.
+///
+/// [CQE_SKIP_SUCCESS](Flags::CQE_SKIP_SUCCESS) is honored, and the rest of a chain of
+/// [linked](Flags::IO_LINK) requests is canceled when a request fails on submission.
+/// Events that do not fit in the completion queue are kept, like with `IORING_FEAT_NODROP`,
+/// and flushed on the next submission.
+pub struct MockRing {
+    memory: Box<Memory>,
+    sq: SubmissionQueue,
+    cq: CompletionQueue,
+    replies: HashMap<u64, VecDeque<Reply>>,
+    opcodes: HashMap<u8, Reply>,
+    in_flight: Vec<squeue::Entry>,
+    submitted: Vec<squeue::Entry>,
+    overflow: VecDeque<sys::io_uring_cqe>,
+}
+
+struct Memory {
+    mask: u32,
+    entries: u32,
+    sqes: Box<[sys::io_uring_sqe]>,
+    cqes: Box<[sys::io_uring_cqe]>,
+    sq_head: AtomicU32,
+    sq_tail: AtomicU32,
+    sq_flags: AtomicU32,
+    sq_dropped: AtomicU32,
+    cq_head: AtomicU32,
+    cq_tail: AtomicU32,
+    cq_flags: AtomicU32,
+    cq_overflow: AtomicU32,
+}
+
+// The queues only point into the memory owned by the ring.
+unsafe impl Send for MockRing {}
+
+impl MockRing {
+    /// Create a ring of `entries` entries in each queue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `entries` is not a power of two.
+    pub fn new(entries: u32) -> MockRing {
+        assert!(
+            entries.is_power_of_two(),
+            "the number of entries must be a power of two"
+        );
+
+        let mut memory = Box::new(Memory {
+            mask: entries - 1,
+            entries,
+            sqes: (0..entries).map(|_| unsafe { mem::zeroed() }).collect(),
+            cqes: (0..entries).map(|_| Default::default()).collect(),
+            sq_head: AtomicU32::new(0),
+            sq_tail: AtomicU32::new(0),
+            sq_flags: AtomicU32::new(0),
+            sq_dropped: AtomicU32::new(0),
+            cq_head: AtomicU32::new(0),
+            cq_tail: AtomicU32::new(0),
+            cq_flags: AtomicU32::new(0),
+            cq_overflow: AtomicU32::new(0),
+        });
+
+        let (sq, cq) = unsafe {
+            let m = &mut *memory;
+            (
+                SubmissionQueue::from_raw_parts(
+                    &m.sq_head,
+                    &m.sq_tail,
+                    &m.mask,
+                    &m.entries,
+                    &m.sq_flags,
+                    &m.sq_dropped,
+                    m.sqes.as_mut_ptr(),
+                ),
+                CompletionQueue::from_raw_parts(
+                    &m.cq_head,
+                    &m.cq_tail,
+                    &m.mask,
+                    &m.entries,
+                    &m.cq_overflow,
+                    m.cqes.as_ptr(),
+                    &m.cq_flags,
+                ),
+            )
+        };
+
+        MockRing {
+            memory,
+            sq,
+            cq,
+            replies: HashMap::new(),
+            opcodes: HashMap::new(),
+            in_flight: Vec::new(),
+            submitted: Vec::new(),
+            overflow: VecDeque::new(),
+        }
+    }
+
+    /// Get submission queue
+    #[inline]
+    pub fn submission(&mut self) -> &mut SubmissionQueue {
+        &mut self.sq
+    }
+
+    /// Get completion queue
+    #[inline]
+    pub fn completion(&mut self) -> &mut CompletionQueue {
+        &mut self.cq
+    }
+
+    /// Queue a reply for the next request submitted with `user_data`.
+    pub fn reply(&mut self, user_data: u64, reply: Reply) -> &mut Self {
+        self.replies.entry(user_data).or_default().push_back(reply);
+        self
+    }
+
+    /// Answer the requests of `opcode` with `reply`,
+    /// unless replies are queued for their `user_data`.
+    pub fn reply_opcode(&mut self, opcode: OpCode, reply: Reply) -> &mut Self {
+        self.opcodes.insert(opcode as u8, reply);
+        self
+    }
+
+    /// Post the completion event of the first request in flight with `user_data`,
+    /// returns `false` if there is none.
+    ///
+    /// The request stays in flight if the reply has [Reply::more].
+    pub fn complete(&mut self, user_data: u64, reply: Reply) -> bool {
+        let index = match self
+            .in_flight
+            .iter()
+            .position(|entry| entry.get_user_data() == user_data)
+        {
+            Some(index) => index,
+            None => return false,
+        };
+
+        let entry = if reply.is_more() {
+            self.in_flight[index].clone()
+        } else {
+            self.in_flight.remove(index)
+        };
+
+        self.post(&entry, reply);
+        true
+    }
+
+    /// The requests that were submitted but not completed yet.
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// All the entries submitted so far, in order.
+    #[inline]
+    pub fn submitted(&self) -> &[squeue::Entry] {
+        &self.submitted
+    }
+
+    /// Consume the entries of the submission queue, and answer them.
+    pub fn submit(&mut self) -> Result<usize, SubmitError> {
+        self.flush();
+
+        let m = &*self.memory;
+        let tail = m.sq_tail.load(Ordering::Acquire);
+        let mut head = m.sq_head.load(Ordering::Acquire);
+        let mut entries = Vec::new();
+
+        while head != tail {
+            let sqe = m.sqes[(head & m.mask) as usize];
+            entries.push(squeue::Entry::from_raw(sqe));
+            head = head.wrapping_add(1);
+        }
+        m.sq_head.store(head, Ordering::Release);
+
+        let mut canceled = false;
+        for entry in entries.iter() {
+            let link = entry
+                .get_flags()
+                .intersects(Flags::IO_LINK | Flags::IO_HARDLINK);
+
+            if canceled {
+                self.post(entry, Reply::new(-libc::ECANCELED));
+            } else if let Some(reply) = self.answer(entry) {
+                canceled = reply.result < 0 && entry.get_flags().contains(Flags::IO_LINK);
+            }
+
+            canceled &= link;
+        }
+
+        self.submitted.extend_from_slice(&entries);
+        Ok(entries.len())
+    }
+
+    /// Submit, and check that at least `want` completion events are ready.
+    ///
+    /// Nothing completes while waiting, so this fails with
+    /// [WouldBlock](io::ErrorKind::WouldBlock) instead of blocking forever.
+    pub fn submit_and_wait(&mut self, want: usize) -> Result<usize, SubmitError> {
+        let submitted = self.submit()?;
+
+        if self.cq.len() < want {
+            let err = io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "not enough completion events to wait for",
+            );
+            return Err(SubmitError::Other(err));
+        }
+
+        Ok(submitted)
+    }
+
+    /// Answer a submitted entry, returns the last reply if it completed.
+    fn answer(&mut self, entry: &squeue::Entry) -> Option<Reply> {
+        let user_data = entry.get_user_data();
+
+        while let Some(reply) = self
+            .replies
+            .get_mut(&user_data)
+            .and_then(VecDeque::pop_front)
+        {
+            self.post(entry, reply);
+            if !reply.is_more() {
+                return Some(reply);
+            }
+        }
+
+        if let Some(&reply) = self.opcodes.get(&entry.opcode()) {
+            self.post(entry, reply);
+            if !reply.is_more() {
+                return Some(reply);
+            }
+        }
+
+        self.in_flight.push(entry.clone());
+        None
+    }
+
+    fn post(&mut self, entry: &squeue::Entry, reply: Reply) {
+        #[cfg(feature = "unstable")]
+        if reply.result >= 0 && entry.get_flags().contains(Flags::CQE_SKIP_SUCCESS) {
+            return;
+        }
+
+        self.overflow.push_back(sys::io_uring_cqe {
+            user_data: entry.get_user_data(),
+            res: reply.result,
+            flags: reply.flags,
+        });
+        self.flush();
+    }
+
+    /// Move the events kept aside into the completion queue, as far as they fit.
+    fn flush(&mut self) {
+        let m = &mut *self.memory;
+        let head = m.cq_head.load(Ordering::Acquire);
+        let mut tail = m.cq_tail.load(Ordering::Acquire);
+
+        while tail.wrapping_sub(head) < m.entries {
+            match self.overflow.pop_front() {
+                Some(cqe) => m.cqes[(tail & m.mask) as usize] = cqe,
+                None => break,
+            }
+            tail = tail.wrapping_add(1);
+        }
+        m.cq_tail.store(tail, Ordering::Release);
+
+        let flags = if self.overflow.is_empty() {
+            0
+        } else {
+            sys::IORING_SQ_CQ_OVERFLOW
+        };
+        m.sq_flags.store(flags, Ordering::Release);
+    }
+}
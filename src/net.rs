@@ -0,0 +1,183 @@
+//! Blocking networking helpers.
+//!
+//! Like [fs](crate::fs), each function waits for its requests to complete on a ring
+//! private to the calling thread. Addresses are passed as [SocketAddr],
+//! and converted to their C representation internally.
+
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::{cmp, io, mem, ptr};
+
+use crate::blocking::{cvt, run};
+use crate::opcode::{self, types};
+use crate::util::{sockaddr, socket_addr};
+
+fn domain(addr: &SocketAddr) -> i32 {
+    match addr {
+        SocketAddr::V4(_) => libc::AF_INET,
+        SocketAddr::V6(_) => libc::AF_INET6,
+    }
+}
+
+/// Create a socket for the address family of `addr`, requires Linux 5.19.
+fn socket(addr: &SocketAddr, socket_type: i32) -> io::Result<RawFd> {
+    let entry = opcode::Socket::new(domain(addr), socket_type | libc::SOCK_CLOEXEC, 0).build();
+
+    let [ret] = unsafe { run([entry])? };
+    cvt(ret).map(|fd| fd as RawFd)
+}
+
+/// Open a TCP connection to `addr`, requires Linux 5.19.
+pub fn connect(addr: SocketAddr) -> io::Result<TcpStream> {
+    let fd = socket(&addr, libc::SOCK_STREAM)?;
+    // Closes the socket if the connection fails.
+    let stream = unsafe { TcpStream::from_raw_fd(fd) };
+
+    let (storage, len) = sockaddr(addr);
+    let entry = opcode::Connect::new(
+        types::Fd(fd),
+        &storage as *const libc::sockaddr_storage as *const libc::sockaddr,
+        len,
+    )
+    .build();
+
+    let [ret] = unsafe { run([entry])? };
+    cvt(ret)?;
+    Ok(stream)
+}
+
+/// Accept a connection, like `accept4(2)`.
+pub fn accept(listener: &TcpListener) -> io::Result<(TcpStream, SocketAddr)> {
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+
+    let entry = opcode::Accept::new(
+        types::Fd(listener.as_raw_fd()),
+        &mut storage as *mut libc::sockaddr_storage as *mut libc::sockaddr,
+        &mut len,
+    )
+    .flags(libc::SOCK_CLOEXEC as _)
+    .build();
+
+    let [ret] = unsafe { run([entry])? };
+    let stream = unsafe { TcpStream::from_raw_fd(cvt(ret)? as _) };
+    Ok((stream, socket_addr(&storage)?))
+}
+
+/// Send data on a connected socket, like `send(2)`,
+/// returns the number of bytes sent.
+pub fn send<S: AsRawFd>(socket: &S, buf: &[u8], flags: i32) -> io::Result<usize> {
+    let len = cmp::min(buf.len(), u32::MAX as usize);
+    let entry = opcode::Send::new(types::Fd(socket.as_raw_fd()), buf.as_ptr(), len as _)
+        .flags(flags)
+        .build();
+
+    let [ret] = unsafe { run([entry])? };
+    cvt(ret).map(|n| n as usize)
+}
+
+/// Send all of `buf` on a connected socket.
+pub fn send_all<S: AsRawFd>(socket: &S, mut buf: &[u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        match send(socket, buf, libc::MSG_NOSIGNAL)? {
+            0 => return Err(io::ErrorKind::WriteZero.into()),
+            n => buf = &buf[n..],
+        }
+    }
+
+    Ok(())
+}
+
+/// Receive data from a connected socket, like `recv(2)`,
+/// returns the number of bytes received.
+pub fn recv<S: AsRawFd>(socket: &S, buf: &mut [u8], flags: i32) -> io::Result<usize> {
+    let len = cmp::min(buf.len(), u32::MAX as usize);
+    let entry = opcode::Recv::new(types::Fd(socket.as_raw_fd()), buf.as_mut_ptr(), len as _)
+        .flags(flags)
+        .build();
+
+    let [ret] = unsafe { run([entry])? };
+    cvt(ret).map(|n| n as usize)
+}
+
+/// Send a datagram to `addr`, like `sendto(2)`.
+pub fn send_to(socket: &UdpSocket, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+    let (mut storage, len) = sockaddr(addr);
+    let mut iov = libc::iovec {
+        iov_base: buf.as_ptr() as *mut _,
+        iov_len: buf.len(),
+    };
+    let msg = msghdr(&mut storage, len, &mut iov);
+
+    let entry = opcode::SendMsg::new(types::Fd(socket.as_raw_fd()), &msg).build();
+
+    let [ret] = unsafe { run([entry])? };
+    cvt(ret).map(|n| n as usize)
+}
+
+/// Receive a datagram, like `recvfrom(2)`,
+/// returns the number of bytes received and the address of the sender.
+pub fn recv_from(socket: &UdpSocket, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let len = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr().cast(),
+        iov_len: buf.len(),
+    };
+    let mut msg = msghdr(&mut storage, len, &mut iov);
+
+    let entry = opcode::RecvMsg::new(types::Fd(socket.as_raw_fd()), &mut msg).build();
+
+    let [ret] = unsafe { run([entry])? };
+    let n = cvt(ret)? as usize;
+    Ok((n, socket_addr(&storage)?))
+}
+
+fn msghdr(
+    storage: &mut libc::sockaddr_storage,
+    len: libc::socklen_t,
+    iov: &mut libc::iovec,
+) -> libc::msghdr {
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_name = storage as *mut libc::sockaddr_storage as *mut _;
+    msg.msg_namelen = len;
+    msg.msg_iov = iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = ptr::null_mut();
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tcp_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = connect(addr).unwrap();
+        let (server, peer) = accept(&listener).unwrap();
+        assert_eq!(peer, client.local_addr().unwrap());
+
+        send_all(&client, b"hello").unwrap();
+        let mut buf = [0; 5];
+        assert_eq!(recv(&server, &mut buf, libc::MSG_WAITALL).unwrap(), 5);
+        assert_eq!(&buf, b"hello");
+
+        drop(client);
+        assert_eq!(recv(&server, &mut buf, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_udp_loopback() {
+        let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        assert_eq!(send_to(&a, b"ping", b.local_addr().unwrap()).unwrap(), 4);
+        let mut buf = [0; 8];
+        let (n, from) = recv_from(&b, &mut buf).unwrap();
+        assert_eq!(&buf[..n], b"ping");
+        assert_eq!(from, a.local_addr().unwrap());
+    }
+}
@@ -1,18 +1,34 @@
 //! Operation code
+//!
+//! Builders whose arguments are neither pointers nor file descriptors, such as [Nop] and
+//! [AsyncCancel], can be used in a `const` context. So a template entry can be built at
+//! compile time and only patched before it is pushed:
+//!
+//! ```
+//! use io_uring::{opcode, squeue};
+//!
+//! const NOP: squeue::Entry = opcode::Nop::new().build();
+//!
+//! let entry = NOP.clone().user_data(0x42);
+//! # assert_eq!(entry.get_user_data(), 0x42);
+//! ```
 
 #![allow(clippy::new_without_default)]
 
+use std::io::{IoSlice, IoSliceMut};
 use std::mem;
 use std::os::unix::io::RawFd;
 
 use crate::squeue::Entry;
+#[cfg(feature = "unstable")]
+use crate::squeue::Entry128;
 use crate::sys;
 
-mod sealed {
+pub(crate) mod sealed {
     use super::types::{Fd, Fixed};
     use std::os::unix::io::RawFd;
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum Target {
         Fd(RawFd),
         Fixed(u32),
@@ -51,7 +67,12 @@ mod sealed {
 pub mod types {
     use crate::sys;
     use bitflags::bitflags;
+    use std::io::{IoSlice, IoSliceMut};
+    #[cfg(feature = "socket2")]
+    use std::mem;
     use std::os::unix::io::RawFd;
+    #[cfg(feature = "unstable")]
+    use std::time::Duration;
 
     pub use sys::__kernel_rwf_t as RwFlags;
     pub use sys::__kernel_timespec as Timespec;
@@ -71,7 +92,9 @@ pub mod types {
     #[repr(transparent)]
     pub struct Fd(pub RawFd);
 
+    /// An index into the registered files.
     #[repr(transparent)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub struct Fixed(pub u32);
 
     bitflags! {
@@ -86,6 +109,16 @@ pub mod types {
         }
     }
 
+    bitflags! {
+        pub struct AsyncCancelFlags: u32 {
+            /// Cancel all the matching requests instead of the first one, requires Linux 5.19.
+            const ALL = sys::IORING_ASYNC_CANCEL_ALL;
+
+            /// Match any request, ignoring the key, requires Linux 5.19.
+            const ANY = sys::IORING_ASYNC_CANCEL_ANY;
+        }
+    }
+
     #[derive(Default, Debug, Clone, Copy)]
     #[repr(transparent)]
     pub struct OpenHow(sys::open_how);
@@ -114,6 +147,228 @@ pub mod types {
             self
         }
     }
+
+    /// The arguments of a wait for completion events, see
+    /// [Submitter::submit_with_args](crate::Submitter::submit_with_args).
+    ///
+    /// These are passed to the kernel with `IORING_ENTER_EXT_ARG`,
+    /// which requires `IORING_FEAT_EXT_ARG` (Linux 5.11).
+    #[cfg(feature = "unstable")]
+    #[derive(Default, Clone, Copy)]
+    pub struct ExtArg<'a> {
+        pub(crate) sigmask: Option<&'a libc::sigset_t>,
+        pub(crate) timeout: Option<Duration>,
+        pub(crate) min_wait: Duration,
+    }
+
+    #[cfg(feature = "unstable")]
+    impl<'a> ExtArg<'a> {
+        pub const fn new() -> Self {
+            ExtArg {
+                sigmask: None,
+                timeout: None,
+                min_wait: Duration::from_secs(0),
+            }
+        }
+
+        /// Replace the signal mask of the current thread by `sigmask` while waiting,
+        /// like `pselect(2)`.
+        ///
+        /// A signal delivered during the wait interrupts it with `EINTR`,
+        /// which is then never retried.
+        pub const fn sigmask(mut self, sigmask: &'a libc::sigset_t) -> Self {
+            self.sigmask = Some(sigmask);
+            self
+        }
+
+        /// Wait at most `timeout`, `ETIME` is returned if it expires
+        /// before enough events are completed and no entry was submitted.
+        pub const fn timeout(mut self, timeout: Duration) -> Self {
+            self.timeout = Some(timeout);
+            self
+        }
+
+        /// Once `min_wait` has elapsed, return as soon as at least one event is available,
+        /// rather than waiting for all the events wanted.
+        ///
+        /// This requires `IORING_FEAT_MIN_TIMEOUT` (Linux 6.12),
+        /// older kernels will return `EINVAL`.
+        pub const fn min_wait(mut self, min_wait: Duration) -> Self {
+            self.min_wait = min_wait;
+            self
+        }
+    }
+
+    /// Room for the socket address of a peer, written by the kernel,
+    /// see [Accept::sockaddr](super::Accept::sockaddr) and [SockAddrBuf::set_msg_name].
+    ///
+    /// The buffer must stay valid until the request writing to it completes.
+    #[cfg(feature = "socket2")]
+    #[derive(Clone, Copy)]
+    pub struct SockAddrBuf {
+        pub(crate) storage: libc::sockaddr_storage,
+        pub(crate) len: libc::socklen_t,
+    }
+
+    #[cfg(feature = "socket2")]
+    impl SockAddrBuf {
+        pub fn new() -> Self {
+            SockAddrBuf {
+                storage: unsafe { mem::zeroed() },
+                len: mem::size_of::<libc::sockaddr_storage>() as _,
+            }
+        }
+
+        /// The address written by an [Accept](super::Accept).
+        pub fn to_sockaddr(&self) -> socket2::SockAddr {
+            self.sockaddr(self.len)
+        }
+
+        /// Receive the source address of a [RecvMsg](super::RecvMsg) with `msg` into the buffer.
+        pub fn set_msg_name(&mut self, msg: &mut libc::msghdr) {
+            msg.msg_name = &mut self.storage as *mut libc::sockaddr_storage as *mut _;
+            msg.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as _;
+        }
+
+        /// The source address received with `msg`,
+        /// which was passed to [SockAddrBuf::set_msg_name].
+        pub fn msg_name(&self, msg: &libc::msghdr) -> socket2::SockAddr {
+            self.sockaddr(msg.msg_namelen)
+        }
+
+        fn sockaddr(&self, len: libc::socklen_t) -> socket2::SockAddr {
+            // The kernel reports the full length of a truncated address.
+            let len = len.min(mem::size_of::<libc::sockaddr_storage>() as _);
+            let addr = &self.storage as *const libc::sockaddr_storage as *const libc::sockaddr;
+            unsafe { socket2::SockAddr::from_raw_parts(addr, len) }
+        }
+    }
+
+    #[cfg(feature = "socket2")]
+    impl Default for SockAddrBuf {
+        fn default() -> Self {
+            SockAddrBuf::new()
+        }
+    }
+
+    /// Send the data of a [SendMsg](super::SendMsg) with `msg` from `bufs`,
+    /// which must stay valid until the request completes.
+    pub fn set_msg_iov(msg: &mut libc::msghdr, bufs: &[IoSlice<'_>]) {
+        msg.msg_iov = bufs.as_ptr() as *mut libc::iovec;
+        msg.msg_iovlen = bufs.len() as _;
+    }
+
+    /// Receive the data of a [RecvMsg](super::RecvMsg) with `msg` into `bufs`,
+    /// which must stay valid until the request completes.
+    pub fn set_msg_iov_mut(msg: &mut libc::msghdr, bufs: &mut [IoSliceMut<'_>]) {
+        msg.msg_iov = bufs.as_mut_ptr() as *mut libc::iovec;
+        msg.msg_iovlen = bufs.len() as _;
+    }
+
+    /// Send a [SendMsg](super::SendMsg) with `msg` to `addr`,
+    /// which must stay valid until the request completes.
+    #[cfg(feature = "socket2")]
+    pub fn set_msg_dest(msg: &mut libc::msghdr, addr: &socket2::SockAddr) {
+        msg.msg_name = addr.as_ptr() as *mut _;
+        msg.msg_namelen = addr.len();
+    }
+}
+
+macro_rules! op_code {
+    ( $( $name:ident = $code:ident ),* $(,)? ) => {
+        /// The operation code of a submission entry.
+        ///
+        /// This covers every `IORING_OP_*` value known to this crate,
+        /// including operations that are not wrapped by a builder yet.
+        #[non_exhaustive]
+        #[repr(u8)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum OpCode {
+            $( $name = sys::$code as u8, )*
+        }
+
+        impl OpCode {
+            /// Decode a raw operation code, returns `None` if it is unknown.
+            pub fn from_raw(code: u8) -> Option<OpCode> {
+                match code as u32 {
+                    $( sys::$code => Some(OpCode::$name), )*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+op_code! {
+    Nop = IORING_OP_NOP,
+    Readv = IORING_OP_READV,
+    Writev = IORING_OP_WRITEV,
+    Fsync = IORING_OP_FSYNC,
+    ReadFixed = IORING_OP_READ_FIXED,
+    WriteFixed = IORING_OP_WRITE_FIXED,
+    PollAdd = IORING_OP_POLL_ADD,
+    PollRemove = IORING_OP_POLL_REMOVE,
+    SyncFileRange = IORING_OP_SYNC_FILE_RANGE,
+    SendMsg = IORING_OP_SENDMSG,
+    RecvMsg = IORING_OP_RECVMSG,
+    Timeout = IORING_OP_TIMEOUT,
+    TimeoutRemove = IORING_OP_TIMEOUT_REMOVE,
+    Accept = IORING_OP_ACCEPT,
+    AsyncCancel = IORING_OP_ASYNC_CANCEL,
+    LinkTimeout = IORING_OP_LINK_TIMEOUT,
+    Connect = IORING_OP_CONNECT,
+    Fallocate = IORING_OP_FALLOCATE,
+    Openat = IORING_OP_OPENAT,
+    Close = IORING_OP_CLOSE,
+    FilesUpdate = IORING_OP_FILES_UPDATE,
+    Statx = IORING_OP_STATX,
+    Read = IORING_OP_READ,
+    Write = IORING_OP_WRITE,
+    Fadvise = IORING_OP_FADVISE,
+    Madvise = IORING_OP_MADVISE,
+    Send = IORING_OP_SEND,
+    Recv = IORING_OP_RECV,
+    Openat2 = IORING_OP_OPENAT2,
+    EpollCtl = IORING_OP_EPOLL_CTL,
+    Splice = IORING_OP_SPLICE,
+    ProvideBuffers = IORING_OP_PROVIDE_BUFFERS,
+    RemoveBuffers = IORING_OP_REMOVE_BUFFERS,
+    Tee = IORING_OP_TEE,
+    Shutdown = IORING_OP_SHUTDOWN,
+    Renameat = IORING_OP_RENAMEAT,
+    Unlinkat = IORING_OP_UNLINKAT,
+    Mkdirat = IORING_OP_MKDIRAT,
+    Symlinkat = IORING_OP_SYMLINKAT,
+    Linkat = IORING_OP_LINKAT,
+    MsgRing = IORING_OP_MSG_RING,
+    Fsetxattr = IORING_OP_FSETXATTR,
+    Setxattr = IORING_OP_SETXATTR,
+    Fgetxattr = IORING_OP_FGETXATTR,
+    Getxattr = IORING_OP_GETXATTR,
+    Socket = IORING_OP_SOCKET,
+    UringCmd = IORING_OP_URING_CMD,
+    SendZc = IORING_OP_SEND_ZC,
+    SendMsgZc = IORING_OP_SENDMSG_ZC,
+    ReadMultishot = IORING_OP_READ_MULTISHOT,
+    Waitid = IORING_OP_WAITID,
+    FutexWait = IORING_OP_FUTEX_WAIT,
+    FutexWake = IORING_OP_FUTEX_WAKE,
+    FutexWaitv = IORING_OP_FUTEX_WAITV,
+    FixedFdInstall = IORING_OP_FIXED_FD_INSTALL,
+}
+
+impl OpCode {
+    #[inline]
+    pub const fn as_raw(self) -> u8 {
+        self as u8
+    }
+}
+
+impl From<OpCode> for u8 {
+    #[inline]
+    fn from(op: OpCode) -> u8 {
+        op.as_raw()
+    }
 }
 
 macro_rules! assign_fd {
@@ -138,7 +393,58 @@ macro_rules! opcode {
     (@type $name:ty ) => {
         $name
     };
+    (@into $field:ident : impl sealed::UseFixed ) => {
+        sealed::UseFixed::into($field)
+    };
+    (@into $field:ident : impl sealed::UseFd ) => {
+        sealed::UseFd::into($field)
+    };
+    (@into $field:ident : $name:ty ) => {
+        $field
+    };
+    (
+        $( #[$outer:meta] )*
+        pub struct $name:ident $fields:tt
+
+        pub const CODE = $opcode:expr;
+
+        $( #[$build_meta:meta] )*
+        pub const fn build($self:ident) -> $entry:ident $build_block:block
+    ) => {
+        opcode! {
+            @impl [const]
+            $( #[$outer] )*
+            pub struct $name $fields
+
+            pub const CODE = $opcode;
+
+            $( #[$build_meta] )*
+            pub fn build($self) -> $entry $build_block
+        }
+    };
     (
+        $( #[$outer:meta] )*
+        pub struct $name:ident $fields:tt
+
+        pub const CODE = $opcode:expr;
+
+        $( #[$build_meta:meta] )*
+        pub fn build($self:ident) -> $entry:ident $build_block:block
+    ) => {
+        opcode! {
+            @impl []
+            $( #[$outer] )*
+            pub struct $name $fields
+
+            pub const CODE = $opcode;
+
+            $( #[$build_meta] )*
+            pub fn build($self) -> $entry $build_block
+        }
+    };
+    (
+        @impl [ $( $const:tt )? ]
+
         $( #[$outer:meta] )*
         pub struct $name:ident {
             $( #[$new_meta:meta] )*
@@ -160,7 +466,7 @@ macro_rules! opcode {
         pub const CODE = $opcode:expr;
 
         $( #[$build_meta:meta] )*
-        pub fn build($self:ident) -> Entry $build_block:block
+        pub fn build($self:ident) -> $entry:ident $build_block:block
     ) => {
         $( #[$outer] )*
         pub struct $name {
@@ -171,9 +477,9 @@ macro_rules! opcode {
         impl $name {
             $( #[$new_meta] )*
             #[inline]
-            pub fn new($( $field : $( $tnt )* ),*) -> Self {
+            pub $( $const )? fn new($( $field : $( $tnt )* ),*) -> Self {
                 $name {
-                    $( $field: $field.into(), )*
+                    $( $field: opcode!(@into $field : $( $tnt )*), )*
                     $( $opt_field: $default, )*
                 }
             }
@@ -191,14 +497,14 @@ macro_rules! opcode {
 
             $( #[$build_meta] )*
             #[inline]
-            pub fn build($self) -> Entry $build_block
+            pub $( $const )? fn build($self) -> $entry $build_block
         }
     }
 }
 
 /// inline zeroed to improve codegen
 #[inline(always)]
-fn sqe_zeroed() -> sys::io_uring_sqe {
+const fn sqe_zeroed() -> sys::io_uring_sqe {
     unsafe { std::mem::zeroed() }
 }
 
@@ -211,7 +517,7 @@ opcode!(
 
     pub const CODE = sys::IORING_OP_NOP;
 
-    pub fn build(self) -> Entry {
+    pub const fn build(self) -> Entry {
         let Nop {} = self;
 
         let mut sqe = sqe_zeroed();
@@ -297,6 +603,24 @@ opcode!(
     }
 );
 
+impl Readv {
+    /// Read into `bufs`, whose layout is that of `libc::iovec`.
+    ///
+    /// The buffers must stay valid until the request completes.
+    pub fn from_slices(fd: impl sealed::UseFixed, bufs: &mut [IoSliceMut<'_>]) -> Self {
+        Readv::new(fd, bufs.as_mut_ptr() as *const libc::iovec, bufs.len() as _)
+    }
+}
+
+impl Writev {
+    /// Write from `bufs`, whose layout is that of `libc::iovec`.
+    ///
+    /// The buffers must stay valid until the request completes.
+    pub fn from_slices(fd: impl sealed::UseFixed, bufs: &[IoSlice<'_>]) -> Self {
+        Writev::new(fd, bufs.as_ptr() as *const libc::iovec, bufs.len() as _)
+    }
+}
+
 opcode!(
     /// File sync. See also `fsync (2)`.
     ///
@@ -451,7 +775,7 @@ opcode!(
 
     pub const CODE = sys::IORING_OP_POLL_REMOVE;
 
-    pub fn build(self) -> Entry {
+    pub const fn build(self) -> Entry {
         let PollRemove { user_data } = self;
 
         let mut sqe = sqe_zeroed();
@@ -604,7 +928,7 @@ opcode!(
 
     pub const CODE = sys::IORING_OP_TIMEOUT_REMOVE;
 
-    pub fn build(self) -> Entry {
+    pub const fn build(self) -> Entry {
         let TimeoutRemove { user_data, flags } = self;
 
         let mut sqe = sqe_zeroed();
@@ -647,18 +971,19 @@ opcode!(
         user_data: { u64 }
         ;;
 
-        // TODO flags
+        flags: types::AsyncCancelFlags = types::AsyncCancelFlags::empty()
     }
 
     pub const CODE = sys::IORING_OP_ASYNC_CANCEL;
 
-    pub fn build(self) -> Entry {
-        let AsyncCancel { user_data } = self;
+    pub const fn build(self) -> Entry {
+        let AsyncCancel { user_data, flags } = self;
 
         let mut sqe = sqe_zeroed();
         sqe.opcode = Self::CODE;
         sqe.fd = -1;
         sqe.__bindgen_anon_2.addr = user_data as _;
+        sqe.__bindgen_anon_3.cancel_flags = flags.bits();
         Entry(sqe)
     }
 );
@@ -711,6 +1036,27 @@ opcode!(
     }
 );
 
+#[cfg(feature = "socket2")]
+impl Accept {
+    /// Accept a connection, and write the address of the peer to `addr`.
+    pub fn sockaddr(fd: impl sealed::UseFixed, addr: &mut types::SockAddrBuf) -> Accept {
+        addr.len = mem::size_of::<libc::sockaddr_storage>() as _;
+        Accept::new(
+            fd,
+            &mut addr.storage as *mut libc::sockaddr_storage as *mut _,
+            &mut addr.len,
+        )
+    }
+}
+
+#[cfg(feature = "socket2")]
+impl Connect {
+    /// Connect to `addr`, which must stay valid until the request completes.
+    pub fn sockaddr(fd: impl sealed::UseFixed, addr: &socket2::SockAddr) -> Connect {
+        Connect::new(fd, addr.as_ptr(), addr.len())
+    }
+}
+
 // === 5.6 ===
 
 opcode!(
@@ -765,8 +1111,10 @@ opcode!(
 
 opcode!(
     /// Issue the equivalent of a `close(2)` system call.
+    ///
+    /// Closing a [Fixed](types::Fixed) file frees its slot, requires Linux 5.15.
     pub struct Close {
-        fd: { impl sealed::UseFd }
+        fd: { impl sealed::UseFixed }
         ;;
     }
 
@@ -777,7 +1125,13 @@ opcode!(
 
         let mut sqe = sqe_zeroed();
         sqe.opcode = Self::CODE;
-        sqe.fd = fd;
+        match fd {
+            sealed::Target::Fd(fd) => sqe.fd = fd,
+            sealed::Target::Fixed(index) => {
+                // The slot is offset by one, zero means a regular file descriptor.
+                sqe.__bindgen_anon_4.__bindgen_anon_1.splice_fd_in = index as i32 + 1;
+            }
+        }
         Entry(sqe)
     }
 );
@@ -1132,7 +1486,7 @@ opcode!(
 
     pub const CODE = sys::IORING_OP_REMOVE_BUFFERS;
 
-    pub fn build(self) -> Entry {
+    pub const fn build(self) -> Entry {
         let RemoveBuffers { nbufs, bgid } = self;
 
         let mut sqe = sqe_zeroed();
@@ -1179,3 +1533,496 @@ opcode!(
         Entry(sqe)
     }
 );
+
+// === 5.11 ===
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Update the expiration of an existing timeout operation, requires Linux 5.11.
+    ///
+    /// The `result` of the completion event is 0 on success,
+    /// or `-libc::ENOENT` if the timeout has already expired.
+    pub struct TimeoutUpdate {
+        user_data: { u64 },
+        timespec: { *const types::Timespec },
+        ;;
+        flags: types::TimeoutFlags = types::TimeoutFlags::empty()
+    }
+
+    pub const CODE = sys::IORING_OP_TIMEOUT_REMOVE;
+
+    pub fn build(self) -> Entry {
+        let TimeoutUpdate { user_data, timespec, flags } = self;
+
+        let mut sqe = sqe_zeroed();
+        sqe.opcode = Self::CODE;
+        sqe.fd = -1;
+        sqe.__bindgen_anon_2.addr = user_data as _;
+        sqe.__bindgen_anon_1.off = timespec as _;
+        sqe.__bindgen_anon_3.timeout_flags = flags.bits() | sys::IORING_TIMEOUT_UPDATE;
+        Entry(sqe)
+    }
+);
+
+// === 5.18 ===
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Post a completion event to another ring, with the given `user_data` and `result`.
+    ///
+    /// The `result` of the completion event of this entry is 0 on success,
+    /// or an error such as `-libc::EOVERFLOW` if the target ring has no room.
+    pub struct MsgRing {
+        ring_fd: { impl sealed::UseFixed },
+        result: { i32 },
+        user_data: { u64 }
+        ;;
+    }
+
+    pub const CODE = sys::IORING_OP_MSG_RING;
+
+    pub fn build(self) -> Entry {
+        let MsgRing { ring_fd, result, user_data } = self;
+
+        let mut sqe = sqe_zeroed();
+        sqe.opcode = Self::CODE;
+        assign_fd!(sqe.fd = ring_fd);
+        sqe.len = result as _;
+        sqe.__bindgen_anon_1.off = user_data;
+        Entry(sqe)
+    }
+);
+
+// === 5.19 ===
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Attempt to cancel the requests issued on a file, requires Linux 5.19.
+    ///
+    /// Matching the requests of a fixed file requires Linux 6.0.
+    pub struct AsyncCancelFd {
+        fd: { impl sealed::UseFixed }
+        ;;
+
+        flags: types::AsyncCancelFlags = types::AsyncCancelFlags::empty()
+    }
+
+    pub const CODE = sys::IORING_OP_ASYNC_CANCEL;
+
+    pub fn build(self) -> Entry {
+        let AsyncCancelFd { fd, flags } = self;
+
+        let mut flags = flags.bits() | sys::IORING_ASYNC_CANCEL_FD;
+        let mut sqe = sqe_zeroed();
+        sqe.opcode = Self::CODE;
+        match fd {
+            sealed::Target::Fd(fd) => sqe.fd = fd,
+            sealed::Target::Fixed(index) => {
+                // The fixed file is matched, not used by the request itself.
+                sqe.fd = index as _;
+                flags |= sys::IORING_ASYNC_CANCEL_FD_FIXED;
+            }
+        }
+        sqe.__bindgen_anon_3.cancel_flags = flags;
+        Entry(sqe)
+    }
+);
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Accept connections continuously, like [Accept] issued again after each connection.
+    ///
+    /// Each accepted connection posts a completion event with
+    /// [is_more](crate::cqueue::Entry::is_more) set, until the request terminates.
+    pub struct AcceptMulti {
+        fd: { impl sealed::UseFixed },
+        ;;
+        flags: u32 = 0,
+        /// Install the accepted sockets as direct descriptors in free slots
+        /// of the registered files table, the result of each completion event
+        /// is the slot instead of a file descriptor.
+        allocate_file_index: bool = false
+    }
+
+    pub const CODE = sys::IORING_OP_ACCEPT;
+
+    pub fn build(self) -> Entry {
+        let AcceptMulti { fd, flags, allocate_file_index } = self;
+
+        let mut sqe = sqe_zeroed();
+        sqe.opcode = Self::CODE;
+        assign_fd!(sqe.fd = fd);
+        sqe.ioprio = sys::IORING_ACCEPT_MULTISHOT as _;
+        sqe.__bindgen_anon_3.accept_flags = flags;
+        if allocate_file_index {
+            sqe.__bindgen_anon_4.__bindgen_anon_1.splice_fd_in = sys::IORING_FILE_INDEX_ALLOC as _;
+        }
+        Entry(sqe)
+    }
+);
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Issue the equivalent of a `socket(2)` system call.
+    pub struct Socket {
+        domain: { i32 },
+        socket_type: { i32 },
+        protocol: { i32 }
+        ;;
+    }
+
+    pub const CODE = sys::IORING_OP_SOCKET;
+
+    pub fn build(self) -> Entry {
+        let Socket { domain, socket_type, protocol } = self;
+
+        let mut sqe = sqe_zeroed();
+        sqe.opcode = Self::CODE;
+        sqe.fd = domain;
+        sqe.__bindgen_anon_1.off = socket_type as _;
+        sqe.len = protocol as _;
+        Entry(sqe)
+    }
+);
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Issue a passthrough command to a file, such as a character device or a socket,
+    /// requires Linux 5.19.
+    ///
+    /// The meaning of `cmd_op` and of the 16 bytes of `cmd` is defined by the driver,
+    /// see [UringCmd80] for commands with a larger payload.
+    pub struct UringCmd16 {
+        fd: { impl sealed::UseFixed },
+        cmd_op: { u32 }
+        ;;
+        cmd: [u8; 16] = [0; 16]
+    }
+
+    pub const CODE = sys::IORING_OP_URING_CMD;
+
+    pub fn build(self) -> Entry {
+        let UringCmd16 { fd, cmd_op, cmd } = self;
+
+        let mut sqe = sqe_zeroed();
+        sqe.opcode = Self::CODE;
+        assign_fd!(sqe.fd = fd);
+        // `cmd_op`, which the bindings do not name, shares the start of `off`,
+        // and the command area starts at `addr3`, up to the end of the entry.
+        unsafe {
+            *(&mut sqe.__bindgen_anon_1.off as *mut u64 as *mut u32) = cmd_op;
+            *(sqe.__bindgen_anon_4.__pad2[1..].as_mut_ptr() as *mut [u8; 16]) = cmd;
+        }
+        Entry(sqe)
+    }
+);
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Like [UringCmd16], with the 80 bytes of command area of a 128-byte entry,
+    /// for rings created with [Builder::setup_sqe128](crate::Builder::setup_sqe128).
+    pub struct UringCmd80 {
+        fd: { impl sealed::UseFixed },
+        cmd_op: { u32 }
+        ;;
+        cmd: [u8; 80] = [0; 80]
+    }
+
+    pub const CODE = sys::IORING_OP_URING_CMD;
+
+    pub fn build(self) -> Entry128 {
+        let UringCmd80 { fd, cmd_op, cmd } = self;
+
+        let mut sqe = sqe_zeroed();
+        sqe.opcode = Self::CODE;
+        assign_fd!(sqe.fd = fd);
+        unsafe {
+            *(&mut sqe.__bindgen_anon_1.off as *mut u64 as *mut u32) = cmd_op;
+        }
+
+        let mut entry = Entry128::from(Entry(sqe));
+        *entry.cmd_mut() = cmd;
+        entry
+    }
+);
+
+// === 6.0 ===
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Install a fixed file of this ring into the fixed file table of another ring,
+    /// and post a completion event with the given `user_data` to it, requires Linux 6.0.
+    ///
+    /// The `result` of the event posted to the other ring is the slot of the file,
+    /// if `dest_slot` is not given, a free slot is allocated.
+    pub struct MsgRingSendFd {
+        ring_fd: { impl sealed::UseFixed },
+        fixed_slot_src: { types::Fixed },
+        user_data: { u64 }
+        ;;
+        dest_slot: Option<types::Fixed> = None
+    }
+
+    pub const CODE = sys::IORING_OP_MSG_RING;
+
+    pub fn build(self) -> Entry {
+        let MsgRingSendFd { ring_fd, fixed_slot_src, user_data, dest_slot } = self;
+
+        let mut sqe = sqe_zeroed();
+        sqe.opcode = Self::CODE;
+        assign_fd!(sqe.fd = ring_fd);
+        sqe.__bindgen_anon_2.addr = sys::IORING_MSG_SEND_FD as _;
+        sqe.__bindgen_anon_1.off = user_data;
+        sqe.__bindgen_anon_4.__bindgen_anon_1.splice_fd_in = match dest_slot {
+            // The slot is offset by one, like the slot of a direct descriptor.
+            Some(types::Fixed(index)) => index as i32 + 1,
+            None => sys::IORING_FILE_INDEX_ALLOC as _,
+        };
+        // `addr3`, which the bindings do not name, holds the source slot.
+        // The entry is zeroed, so the padding is initialized.
+        unsafe {
+            sqe.__bindgen_anon_4.__pad2[1] = fixed_slot_src.0 as _;
+        }
+        Entry(sqe)
+    }
+);
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Send a buffer on a socket without copying it, requires Linux 6.0.
+    ///
+    /// The result of the send is posted first, flagged with
+    /// [is_more](crate::cqueue::Entry::is_more) if a notification follows.
+    /// The notification, flagged with [is_notif](crate::cqueue::Entry::is_notif),
+    /// is posted once the kernel no longer uses the buffer, which must stay valid until then,
+    /// see [SendZcTracker](crate::zerocopy::SendZcTracker).
+    pub struct SendZc {
+        fd: { impl sealed::UseFixed },
+        buf: { *const u8 },
+        len: { u32 },
+        ;;
+        /// Send from the registered buffer of this index, which `buf` points into.
+        buf_index: Option<u16> = None,
+        dest_addr: *const libc::sockaddr = std::ptr::null(),
+        dest_addr_len: libc::socklen_t = 0,
+        flags: i32 = 0,
+        /// Set `IORING_NOTIF_USAGE_ZC_COPIED` in the result of the notification
+        /// if the data was copied after all.
+        report_usage: bool = false
+    }
+
+    pub const CODE = sys::IORING_OP_SEND_ZC;
+
+    pub fn build(self) -> Entry {
+        let SendZc { fd, buf, len, buf_index, dest_addr, dest_addr_len, flags, report_usage } = self;
+
+        let mut sqe = sqe_zeroed();
+        sqe.opcode = Self::CODE;
+        assign_fd!(sqe.fd = fd);
+        sqe.__bindgen_anon_2.addr = buf as _;
+        sqe.len = len;
+        sqe.__bindgen_anon_3.msg_flags = flags as _;
+        if let Some(buf_index) = buf_index {
+            sqe.ioprio |= sys::IORING_RECVSEND_FIXED_BUF as u16;
+            sqe.__bindgen_anon_4.__bindgen_anon_1.__bindgen_anon_1.buf_index = buf_index;
+        }
+        if report_usage {
+            sqe.ioprio |= sys::IORING_SEND_ZC_REPORT_USAGE as u16;
+        }
+        sqe.__bindgen_anon_1.off = dest_addr as _;
+        // `addr_len`, which the bindings do not name, shares the start of `splice_fd_in`.
+        // The entry is zeroed, so the rest of the field is initialized.
+        unsafe {
+            let splice_fd_in = &mut sqe.__bindgen_anon_4.__bindgen_anon_1.splice_fd_in;
+            *(splice_fd_in as *mut i32 as *mut u16) = dest_addr_len as _;
+        }
+        Entry(sqe)
+    }
+);
+
+// === 6.7 ===
+
+/// The entry of a socket command, whose operation shares the start of `off`, like `cmd_op`.
+#[cfg(feature = "unstable")]
+fn socket_cmd(fd: sealed::Target, cmd_op: u32) -> sys::io_uring_sqe {
+    let mut sqe = sqe_zeroed();
+    sqe.opcode = UringCmd16::CODE;
+    assign_fd!(sqe.fd = fd);
+    unsafe {
+        *(&mut sqe.__bindgen_anon_1.off as *mut u64 as *mut u32) = cmd_op;
+    }
+    sqe
+}
+
+/// Fill in the option of a socket command, the `level` and `optname` share `addr`,
+/// `optlen` is `splice_fd_in`, and `optval` is `addr3`.
+#[cfg(feature = "unstable")]
+fn assign_sockopt(sqe: &mut sys::io_uring_sqe, level: i32, optname: i32, optval: u64, optlen: u32) {
+    unsafe {
+        let addr = &mut sqe.__bindgen_anon_2.addr as *mut u64 as *mut i32;
+        *addr = level;
+        *addr.add(1) = optname;
+        sqe.__bindgen_anon_4.__bindgen_anon_1.splice_fd_in = optlen as _;
+        sqe.__bindgen_anon_4.__pad2[1] = optval;
+    }
+}
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Get the number of unread bytes in the receive queue of a socket,
+    /// which is the result of the request, like the `SIOCINQ` ioctl, requires Linux 6.7.
+    pub struct SiocInq {
+        fd: { impl sealed::UseFixed }
+        ;;
+    }
+
+    pub const CODE = sys::IORING_OP_URING_CMD;
+
+    pub fn build(self) -> Entry {
+        let SiocInq { fd } = self;
+        Entry(socket_cmd(fd, sys::SOCKET_URING_OP_SIOCINQ))
+    }
+);
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Get the number of unsent bytes in the send queue of a socket,
+    /// which is the result of the request, like the `SIOCOUTQ` ioctl, requires Linux 6.7.
+    pub struct SiocOutq {
+        fd: { impl sealed::UseFixed }
+        ;;
+    }
+
+    pub const CODE = sys::IORING_OP_URING_CMD;
+
+    pub fn build(self) -> Entry {
+        let SiocOutq { fd } = self;
+        Entry(socket_cmd(fd, sys::SOCKET_URING_OP_SIOCOUTQ))
+    }
+);
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Issue the equivalent of a `getsockopt(2)` system call, requires Linux 6.7.
+    ///
+    /// The value of the option is written to `optval`, of `optlen` bytes,
+    /// and the result of the request is its actual length.
+    /// Only the options of the `SOL_SOCKET` level are supported.
+    pub struct GetSockOpt {
+        fd: { impl sealed::UseFixed },
+        level: { i32 },
+        optname: { i32 },
+        optval: { *mut libc::c_void },
+        optlen: { u32 }
+        ;;
+    }
+
+    pub const CODE = sys::IORING_OP_URING_CMD;
+
+    pub fn build(self) -> Entry {
+        let GetSockOpt { fd, level, optname, optval, optlen } = self;
+
+        let mut sqe = socket_cmd(fd, sys::SOCKET_URING_OP_GETSOCKOPT);
+        assign_sockopt(&mut sqe, level, optname, optval as _, optlen);
+        Entry(sqe)
+    }
+);
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Issue the equivalent of a `setsockopt(2)` system call, requires Linux 6.7.
+    ///
+    /// ```
+    /// # use io_uring::opcode::{self, types};
+    /// let nodelay: libc::c_int = 1;
+    /// let entry = opcode::SetSockOpt::new(
+    ///     types::Fd(3),
+    ///     libc::IPPROTO_TCP,
+    ///     libc::TCP_NODELAY,
+    ///     &nodelay as *const _ as *const _,
+    ///     std::mem::size_of_val(&nodelay) as _,
+    /// )
+    /// .build();
+    /// ```
+    pub struct SetSockOpt {
+        fd: { impl sealed::UseFixed },
+        level: { i32 },
+        optname: { i32 },
+        optval: { *const libc::c_void },
+        optlen: { u32 }
+        ;;
+    }
+
+    pub const CODE = sys::IORING_OP_URING_CMD;
+
+    pub fn build(self) -> Entry {
+        let SetSockOpt { fd, level, optname, optval, optlen } = self;
+
+        let mut sqe = socket_cmd(fd, sys::SOCKET_URING_OP_SETSOCKOPT);
+        assign_sockopt(&mut sqe, level, optname, optval as _, optlen);
+        Entry(sqe)
+    }
+);
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Wait for a child process to change state, like `waitid(2)`, requires Linux 6.7.
+    ///
+    /// `which` and `id` select the children, such as `libc::P_PIDFD` and a pidfd,
+    /// and the state of the child is written to `info`, which must stay valid
+    /// until the request completes.
+    pub struct Waitid {
+        which: { libc::idtype_t },
+        id: { libc::id_t },
+        info: { *mut libc::siginfo_t }
+        ;;
+        /// The options of `waitid(2)`, which must include the states to wait for.
+        options: i32 = libc::WEXITED
+    }
+
+    pub const CODE = sys::IORING_OP_WAITID;
+
+    pub fn build(self) -> Entry {
+        let Waitid { which, id, info, options } = self;
+
+        let mut sqe = sqe_zeroed();
+        sqe.opcode = Self::CODE;
+        sqe.fd = id as _;
+        sqe.len = which as _;
+        sqe.__bindgen_anon_1.addr2 = info as _;
+        // `options` shares `file_index`, which is `splice_fd_in`.
+        sqe.__bindgen_anon_4.__bindgen_anon_1.splice_fd_in = options;
+        Entry(sqe)
+    }
+);
+
+// === 6.8 ===
+
+#[cfg(feature = "unstable")]
+opcode!(
+    /// Install a fixed file as a regular file descriptor of the process,
+    /// which is the result of the request, requires Linux 6.8.
+    ///
+    /// The fixed file stays in its slot.
+    pub struct FixedFdInstall {
+        fd: { types::Fixed }
+        ;;
+        /// Leave `O_CLOEXEC` unset on the new file descriptor.
+        no_cloexec: bool = false
+    }
+
+    pub const CODE = sys::IORING_OP_FIXED_FD_INSTALL;
+
+    pub fn build(self) -> Entry {
+        let FixedFdInstall { fd, no_cloexec } = self;
+
+        let mut sqe = sqe_zeroed();
+        sqe.opcode = Self::CODE;
+        assign_fd!(sqe.fd = sealed::UseFixed::into(fd));
+        // `install_fd_flags`, which the bindings do not name, shares `rw_flags`.
+        if no_cloexec {
+            sqe.__bindgen_anon_3.rw_flags = sys::IORING_FIXED_FD_NO_CLOEXEC as _;
+        }
+        Entry(sqe)
+    }
+);
@@ -0,0 +1,509 @@
+//! Operations that own their resources.
+//!
+//! The opcode builders take raw pointers, and the memory they refer to must stay valid
+//! until the request completes, which is easy to get wrong.
+//! The operations of this module own their buffers, paths and addresses instead,
+//! and [Ops] keeps them until the completion event of the request is reaped,
+//! then hands them back.
+//! The buffers are a `Vec<u8>` by default, and also `Bytes` or `BytesMut`
+//! with the `bytes` feature.
+
+use std::ffi::CString;
+use std::io;
+use std::net::SocketAddr;
+use std::os::unix::io::RawFd;
+
+use crate::cqueue;
+use crate::opcode::{self, types};
+use crate::squeue::{self, AvailableQueue, PushError};
+use crate::tracker::OpTracker;
+use crate::util;
+
+/// An operation that owns the memory its request refers to.
+///
+/// # Safety
+///
+/// The entry built by [Op::entry] may only refer to memory owned by the operation
+/// that does not move with it, like the contents of a `Vec` or a `Box`,
+/// and that stays valid until [Op::complete] or the operation is dropped.
+pub unsafe trait Op {
+    /// What the operation hands back once it has completed.
+    type Output;
+
+    /// Build the entry of the request.
+    fn entry(&mut self) -> squeue::Entry;
+
+    /// Turn the completion event of the request into the output.
+    fn complete(self, entry: &cqueue::Entry) -> Self::Output;
+}
+
+/// The operations in flight, keyed by the `user_data` of their requests.
+///
+/// Dropping this while an operation is in flight leaks the operation,
+/// because the kernel may still use its memory.
+pub struct Ops<O> {
+    ops: OpTracker<O>,
+}
+
+impl<O: Op> Ops<O> {
+    pub fn new() -> Ops<O> {
+        Ops {
+            ops: OpTracker::new(),
+        }
+    }
+
+    /// The number of operations in flight.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Push the request of an operation into the submission queue,
+    /// and keep the operation until its completion event is passed to [Ops::complete].
+    ///
+    /// The `user_data` of the entry is set to the returned key.
+    pub fn push(&mut self, sq: &mut AvailableQueue<'_>, mut op: O) -> Result<u64, PushError<O>> {
+        let entry = op.entry();
+
+        // The operation owns the memory of the request, and is kept until it completes.
+        unsafe { self.ops.push(sq, entry, op) }
+            .map_err(|PushError::Full((_, op))| PushError::Full(op))
+    }
+
+    /// Complete the operation of a completion event,
+    /// returns `None` if the event does not belong to an operation in flight.
+    pub fn complete(&mut self, entry: &cqueue::Entry) -> Option<O::Output> {
+        let op = self.ops.complete(entry)?;
+        Some(op.complete(entry))
+    }
+}
+
+impl<O: Op> Default for Ops<O> {
+    fn default() -> Ops<O> {
+        Ops::new()
+    }
+}
+
+/// A buffer that an operation writes from.
+///
+/// # Safety
+///
+/// The bytes behind [OwnedBuf::stable_ptr] must not move with the buffer,
+/// and stay valid until it is dropped.
+pub unsafe trait OwnedBuf: 'static {
+    /// A pointer to the start of the buffer.
+    fn stable_ptr(&self) -> *const u8;
+
+    /// The number of initialized bytes, which are written.
+    fn bytes_init(&self) -> usize;
+}
+
+/// A buffer that an operation reads into, after its initialized bytes.
+///
+/// # Safety
+///
+/// The memory behind [OwnedBufMut::spare_mut_ptr] must not move with the buffer,
+/// stay valid until it is dropped, and have room for [OwnedBufMut::spare_len] bytes.
+pub unsafe trait OwnedBufMut: OwnedBuf {
+    /// A pointer to the spare capacity after the initialized bytes.
+    fn spare_mut_ptr(&mut self) -> *mut u8;
+
+    /// The size of the spare capacity.
+    fn spare_len(&self) -> usize;
+
+    /// Mark `n` bytes of the spare capacity as initialized.
+    ///
+    /// # Safety
+    ///
+    /// The `n` bytes must have been written, and be at most [OwnedBufMut::spare_len].
+    unsafe fn advance(&mut self, n: usize);
+}
+
+unsafe impl OwnedBuf for Vec<u8> {
+    fn stable_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len()
+    }
+}
+
+unsafe impl OwnedBufMut for Vec<u8> {
+    fn spare_mut_ptr(&mut self) -> *mut u8 {
+        self.spare_capacity_mut().as_mut_ptr().cast()
+    }
+
+    fn spare_len(&self) -> usize {
+        self.capacity() - self.len()
+    }
+
+    unsafe fn advance(&mut self, n: usize) {
+        self.set_len(self.len() + n);
+    }
+}
+
+#[cfg(feature = "bytes")]
+unsafe impl OwnedBuf for bytes::Bytes {
+    fn stable_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(feature = "bytes")]
+unsafe impl OwnedBuf for bytes::BytesMut {
+    fn stable_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(feature = "bytes")]
+unsafe impl OwnedBufMut for bytes::BytesMut {
+    fn spare_mut_ptr(&mut self) -> *mut u8 {
+        self.spare_capacity_mut().as_mut_ptr().cast()
+    }
+
+    fn spare_len(&self) -> usize {
+        self.capacity() - self.len()
+    }
+
+    unsafe fn advance(&mut self, n: usize) {
+        self.set_len(self.len() + n);
+    }
+}
+
+fn io_result(entry: &cqueue::Entry) -> io::Result<usize> {
+    entry.result_as_io().map(|n| n as usize)
+}
+
+/// Read into the spare capacity of a buffer, like `pread(2)`.
+///
+/// Hands back the buffer with the bytes read appended to it.
+pub struct Read<B = Vec<u8>> {
+    fd: RawFd,
+    buf: B,
+    offset: libc::off_t,
+}
+
+impl<B: OwnedBufMut> Read<B> {
+    pub fn new(fd: RawFd, buf: B, offset: libc::off_t) -> Read<B> {
+        Read { fd, buf, offset }
+    }
+}
+
+unsafe impl<B: OwnedBufMut> Op for Read<B> {
+    type Output = (io::Result<usize>, B);
+
+    fn entry(&mut self) -> squeue::Entry {
+        opcode::Read::new(
+            types::Fd(self.fd),
+            self.buf.spare_mut_ptr(),
+            self.buf.spare_len() as _,
+        )
+        .offset(self.offset)
+        .build()
+    }
+
+    fn complete(mut self, entry: &cqueue::Entry) -> Self::Output {
+        let ret = io_result(entry);
+        if let Ok(n) = ret {
+            unsafe { self.buf.advance(n) };
+        }
+        (ret, self.buf)
+    }
+}
+
+/// Write a buffer, like `pwrite(2)`.
+///
+/// Hands back the buffer unchanged.
+pub struct Write<B = Vec<u8>> {
+    fd: RawFd,
+    buf: B,
+    offset: libc::off_t,
+}
+
+impl<B: OwnedBuf> Write<B> {
+    pub fn new(fd: RawFd, buf: B, offset: libc::off_t) -> Write<B> {
+        Write { fd, buf, offset }
+    }
+}
+
+unsafe impl<B: OwnedBuf> Op for Write<B> {
+    type Output = (io::Result<usize>, B);
+
+    fn entry(&mut self) -> squeue::Entry {
+        opcode::Write::new(
+            types::Fd(self.fd),
+            self.buf.stable_ptr(),
+            self.buf.bytes_init() as _,
+        )
+        .offset(self.offset)
+        .build()
+    }
+
+    fn complete(self, entry: &cqueue::Entry) -> Self::Output {
+        (io_result(entry), self.buf)
+    }
+}
+
+/// Receive into the spare capacity of a buffer, like `recv(2)`.
+///
+/// Hands back the buffer with the bytes received appended to it.
+pub struct Recv<B = Vec<u8>> {
+    fd: RawFd,
+    buf: B,
+    flags: i32,
+}
+
+impl<B: OwnedBufMut> Recv<B> {
+    pub fn new(fd: RawFd, buf: B, flags: i32) -> Recv<B> {
+        Recv { fd, buf, flags }
+    }
+}
+
+unsafe impl<B: OwnedBufMut> Op for Recv<B> {
+    type Output = (io::Result<usize>, B);
+
+    fn entry(&mut self) -> squeue::Entry {
+        opcode::Recv::new(
+            types::Fd(self.fd),
+            self.buf.spare_mut_ptr(),
+            self.buf.spare_len() as _,
+        )
+        .flags(self.flags)
+        .build()
+    }
+
+    fn complete(mut self, entry: &cqueue::Entry) -> Self::Output {
+        let ret = io_result(entry);
+        if let Ok(n) = ret {
+            unsafe { self.buf.advance(n) };
+        }
+        (ret, self.buf)
+    }
+}
+
+/// Send a buffer, like `send(2)`.
+///
+/// Hands back the buffer unchanged.
+pub struct Send<B = Vec<u8>> {
+    fd: RawFd,
+    buf: B,
+    flags: i32,
+}
+
+impl<B: OwnedBuf> Send<B> {
+    pub fn new(fd: RawFd, buf: B, flags: i32) -> Send<B> {
+        Send { fd, buf, flags }
+    }
+}
+
+unsafe impl<B: OwnedBuf> Op for Send<B> {
+    type Output = (io::Result<usize>, B);
+
+    fn entry(&mut self) -> squeue::Entry {
+        opcode::Send::new(
+            types::Fd(self.fd),
+            self.buf.stable_ptr(),
+            self.buf.bytes_init() as _,
+        )
+        .flags(self.flags)
+        .build()
+    }
+
+    fn complete(self, entry: &cqueue::Entry) -> Self::Output {
+        (io_result(entry), self.buf)
+    }
+}
+
+/// Open a file, like `openat(2)`.
+///
+/// Hands back the new file descriptor.
+pub struct Openat {
+    dirfd: RawFd,
+    path: CString,
+    flags: i32,
+    mode: libc::mode_t,
+}
+
+impl Openat {
+    pub fn new(dirfd: RawFd, path: CString, flags: i32, mode: libc::mode_t) -> Openat {
+        Openat {
+            dirfd,
+            path,
+            flags,
+            mode,
+        }
+    }
+}
+
+unsafe impl Op for Openat {
+    type Output = io::Result<RawFd>;
+
+    fn entry(&mut self) -> squeue::Entry {
+        opcode::Openat::new(types::Fd(self.dirfd), self.path.as_ptr())
+            .flags(self.flags)
+            .mode(self.mode)
+            .build()
+    }
+
+    fn complete(self, entry: &cqueue::Entry) -> Self::Output {
+        entry.result_as_io().map(|fd| fd as RawFd)
+    }
+}
+
+/// Connect a socket, like `connect(2)`.
+pub struct Connect {
+    fd: RawFd,
+    addr: Box<(libc::sockaddr_storage, libc::socklen_t)>,
+}
+
+impl Connect {
+    pub fn new(fd: RawFd, addr: SocketAddr) -> Connect {
+        Connect {
+            fd,
+            addr: Box::new(util::sockaddr(addr)),
+        }
+    }
+}
+
+unsafe impl Op for Connect {
+    type Output = io::Result<()>;
+
+    fn entry(&mut self) -> squeue::Entry {
+        let (storage, len) = &*self.addr;
+        let addr = storage as *const libc::sockaddr_storage;
+
+        opcode::Connect::new(types::Fd(self.fd), addr.cast(), *len).build()
+    }
+
+    fn complete(self, entry: &cqueue::Entry) -> Self::Output {
+        entry.result_as_io().map(drop)
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::{MockRing, Reply};
+
+    #[test]
+    fn test_push_full_and_complete() {
+        let mut ring = MockRing::new(2);
+        let mut ops = Ops::new();
+
+        let keys: Vec<_> = (0..2)
+            .map(|_| {
+                let op = Write::new(3, vec![1; 4], 0);
+                ops.push(&mut ring.submission().available(), op).unwrap()
+            })
+            .collect();
+
+        // A rejected operation is handed back with its buffer.
+        match ops.push(
+            &mut ring.submission().available(),
+            Write::new(3, vec![2; 4], 0),
+        ) {
+            Err(PushError::Full(op)) => assert_eq!(op.buf, [2; 4]),
+            Ok(_) => panic!("pushed into a full queue"),
+        }
+        assert_eq!(ops.len(), 2);
+
+        ring.reply(keys[0], Reply::new(4))
+            .reply(keys[1], Reply::new(-libc::EAGAIN));
+        ring.submit().unwrap();
+
+        let outputs: Vec<_> = ring
+            .completion()
+            .available()
+            .map(|cqe| {
+                let (ret, buf) = ops.complete(&cqe).unwrap();
+                (ret.map_err(|err| err.raw_os_error()), buf)
+            })
+            .collect();
+        assert_eq!(
+            outputs,
+            [(Ok(4), vec![1; 4]), (Err(Some(libc::EAGAIN)), vec![1; 4])]
+        );
+        assert!(ops.is_empty());
+
+        let stale = cqueue::Entry::from(crate::sys::io_uring_cqe {
+            user_data: keys[0],
+            res: 0,
+            flags: 0,
+        });
+        assert!(ops.complete(&stale).is_none());
+    }
+
+    #[test]
+    fn test_failed_read_keeps_buf() {
+        let mut ring = MockRing::new(2);
+        let mut ops = Ops::new();
+
+        let mut buf = Vec::with_capacity(8);
+        buf.push(1);
+        let key = ops
+            .push(&mut ring.submission().available(), Read::new(3, buf, 0))
+            .unwrap();
+        ring.reply(key, Reply::new(-libc::EBADF));
+        ring.submit().unwrap();
+
+        let cqe = ring.completion().available().next().unwrap();
+        let (ret, buf) = ops.complete(&cqe).unwrap();
+        assert_eq!(ret.unwrap_err().raw_os_error(), Some(libc::EBADF));
+        assert_eq!(buf, [1]);
+        assert!(buf.capacity() >= 8);
+    }
+
+    #[cfg(feature = "bytes")]
+    fn run<O: Op>(ring: &mut crate::IoUring, op: O) -> O::Output {
+        let mut ops = Ops::new();
+        assert!(ops.push(&mut ring.submission().available(), op).is_ok());
+        ring.submit_and_wait(1).unwrap();
+
+        let cqe = ring.completion().available().next().unwrap();
+        ops.complete(&cqe).unwrap()
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_bytes() -> io::Result<()> {
+        use bytes::{Bytes, BytesMut};
+
+        let mut ring = crate::IoUring::new(4)?;
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+
+        let (ret, buf) = run(
+            &mut ring,
+            Write::new(fds[1], Bytes::from_static(b"hello"), -1),
+        );
+        assert_eq!(ret?, 5);
+        assert_eq!(buf, "hello");
+
+        let mut buf = BytesMut::with_capacity(16);
+        buf.extend_from_slice(b"> ");
+        let (ret, buf) = run(&mut ring, Read::new(fds[0], buf, -1));
+        assert_eq!(ret?, 5);
+        assert_eq!(buf, "> hello");
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+//! Registered credentials.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::os::unix::io::AsRawFd;
+use std::{io, mem, ptr};
+
+use crate::register::execute;
+use crate::sys;
+use crate::util::{dup, Fd};
+use crate::IoUring;
+
+/// The id of credentials registered with a ring,
+/// requests set with [Entry::personality](crate::squeue::Entry::personality) run with them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PersonalityId(u16);
+
+impl PersonalityId {
+    /// Wrap an id returned by [Submitter::register_personality](crate::Submitter::register_personality).
+    #[inline]
+    pub const fn from_raw(id: u16) -> PersonalityId {
+        PersonalityId(id)
+    }
+
+    #[inline]
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+}
+
+/// Credentials registered with a ring, by key, e.g. by user for a gateway
+/// performing I/O on behalf of its users.
+///
+/// Registering captures the credentials of the calling thread, so they are typically
+/// registered between `setfsuid(2)`-style calls switching to a user, and back.
+/// All the credentials are unregistered when the registry is dropped,
+/// the requests already submitted keep running with theirs.
+pub struct Personalities<K> {
+    ring: Fd,
+    ids: HashMap<K, PersonalityId>,
+}
+
+impl<K: Eq + Hash> Personalities<K> {
+    /// Create a registry for `ring`, which it keeps open.
+    pub fn new(ring: &IoUring) -> io::Result<Personalities<K>> {
+        Ok(Personalities {
+            ring: dup(ring.as_raw_fd())?,
+            ids: HashMap::new(),
+        })
+    }
+
+    /// The number of registered credentials.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// The id of the credentials of `key`.
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<PersonalityId> {
+        self.ids.get(key).copied()
+    }
+
+    /// Register the credentials of the calling thread for `key`,
+    /// replacing and unregistering those registered before.
+    pub fn register(&mut self, key: K) -> io::Result<PersonalityId> {
+        let id = register(&self.ring)?;
+
+        match self.ids.insert(key, id) {
+            Some(old) => unregister(&self.ring, old).map(|_| id),
+            None => Ok(id),
+        }
+    }
+
+    /// Register the credentials of the calling thread again for `key`,
+    /// e.g. after they changed, and return the new id.
+    ///
+    /// Fails with [NotFound](io::ErrorKind::NotFound) if no credentials are registered for `key`.
+    pub fn refresh(&mut self, key: &K) -> io::Result<PersonalityId> {
+        let slot = match self.ids.get_mut(key) {
+            Some(slot) => slot,
+            None => return Err(io::ErrorKind::NotFound.into()),
+        };
+
+        let id = register(&self.ring)?;
+        let old = mem::replace(slot, id);
+        unregister(&self.ring, old).map(|_| id)
+    }
+
+    /// Unregister the credentials of `key`, returns `false` if there are none.
+    pub fn unregister(&mut self, key: &K) -> io::Result<bool> {
+        match self.ids.remove(key) {
+            Some(id) => unregister(&self.ring, id).map(|_| true),
+            None => Ok(false),
+        }
+    }
+}
+
+impl<K> Drop for Personalities<K> {
+    fn drop(&mut self) {
+        for &id in self.ids.values() {
+            let _ = unregister(&self.ring, id);
+        }
+    }
+}
+
+fn register(ring: &Fd) -> io::Result<PersonalityId> {
+    let id = execute(
+        ring.as_raw_fd(),
+        sys::IORING_REGISTER_PERSONALITY,
+        ptr::null(),
+        0,
+    )?;
+
+    Ok(PersonalityId(id as u16))
+}
+
+fn unregister(ring: &Fd, id: PersonalityId) -> io::Result<()> {
+    execute(
+        ring.as_raw_fd(),
+        sys::IORING_UNREGISTER_PERSONALITY,
+        ptr::null(),
+        id.0 as _,
+    )
+    .map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opcode;
+
+    /// Run a no-op with the credentials of `id`, which fails if they are not registered.
+    fn nop(ring: &mut IoUring, id: PersonalityId) -> i32 {
+        let entry = opcode::Nop::new().build().personality(id);
+        unsafe { ring.submission().available().push(entry).unwrap() };
+        ring.submit_and_wait(1).unwrap();
+        ring.completion().available().next().unwrap().result()
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut ring = IoUring::new(4).unwrap();
+        let mut registry = Personalities::new(&ring).unwrap();
+        assert!(registry.is_empty());
+
+        let a = registry.register("a").unwrap();
+        let b = registry.register("b").unwrap();
+        assert_ne!(a, b);
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.get(&"a"), Some(a));
+        assert_eq!(nop(&mut ring, a), 0);
+
+        // Registering a key again unregisters its old credentials.
+        let a2 = registry.register("a").unwrap();
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.get(&"a"), Some(a2));
+        assert_eq!(nop(&mut ring, a), -libc::EINVAL);
+        assert_eq!(nop(&mut ring, a2), 0);
+
+        let a3 = registry.refresh(&"a").unwrap();
+        assert_eq!(registry.get(&"a"), Some(a3));
+        assert_eq!(nop(&mut ring, a2), -libc::EINVAL);
+        let err = registry.refresh(&"c").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        assert!(registry.unregister(&"a").unwrap());
+        assert!(!registry.unregister(&"a").unwrap());
+        assert_eq!(registry.get(&"a"), None);
+        assert_eq!(nop(&mut ring, a3), -libc::EINVAL);
+
+        drop(registry);
+        assert_eq!(nop(&mut ring, b), -libc::EINVAL);
+    }
+}
@@ -0,0 +1,291 @@
+//! Child processes supervised through the ring.
+//!
+//! A [PidFd] refers to a process, and becomes readable once it has exited,
+//! so a supervisor can wait for the exit of many children in its event loop
+//! with [PollAdd](opcode::PollAdd), and collect their status with [PidFd::try_wait].
+//! From Linux 6.7 on, [Waitid](opcode::Waitid) collects the status in the ring itself.
+//!
+//! ```no_run
+//! use io_uring::process::PidFd;
+//! use std::process::Command;
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let mut ring = io_uring::IoUring::new(8)?;
+//! let child = Command::new("true").spawn()?;
+//! let pidfd = PidFd::from_child(&child)?;
+//!
+//! unsafe {
+//!     ring.submission()
+//!         .available()
+//!         .push(pidfd.poll_exit().build().user_data(child.id() as u64))
+//!         .expect("queue is full");
+//! }
+//! ring.submit_and_wait(1)?;
+//!
+//! let cqe = ring.completion().available().next().expect("completion queue is empty");
+//! assert_eq!(cqe.user_data(), child.id() as u64);
+//! assert!(pidfd.try_wait()?.expect("child is running").success());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryInto;
+use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Child, ExitStatus};
+use std::{fmt, io, mem};
+
+use crate::opcode::{self, types};
+use crate::util::Fd;
+
+/// A file descriptor referring to a process, see `pidfd_open(2)`.
+pub struct PidFd {
+    fd: Fd,
+    pid: libc::pid_t,
+}
+
+/// The state of a child, written by [Waitid](opcode::Waitid) or [PidFd::try_wait].
+#[repr(transparent)]
+pub struct WaitInfo(libc::siginfo_t);
+
+/// The fields of `siginfo_t` for `SIGCHLD`, which libc does not name.
+#[repr(C)]
+struct SigChld {
+    signo: libc::c_int,
+    errno: libc::c_int,
+    code: libc::c_int,
+    // The union of the fields is aligned like a pointer, so it follows
+    // a padding on 64-bit targets.
+    fields: SigChldFields,
+}
+
+#[repr(C)]
+struct SigChldFields {
+    pid: libc::pid_t,
+    uid: libc::uid_t,
+    status: libc::c_int,
+    _utime: libc::c_long,
+    _stime: libc::c_long,
+}
+
+impl PidFd {
+    /// Open a pidfd for the process `pid`, requires Linux 5.3.
+    pub fn open(pid: libc::pid_t) -> io::Result<PidFd> {
+        let ret = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) };
+        let fd = (ret as RawFd)
+            .try_into()
+            .map_err(|_| io::Error::last_os_error())?;
+
+        Ok(PidFd { fd, pid })
+    }
+
+    /// Open a pidfd for a child, which must not have been waited for yet.
+    pub fn from_child(child: &Child) -> io::Result<PidFd> {
+        PidFd::open(child.id() as _)
+    }
+
+    /// The id of the process.
+    #[inline]
+    pub fn pid(&self) -> libc::pid_t {
+        self.pid
+    }
+
+    /// A poll that completes once the process has exited.
+    ///
+    /// The pidfd must stay open until the request completes.
+    pub fn poll_exit(&self) -> opcode::PollAdd {
+        opcode::PollAdd::new(types::Fd(self.fd.as_raw_fd()), libc::POLLIN)
+    }
+
+    /// A wait for the exit of the child, whose state is written to `info`,
+    /// requires Linux 6.7.
+    ///
+    /// The pidfd must stay open, and `info` valid, until the request completes.
+    /// Other states can be waited for by setting the options of the request.
+    pub fn wait(&self, info: &mut WaitInfo) -> opcode::Waitid {
+        opcode::Waitid::new(
+            libc::P_PIDFD,
+            self.fd.as_raw_fd() as _,
+            info as *mut WaitInfo as *mut libc::siginfo_t,
+        )
+    }
+
+    /// Collect the exit status of the child without blocking,
+    /// returns `None` if it is still running.
+    ///
+    /// This is meant to be called once the poll of [PidFd::poll_exit] completes.
+    pub fn try_wait(&self) -> io::Result<Option<ExitStatus>> {
+        let mut info = WaitInfo::default();
+
+        let ret = unsafe {
+            libc::waitid(
+                libc::P_PIDFD,
+                self.fd.as_raw_fd() as _,
+                &mut info.0,
+                libc::WEXITED | libc::WNOHANG,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(info.exit_status())
+    }
+
+    /// Send `signal` to the process, requires Linux 5.1.
+    ///
+    /// Unlike `kill(2)`, the signal cannot reach another process that reused the id.
+    pub fn kill(&self, signal: libc::c_int) -> io::Result<()> {
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_pidfd_send_signal,
+                self.fd.as_raw_fd(),
+                signal,
+                std::ptr::null::<libc::siginfo_t>(),
+                0,
+            )
+        };
+
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl AsRawFd for PidFd {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for PidFd {
+    #[inline]
+    fn into_raw_fd(self) -> RawFd {
+        self.fd.into_raw_fd()
+    }
+}
+
+impl fmt::Debug for PidFd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PidFd")
+            .field("fd", &self.fd.as_raw_fd())
+            .field("pid", &self.pid)
+            .finish()
+    }
+}
+
+impl WaitInfo {
+    #[inline]
+    fn chld(&self) -> &SigChld {
+        unsafe { &*(&self.0 as *const libc::siginfo_t as *const SigChld) }
+    }
+
+    /// The id of the child, 0 if no child has changed state.
+    pub fn pid(&self) -> libc::pid_t {
+        self.chld().fields.pid
+    }
+
+    /// The state of the child, such as `libc::CLD_EXITED` or `libc::CLD_KILLED`.
+    pub fn code(&self) -> libc::c_int {
+        self.chld().code
+    }
+
+    /// The exit code or the signal of the child, depending on [WaitInfo::code].
+    pub fn status(&self) -> libc::c_int {
+        self.chld().fields.status
+    }
+
+    /// The exit status of the child, `None` if it has not terminated.
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        let status = self.status();
+
+        // Encode the status the way `waitpid(2)` reports it.
+        let raw = match self.code() {
+            libc::CLD_EXITED => (status & 0xff) << 8,
+            libc::CLD_KILLED => status & 0x7f,
+            libc::CLD_DUMPED => status & 0x7f | 0x80,
+            _ => return None,
+        };
+
+        Some(ExitStatus::from_raw(raw))
+    }
+}
+
+impl Default for WaitInfo {
+    fn default() -> WaitInfo {
+        WaitInfo(unsafe { mem::zeroed() })
+    }
+}
+
+impl fmt::Debug for WaitInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chld = self.chld();
+        f.debug_struct("WaitInfo")
+            .field("signo", &chld.signo)
+            .field("errno", &chld.errno)
+            .field("code", &chld.code)
+            .field("pid", &chld.fields.pid)
+            .field("uid", &chld.fields.uid)
+            .field("status", &chld.fields.status)
+            .finish()
+    }
+}
+
+// The children are reaped through their pidfd.
+#[cfg(test)]
+#[allow(clippy::zombie_processes)]
+mod tests {
+    use super::*;
+    use crate::IoUring;
+    use std::process::Command;
+
+    fn complete(ring: &mut IoUring, entry: crate::squeue::Entry) -> i32 {
+        unsafe { ring.submission().available().push(entry).unwrap() };
+        ring.submit_and_wait(1).unwrap();
+        ring.completion().available().next().unwrap().result()
+    }
+
+    #[test]
+    fn test_poll_exit() {
+        let mut ring = IoUring::new(4).unwrap();
+        let child = Command::new("sh").args(["-c", "exit 3"]).spawn().unwrap();
+        let pidfd = PidFd::from_child(&child).unwrap();
+        assert_eq!(pidfd.pid(), child.id() as libc::pid_t);
+
+        let ret = complete(&mut ring, pidfd.poll_exit().build());
+        assert_eq!(ret & libc::POLLIN as i32, libc::POLLIN as i32);
+
+        let status = pidfd.try_wait().unwrap().unwrap();
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[test]
+    fn test_kill() {
+        let mut ring = IoUring::new(4).unwrap();
+        let child = Command::new("sleep").arg("60").spawn().unwrap();
+        let pidfd = PidFd::from_child(&child).unwrap();
+        assert!(pidfd.try_wait().unwrap().is_none());
+
+        pidfd.kill(libc::SIGKILL).unwrap();
+        complete(&mut ring, pidfd.poll_exit().build());
+
+        let status = pidfd.try_wait().unwrap().unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+    }
+
+    #[test]
+    fn test_waitid() {
+        let mut ring = IoUring::new(4).unwrap();
+        let child = Command::new("sh").args(["-c", "exit 5"]).spawn().unwrap();
+        let pidfd = PidFd::from_child(&child).unwrap();
+
+        let mut info = WaitInfo::default();
+        assert_eq!(complete(&mut ring, pidfd.wait(&mut info).build()), 0);
+        assert_eq!(info.pid(), pidfd.pid());
+        assert_eq!(info.code(), libc::CLD_EXITED);
+        assert_eq!(info.exit_status().and_then(|status| status.code()), Some(5));
+    }
+}
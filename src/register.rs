@@ -1,6 +1,7 @@
 use std::os::unix::io::RawFd;
 use std::{io, mem, ptr};
 
+use crate::opcode::OpCode;
 use crate::sys;
 
 pub(crate) fn execute(
@@ -9,14 +10,7 @@ pub(crate) fn execute(
     arg: *const libc::c_void,
     len: libc::c_uint,
 ) -> io::Result<i32> {
-    unsafe {
-        let ret = sys::io_uring_register(fd, opcode, arg, len);
-        if ret >= 0 {
-            Ok(ret)
-        } else {
-            Err(io::Error::last_os_error())
-        }
-    }
+    unsafe { sys::io_uring_register(fd, opcode, arg, len) }
 }
 
 pub struct Probe(ptr::NonNull<sys::io_uring_probe>);
@@ -59,6 +53,13 @@ impl Probe {
             }
         }
     }
+
+    /// Iterate over the known operations supported by the kernel.
+    pub fn supported_ops(&self) -> impl Iterator<Item = OpCode> + '_ {
+        (0..=u8::MAX)
+            .filter(move |&code| self.is_supported(code))
+            .filter_map(OpCode::from_raw)
+    }
 }
 
 impl Default for Probe {
@@ -79,3 +80,92 @@ impl Drop for Probe {
         }
     }
 }
+
+/// What the running kernel supports, see [IoUring::capabilities](crate::IoUring::capabilities).
+///
+/// Features without an opcode or a flag of their own cannot be probed,
+/// the `likely_supports_*` methods infer them from an operation added by the same
+/// kernel release. This is a heuristic, which backported operations can fool,
+/// so the result of a request should still be checked.
+#[cfg(feature = "unstable")]
+pub struct Capabilities {
+    pub(crate) params: crate::Parameters,
+    pub(crate) probe: Probe,
+    pub(crate) buf_ring: bool,
+}
+
+#[cfg(feature = "unstable")]
+impl Capabilities {
+    /// The parameters of the ring, with its features.
+    #[inline]
+    pub fn params(&self) -> &crate::Parameters {
+        &self.params
+    }
+
+    /// The supported operations, empty before Linux 5.6.
+    #[inline]
+    pub fn probe(&self) -> &Probe {
+        &self.probe
+    }
+
+    #[inline]
+    pub fn is_supported(&self, opcode: OpCode) -> bool {
+        self.probe.is_supported(opcode as u8)
+    }
+
+    /// Whether [TimeoutUpdate](crate::opcode::TimeoutUpdate) is likely supported (Linux 5.11),
+    /// inferred from [OpCode::Shutdown].
+    pub fn likely_supports_timeout_update(&self) -> bool {
+        self.probe.is_supported(sys::IORING_OP_SHUTDOWN as _)
+    }
+
+    /// Whether [Close](crate::opcode::Close) likely frees [Fixed](crate::opcode::types::Fixed)
+    /// slots (Linux 5.15), inferred from [OpCode::Linkat].
+    pub fn likely_supports_close_fixed(&self) -> bool {
+        self.probe.is_supported(sys::IORING_OP_LINKAT as _)
+    }
+
+    /// Whether [Flags::CQE_SKIP_SUCCESS](crate::squeue::Flags::CQE_SKIP_SUCCESS) is supported
+    /// (Linux 5.17).
+    pub fn supports_cqe_skip(&self) -> bool {
+        self.params.0.features & sys::IORING_FEAT_CQE_SKIP != 0
+    }
+
+    /// Whether [AcceptMulti](crate::opcode::AcceptMulti) is likely supported (Linux 5.19),
+    /// inferred from [OpCode::Socket].
+    pub fn likely_supports_multishot_accept(&self) -> bool {
+        self.probe.is_supported(sys::IORING_OP_SOCKET as _)
+    }
+
+    /// Whether canceling by file with [AsyncCancelFd](crate::opcode::AsyncCancelFd),
+    /// and the [AsyncCancelFlags](crate::opcode::types::AsyncCancelFlags), are likely supported
+    /// (Linux 5.19), inferred from [OpCode::Socket].
+    pub fn likely_supports_cancel_flags(&self) -> bool {
+        self.probe.is_supported(sys::IORING_OP_SOCKET as _)
+    }
+
+    /// Whether rings of provided buffers can be registered, as checked by trying it
+    /// (Linux 5.19).
+    #[inline]
+    pub fn supports_buf_ring(&self) -> bool {
+        self.buf_ring
+    }
+
+    /// Whether multishot receives are likely supported (Linux 6.0),
+    /// inferred from [OpCode::SendZc].
+    pub fn likely_supports_multishot_recv(&self) -> bool {
+        self.probe.is_supported(sys::IORING_OP_SEND_ZC as _)
+    }
+
+    /// Whether [MsgRingSendFd](crate::opcode::MsgRingSendFd) is likely supported (Linux 6.0),
+    /// inferred from [OpCode::SendZc].
+    pub fn likely_supports_msg_ring_fd(&self) -> bool {
+        self.probe.is_supported(sys::IORING_OP_SEND_ZC as _)
+    }
+
+    /// Whether [Submitter::submit_with_timeout](crate::Submitter::submit_with_timeout)
+    /// and the other waits with a timeout are supported (Linux 5.11).
+    pub fn supports_ext_arg(&self) -> bool {
+        self.params.is_feature_ext_arg()
+    }
+}
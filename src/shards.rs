@@ -0,0 +1,214 @@
+//! Thread-per-core rings.
+//!
+//! [Shards] creates one ring per thread, sharing a single pool of kernel workers,
+//! and each [Shard] can post completion events to the rings of the other shards,
+//! to hand a request over to the thread that owns the resource.
+
+use std::os::unix::io::AsRawFd;
+use std::sync::Arc;
+use std::{io, thread};
+
+use crate::opcode::{self, types};
+use crate::squeue::{self, Flags, PushError};
+use crate::util::{dup, Fd};
+use crate::{Builder, IoUring};
+
+/// A group of rings, one per thread.
+pub struct Shards {
+    rings: Vec<IoUring>,
+    peers: Arc<[Fd]>,
+}
+
+/// A ring of a [Shards] group, meant to be owned by a single thread.
+pub struct Shard {
+    id: usize,
+    ring: IoUring,
+    peers: Arc<[Fd]>,
+}
+
+impl Shards {
+    /// Create `count` rings of `entries` entries.
+    #[inline]
+    pub fn new(count: usize, entries: u32) -> io::Result<Shards> {
+        Shards::with_builder(&Builder::default(), count, entries)
+    }
+
+    /// Create `count` rings of `entries` entries with the parameters of `builder`.
+    ///
+    /// All rings after the first one attach to its io-wq with `IORING_SETUP_ATTACH_WQ`,
+    /// so the shards do not each start their own kernel workers.
+    pub fn with_builder(builder: &Builder, count: usize, entries: u32) -> io::Result<Shards> {
+        let mut rings = Vec::with_capacity(count);
+
+        if count > 0 {
+            let first = builder.build(entries)?;
+            let mut builder = builder.clone();
+            builder.setup_attach_wq(first.as_raw_fd());
+            rings.push(first);
+
+            for _ in 1..count {
+                rings.push(builder.build(entries)?);
+            }
+        }
+
+        // Each shard keeps its own handle on the other rings,
+        // so that a message never goes to a closed and reused file descriptor.
+        let peers = rings
+            .iter()
+            .map(|ring| dup(ring.as_raw_fd()))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(Shards {
+            rings,
+            peers: peers.into(),
+        })
+    }
+
+    /// The number of shards.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.rings.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.rings.is_empty()
+    }
+
+    /// Split the group into its shards, ordered by id,
+    /// to be moved to the threads that own them.
+    pub fn into_shards(self) -> Vec<Shard> {
+        let peers = self.peers;
+
+        self.rings
+            .into_iter()
+            .enumerate()
+            .map(|(id, ring)| Shard {
+                id,
+                ring,
+                peers: peers.clone(),
+            })
+            .collect()
+    }
+
+    /// Spawn a thread per shard that runs `f` with it,
+    /// and return their join handles, ordered by shard id.
+    pub fn spawn<F, T>(self, f: F) -> io::Result<Vec<thread::JoinHandle<T>>>
+    where
+        F: Fn(Shard) -> T + Send + Sync + 'static,
+        T: Send + 'static,
+    {
+        let f = Arc::new(f);
+
+        self.into_shards()
+            .into_iter()
+            .map(|shard| {
+                let f = f.clone();
+                thread::Builder::new()
+                    .name(format!("io-uring-shard-{}", shard.id))
+                    .spawn(move || f(shard))
+            })
+            .collect()
+    }
+}
+
+impl Shard {
+    /// The index of this shard in its group.
+    #[inline]
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// The number of shards in the group.
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// The ring of this shard.
+    #[inline]
+    pub fn ring(&mut self) -> &mut IoUring {
+        &mut self.ring
+    }
+
+    /// Build an entry that posts a completion event with `user_data` and `result`
+    /// to the ring of the shard `target`.
+    ///
+    /// The entry has [Flags::CQE_SKIP_SUCCESS] set,
+    /// so this ring only gets a completion event if the message could not be delivered.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `target` is not a shard of the group.
+    pub fn message(&self, target: usize, user_data: u64, result: i32) -> squeue::Entry {
+        let fd = self.peers[target].as_raw_fd();
+
+        opcode::MsgRing::new(types::Fd(fd), result, user_data)
+            .build()
+            .flags(Flags::CQE_SKIP_SUCCESS)
+    }
+
+    /// Push a [message](Shard::message) for the shard `target` into the submission queue,
+    /// it is delivered on the next submit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `target` is not a shard of the group.
+    pub fn steer(
+        &mut self,
+        target: usize,
+        user_data: u64,
+        result: i32,
+    ) -> Result<(), PushError<squeue::Entry>> {
+        let entry = self.message(target, user_data, result);
+
+        // The entry only refers to a ring file descriptor that the shard keeps open.
+        unsafe { self.ring.submission().available().push(entry) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steer() {
+        let shards = Shards::new(3, 8).unwrap();
+        assert_eq!(shards.len(), 3);
+
+        let mut shards = shards.into_shards();
+        assert_eq!(shards.iter().map(Shard::id).collect::<Vec<_>>(), [0, 1, 2]);
+        assert!(shards.iter().all(|shard| shard.count() == 3));
+
+        shards[0].steer(2, 0x42, 7).unwrap();
+        shards[0].ring().submit().unwrap();
+        shards[2].ring().submitter().wait(1).unwrap();
+
+        let cqe = shards[2].ring().completion().available().next().unwrap();
+        assert_eq!((cqe.user_data(), cqe.result()), (0x42, 7));
+
+        // The message was delivered, so neither the sender nor the other shard has an event.
+        assert!(shards[0].ring().completion().is_empty());
+        assert!(shards[1].ring().completion().is_empty());
+    }
+
+    #[test]
+    fn test_spawn_ring_of_messages() {
+        let handles = Shards::new(4, 8)
+            .unwrap()
+            .spawn(|mut shard| {
+                let next = (shard.id() + 1) % shard.count();
+                shard.steer(next, shard.id() as u64, 0).unwrap();
+                shard.ring().submit_and_wait(1).unwrap();
+
+                let cqe = shard.ring().completion().available().next().unwrap();
+                (shard.id(), cqe.user_data())
+            })
+            .unwrap();
+
+        for handle in handles {
+            let (id, from) = handle.join().unwrap();
+            assert_eq!((from as usize + 1) % 4, id);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use crate::{CompletionQueue, IoUring, SubmissionQueue, Submitter};
+
+/// The submission side of a ring, see [IoUring::split_owned].
+pub struct SubmissionHalf {
+    ring: Arc<IoUring>,
+    sq: SubmissionQueue,
+}
+
+/// The completion side of a ring, see [IoUring::split_owned].
+pub struct CompletionHalf {
+    ring: Arc<IoUring>,
+    cq: CompletionQueue,
+}
+
+// Each half has exclusive access to its queue,
+// and the ring itself is only used through `&self` methods.
+unsafe impl Send for SubmissionHalf {}
+unsafe impl Send for CompletionHalf {}
+
+pub(crate) fn split(ring: IoUring) -> (SubmissionHalf, CompletionHalf) {
+    // The queues of the ring are not pushed to while it is split,
+    // they are only resynced by the last half, before the ring uses them on drop.
+    let (sq, cq) = unsafe { (ring.sq.alias(), ring.cq.alias()) };
+    let ring = Arc::new(ring);
+
+    (
+        SubmissionHalf {
+            ring: ring.clone(),
+            sq,
+        },
+        CompletionHalf { ring, cq },
+    )
+}
+
+impl SubmissionHalf {
+    #[inline]
+    pub fn submitter(&self) -> Submitter<'_> {
+        submitter(&self.ring, &self.sq)
+    }
+
+    #[inline]
+    pub fn submission(&mut self) -> &mut SubmissionQueue {
+        &mut self.sq
+    }
+
+    /// Get the submitter and the submission queue at the same time.
+    #[inline]
+    pub fn split(&mut self) -> (Submitter<'_>, &mut SubmissionQueue) {
+        (submitter(&self.ring, &self.sq), &mut self.sq)
+    }
+}
+
+impl CompletionHalf {
+    /// The submitter can be used to wait for completions from this half.
+    #[inline]
+    pub fn submitter(&self) -> Submitter<'_> {
+        submitter(&self.ring, &self.ring.sq)
+    }
+
+    #[inline]
+    pub fn completion(&mut self) -> &mut CompletionQueue {
+        &mut self.cq
+    }
+
+    #[cfg(feature = "unstable")]
+    pub(crate) fn ring(&self) -> &Arc<IoUring> {
+        &self.ring
+    }
+}
+
+impl Drop for SubmissionHalf {
+    fn drop(&mut self) {
+        resync(&mut self.ring);
+    }
+}
+
+impl Drop for CompletionHalf {
+    fn drop(&mut self) {
+        resync(&mut self.ring);
+    }
+}
+
+/// Reload the head the submission queue of the ring cached,
+/// once no half can advance the tail anymore.
+fn resync(ring: &mut Arc<IoUring>) {
+    if let Some(ring) = Arc::get_mut(ring) {
+        ring.sq.sync_head();
+    }
+}
+
+#[inline]
+fn submitter<'a>(ring: &'a IoUring, sq: &SubmissionQueue) -> Submitter<'a> {
+    Submitter::new(
+        &ring.fd,
+        &ring.params,
+        ring.ring_fd_index,
+        ring.retry_eintr,
+        sq,
+        &ring.cq,
+    )
+}
@@ -1,12 +1,21 @@
 //! Submission Queue
 
-use std::sync::atomic;
+#[cfg(feature = "stats")]
+use std::sync::Arc;
+use std::{error, fmt, ptr};
 
+use crate::opcode::OpCode;
+#[cfg(feature = "stats")]
+use crate::stats::SubmissionCounters;
+use crate::sync::atomic;
 use crate::sys;
 use crate::util::{unsync_load, Mmap};
 
 use bitflags::bitflags;
 
+/// The raw layout of a submission entry, see [Entry::from_raw].
+pub use crate::sys::io_uring_sqe;
+
 pub struct SubmissionQueue {
     pub(crate) head: *const atomic::AtomicU32,
     pub(crate) tail: *const atomic::AtomicU32,
@@ -14,8 +23,34 @@ pub struct SubmissionQueue {
     pub(crate) ring_entries: *const u32,
     pub(crate) flags: *const atomic::AtomicU32,
     dropped: *const atomic::AtomicU32,
+    array: *mut u32,
 
     pub(crate) sqes: *mut sys::io_uring_sqe,
+    sqe_shift: u32,
+
+    /// The head as last seen by an [AvailableQueue], only refreshed when the queue looks full.
+    cached_head: u32,
+
+    #[cfg(feature = "stats")]
+    pub(crate) stats: Arc<SubmissionCounters>,
+}
+
+/// The raw pointers of a submission queue, see [SubmissionQueue::raw_parts].
+///
+/// They are named after the fields of `struct io_uring_sq` of liburing.
+#[derive(Debug, Clone, Copy)]
+pub struct RawParts {
+    pub khead: *mut u32,
+    pub ktail: *mut u32,
+    pub kring_mask: *mut u32,
+    pub kring_entries: *mut u32,
+    pub kflags: *mut u32,
+    pub kdropped: *mut u32,
+    /// The index array, which maps each slot of the ring to the entry of the same index.
+    /// It is null for rings set up with `IORING_SETUP_NO_SQARRAY`.
+    pub array: *mut u32,
+    /// The entries, which are 128 bytes each for rings set up with `IORING_SETUP_SQE128`.
+    pub sqes: *mut io_uring_sqe,
 }
 
 pub struct AvailableQueue<'a> {
@@ -26,11 +61,52 @@ pub struct AvailableQueue<'a> {
     queue: &'a mut SubmissionQueue,
 }
 
+/// A chain of linked entries, see [AvailableQueue::chain].
+pub struct Chain<'a, 'b> {
+    queue: &'a mut AvailableQueue<'b>,
+    len: u32,
+    link: Flags,
+    overflow: bool,
+}
+
+/// A free slot of the submission queue, see [AvailableQueue::slot].
+pub struct Slot<'a, 'b> {
+    queue: &'a mut AvailableQueue<'b>,
+}
+
 /// Submission Entry
 #[repr(transparent)]
 #[derive(Clone)]
 pub struct Entry(pub(crate) sys::io_uring_sqe);
 
+#[cfg(feature = "unstable")]
+/// A 128-byte Submission Entry,
+/// for rings created with [Builder::setup_sqe128](crate::Builder::setup_sqe128).
+///
+/// The second half extends the command area of the entry,
+/// which carries the payload of passthrough commands.
+#[repr(C)]
+#[derive(Clone)]
+pub struct Entry128(pub(crate) Entry, pub(crate) [u8; 64]);
+
+/// The application-supplied value identifying a request,
+/// copied from the submission entry into its completion entries.
+///
+/// It converts from and into a `u64`, or can carry a pointer to per-request state.
+#[repr(transparent)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UserData(u64);
+
+/// An error pushing into the submission queue.
+///
+/// [AvailableQueue::push] hands the [Entry] back,
+/// pushing a batch of entries only reports the error.
+#[non_exhaustive]
+pub enum PushError<T = ()> {
+    /// The queue does not have enough free space.
+    Full(T),
+}
+
 bitflags! {
     /// Submission flags
     pub struct Flags: u8 {
@@ -62,6 +138,13 @@ bitflags! {
 
         #[cfg(feature = "unstable")]
         const BUFFER_SELECT = 1 << sys::IOSQE_BUFFER_SELECT_BIT;
+
+        /// Don't post a completion event if the request succeeds,
+        /// only failures are reported.
+        ///
+        /// This cannot be combined with [IO_DRAIN] on the same ring.
+        #[cfg(feature = "unstable")]
+        const CQE_SKIP_SUCCESS = 1 << sys::IOSQE_CQE_SKIP_SUCCESS_BIT;
     }
 }
 
@@ -83,11 +166,48 @@ impl SubmissionQueue {
             let sqes            = sqe_mmap + 0                      => *mut sys::io_uring_sqe;
         }
 
-        // To keep it simple, map it directly to `sqes`.
-        for i in 0..*ring_entries {
-            array.add(i as usize).write_volatile(i);
+        // Map the array directly to `sqes` once, so pushing never has to write it.
+        // Without the array, the kernel indexes `sqes` with the ring index itself.
+        let array = if p.flags & sys::IORING_SETUP_NO_SQARRAY == 0 {
+            for i in 0..*ring_entries {
+                array.add(i as usize).write_volatile(i);
+            }
+            array
+        } else {
+            ptr::null_mut()
+        };
+
+        SubmissionQueue {
+            head,
+            tail,
+            ring_mask,
+            ring_entries,
+            flags,
+            dropped,
+            array,
+            sqes,
+            sqe_shift: if p.flags & sys::IORING_SETUP_SQE128 != 0 {
+                1
+            } else {
+                0
+            },
+            cached_head: (*head).load(atomic::Ordering::Acquire),
+            #[cfg(feature = "stats")]
+            stats: Default::default(),
         }
+    }
 
+    /// Build a queue over an in-memory ring, for the loom tests and the mock ring.
+    #[cfg(any(all(test, loom), feature = "mock"))]
+    pub(crate) unsafe fn from_raw_parts(
+        head: *const atomic::AtomicU32,
+        tail: *const atomic::AtomicU32,
+        ring_mask: *const u32,
+        ring_entries: *const u32,
+        flags: *const atomic::AtomicU32,
+        dropped: *const atomic::AtomicU32,
+        sqes: *mut sys::io_uring_sqe,
+    ) -> SubmissionQueue {
         SubmissionQueue {
             head,
             tail,
@@ -95,18 +215,84 @@ impl SubmissionQueue {
             ring_entries,
             flags,
             dropped,
+            array: ptr::null_mut(),
             sqes,
+            sqe_shift: 0,
+            cached_head: (*head).load(atomic::Ordering::Acquire),
+            #[cfg(feature = "stats")]
+            stats: Default::default(),
         }
     }
 
+    /// Create another handle to the same queue.
+    ///
+    /// # Safety
+    ///
+    /// Only one of the handles may be used to push,
+    /// and the handles must not outlive the ring.
+    pub(crate) unsafe fn alias(&self) -> SubmissionQueue {
+        SubmissionQueue {
+            head: self.head,
+            tail: self.tail,
+            ring_mask: self.ring_mask,
+            ring_entries: self.ring_entries,
+            flags: self.flags,
+            dropped: self.dropped,
+            array: self.array,
+            sqes: self.sqes,
+            sqe_shift: self.sqe_shift,
+            cached_head: self.cached_head,
+            #[cfg(feature = "stats")]
+            stats: self.stats.clone(),
+        }
+    }
+
+    /// Get the entry at a masked ring index, taking the entry size into account.
+    #[inline]
+    pub(crate) unsafe fn sqe(&self, index: u32) -> *mut sys::io_uring_sqe {
+        debug_assert!(index <= *self.ring_mask, "unmasked ring index");
+        self.sqes.add((index as usize) << self.sqe_shift)
+    }
+
+    #[cfg(feature = "unstable")]
+    /// Whether the entries of this queue are 128 bytes,
+    /// so that [Entry128] can be pushed.
+    #[inline]
+    pub fn is_sqe128(&self) -> bool {
+        self.sqe_shift != 0
+    }
+
+    /// When [Builder::setup_sqpoll](crate::Builder::setup_sqpoll) is enabled,
+    /// the poll thread goes to sleep after being idle and sets this flag.
+    /// It must then be woken up with `IORING_ENTER_SQ_WAKEUP`,
+    /// which [Submitter::submit](crate::Submitter::submit) does automatically.
     pub fn need_wakeup(&self) -> bool {
+        atomic::fence(atomic::Ordering::SeqCst);
+
         unsafe { (*self.flags).load(atomic::Ordering::Acquire) & sys::IORING_SQ_NEED_WAKEUP != 0 }
     }
 
+    /// Whether the kernel has task work pending that only runs when the ring is entered.
+    ///
+    /// This is only set with [Builder::setup_taskrun_flag](crate::Builder::setup_taskrun_flag),
+    /// [Submitter::submit](crate::Submitter::submit) then enters the kernel to run it.
+    #[cfg(feature = "unstable")]
+    pub fn taskrun(&self) -> bool {
+        unsafe { (*self.flags).load(atomic::Ordering::Acquire) & sys::IORING_SQ_TASKRUN != 0 }
+    }
+
+    /// The number of invalid entries that the kernel skipped instead of submitting,
+    /// for example because of an out-of-range index.
+    ///
+    /// No completion event is posted for these entries, so a growing counter
+    /// is the only sign that a submission was lost.
     pub fn dropped(&self) -> u32 {
         unsafe { (*self.dropped).load(atomic::Ordering::Acquire) }
     }
 
+    /// Whether completion events are waiting in the kernel because the completion queue was full.
+    ///
+    /// See [Submitter::flush_overflow](crate::Submitter::flush_overflow).
     #[cfg(feature = "unstable")]
     pub fn cq_overflow(&self) -> bool {
         unsafe { (*self.flags).load(atomic::Ordering::Acquire) & sys::IORING_SQ_CQ_OVERFLOW != 0 }
@@ -117,6 +303,43 @@ impl SubmissionQueue {
         unsafe { self.ring_entries.read() as usize }
     }
 
+    /// The mask applied to the head and tail to get an index into the ring.
+    #[inline]
+    pub fn ring_mask(&self) -> u32 {
+        unsafe { self.ring_mask.read() }
+    }
+
+    /// A snapshot of the head, which the kernel advances as it consumes entries.
+    #[inline]
+    pub fn head(&self) -> u32 {
+        unsafe { (*self.head).load(atomic::Ordering::Acquire) }
+    }
+
+    /// A snapshot of the tail, which advances as pushed entries are published.
+    #[inline]
+    pub fn tail(&self) -> u32 {
+        unsafe { unsync_load(self.tail) }
+    }
+
+    /// The pointers of the queue into the memory shared with the kernel,
+    /// so that it can be operated on by other code, such as C code using liburing.
+    ///
+    /// Entries pushed through an [AvailableQueue] are only published to the tail
+    /// once it is synced or dropped, so the other code may only push entries
+    /// while no [AvailableQueue] of this queue is alive.
+    pub fn raw_parts(&self) -> RawParts {
+        RawParts {
+            khead: self.head as *mut u32,
+            ktail: self.tail as *mut u32,
+            kring_mask: self.ring_mask as *mut u32,
+            kring_entries: self.ring_entries as *mut u32,
+            kflags: self.flags as *mut u32,
+            kdropped: self.dropped as *mut u32,
+            array: self.array,
+            sqes: self.sqes,
+        }
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         unsafe {
@@ -137,12 +360,40 @@ impl SubmissionQueue {
         self.len() == self.capacity()
     }
 
+    /// The number of entries that can still be pushed before the queue is full.
+    #[inline]
+    pub fn space_left(&self) -> usize {
+        self.capacity().saturating_sub(self.len())
+    }
+
+    /// Reload the cached head, after the tail was advanced through another handle.
+    #[inline]
+    pub(crate) fn sync_head(&mut self) {
+        self.cached_head = unsafe { (*self.head).load(atomic::Ordering::Acquire) };
+    }
+
     /// Get currently available submission queue
+    ///
+    /// Entries pushed into the returned [AvailableQueue] are only written locally,
+    /// the tail is published to the kernel when it is synced or dropped.
+    /// This lets a batch of entries become visible to an SQPOLL thread at once
+    /// instead of one by one.
+    ///
+    /// The head is not loaded from the kernel until the queue looks full,
+    /// so [AvailableQueue::len] may count entries the kernel already consumed.
     pub fn available(&mut self) -> AvailableQueue<'_> {
         unsafe {
+            let tail = unsync_load(self.tail);
+
+            // The cached head is stale if the queue looks full at it, or if the tail
+            // was advanced past it through another handle.
+            if tail.wrapping_sub(self.cached_head) >= self.ring_entries.read() {
+                self.sync_head();
+            }
+
             AvailableQueue {
-                head: (*self.head).load(atomic::Ordering::Acquire),
-                tail: unsync_load(self.tail),
+                head: self.cached_head,
+                tail,
                 ring_mask: self.ring_mask.read(),
                 ring_entries: self.ring_entries.read(),
                 queue: self,
@@ -153,21 +404,62 @@ impl SubmissionQueue {
 
 impl AvailableQueue<'_> {
     /// Sync queue
+    ///
+    /// Publishes all entries pushed so far with a single release store of the tail,
+    /// and refreshes the head to reclaim the entries the kernel consumed.
     pub fn sync(&mut self) {
+        #[cfg(any(feature = "tracing", feature = "stats"))]
+        self.record_push();
+
         unsafe {
             (*self.queue.tail).store(self.tail, atomic::Ordering::Release);
             self.head = (*self.queue.head).load(atomic::Ordering::Acquire);
         }
     }
 
+    /// Trace and count the entries pushed since the tail was last published.
+    #[cfg(any(feature = "tracing", feature = "stats"))]
+    fn record_push(&self) {
+        unsafe {
+            let mut tail = unsync_load(self.queue.tail);
+            while tail != self.tail {
+                #[cfg(feature = "tracing")]
+                crate::trace::push(&*self.queue.sqe(tail & self.ring_mask));
+                #[cfg(feature = "stats")]
+                self.queue.stats.push();
+                tail = tail.wrapping_add(1);
+            }
+        }
+    }
+
+    /// Check that `n` entries fit, reloading the head if they do not fit at the cached one.
+    #[inline]
+    fn has_space(&mut self, n: usize) -> bool {
+        if self.space_left() >= n {
+            return true;
+        }
+
+        self.head = unsafe { (*self.queue.head).load(atomic::Ordering::Acquire) };
+        self.space_left() >= n
+    }
+
     #[inline]
     pub fn capacity(&self) -> usize {
         self.ring_entries as usize
     }
 
+    /// The number of entries in the queue.
+    ///
+    /// This is computed from the cached head, which is only reloaded when the queue looks full.
     #[inline]
     pub fn len(&self) -> usize {
-        self.tail.wrapping_sub(self.head) as usize
+        let len = self.tail.wrapping_sub(self.head);
+        if len < self.ring_entries {
+            return len as usize;
+        }
+
+        let head = unsafe { (*self.queue.head).load(atomic::Ordering::Acquire) };
+        self.tail.wrapping_sub(head) as usize
     }
 
     #[inline]
@@ -177,29 +469,276 @@ impl AvailableQueue<'_> {
 
     #[inline]
     pub fn is_full(&self) -> bool {
-        self.tail.wrapping_sub(self.head) == self.ring_entries
+        self.len() >= self.capacity()
+    }
+
+    /// The number of entries that can still be pushed before the queue is full.
+    ///
+    /// This is computed from the cached head, so it may increase after [AvailableQueue::sync]
+    /// or a push that did not fit at first, if the kernel consumed entries.
+    #[inline]
+    pub fn space_left(&self) -> usize {
+        self.capacity().saturating_sub(self.len())
     }
 
     /// Attempts to push an [Entry] into the queue.
-    /// If the queue is full, the element is returned back as an error.
+    /// If the queue is full, the element is returned back in [PushError::Full].
     ///
     /// # Safety
     ///
     /// Developers must ensure that parameters of the [Entry] (such as buffer) are valid,
     /// otherwise it may cause memory problems.
-    pub unsafe fn push(&mut self, Entry(entry): Entry) -> Result<(), Entry> {
-        if !self.is_full() {
-            *self.queue.sqes.add((self.tail & self.ring_mask) as usize) = entry;
+    ///
+    /// The memory and file descriptors the entry refers to are used by the kernel
+    /// until the request completes, so they must stay valid until its completion event
+    /// has been posted, not only until it is submitted.
+    /// With the `unstable` feature, the `owned` module has operations that can be pushed safely.
+    pub unsafe fn push(&mut self, Entry(entry): Entry) -> Result<(), PushError<Entry>> {
+        if self.has_space(1) {
+            *self.queue.sqe(self.tail & self.ring_mask) = entry;
+            self.tail = self.tail.wrapping_add(1);
+            Ok(())
+        } else {
+            #[cfg(feature = "stats")]
+            self.queue.stats.full();
+            Err(PushError::Full(Entry(entry)))
+        }
+    }
+
+    #[cfg(feature = "unstable")]
+    /// Attempts to push an [Entry128] into the queue.
+    /// If the queue is full, the element is returned back in [PushError::Full].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ring was not created with [Builder::setup_sqe128](crate::Builder::setup_sqe128).
+    ///
+    /// # Safety
+    ///
+    /// Developers must ensure that parameters of the [Entry128] (such as buffer) are valid,
+    /// otherwise it may cause memory problems.
+    #[allow(clippy::result_large_err)]
+    pub unsafe fn push128(&mut self, entry: Entry128) -> Result<(), PushError<Entry128>> {
+        assert!(
+            self.queue.is_sqe128(),
+            "the ring does not use 128-byte entries"
+        );
+
+        if self.has_space(1) {
+            *(self.queue.sqe(self.tail & self.ring_mask) as *mut Entry128) = entry;
             self.tail = self.tail.wrapping_add(1);
             Ok(())
         } else {
-            Err(Entry(entry))
+            #[cfg(feature = "stats")]
+            self.queue.stats.full();
+            Err(PushError::Full(entry))
+        }
+    }
+
+    /// Attempts to push a raw submission entry into the queue,
+    /// for operations that have no builder in [opcode](crate::opcode) yet.
+    /// If the queue is full, the entry is returned back in [PushError::Full].
+    ///
+    /// # Safety
+    ///
+    /// Developers must ensure that the entry is well-formed for its opcode
+    /// and that the resources it refers to are valid, otherwise it may cause memory problems.
+    pub unsafe fn push_raw(&mut self, entry: io_uring_sqe) -> Result<(), PushError<io_uring_sqe>> {
+        self.push(Entry(entry))
+            .map_err(|err| PushError::Full(err.into_inner().0))
+    }
+
+    /// Attempts to push several [Entry] into the queue.
+    /// If the queue does not have enough space for all of them, nothing is pushed.
+    ///
+    /// The entries are contiguous in the queue and become visible to the kernel together,
+    /// which makes this suitable for linked entries.
+    ///
+    /// # Safety
+    ///
+    /// Developers must ensure that parameters of all the [Entry] (such as buffer) are valid,
+    /// otherwise it may cause memory problems.
+    pub unsafe fn push_multiple(&mut self, entries: &[Entry]) -> Result<(), PushError> {
+        if !self.has_space(entries.len()) {
+            #[cfg(feature = "stats")]
+            self.queue.stats.full();
+            return Err(PushError::Full(()));
+        }
+
+        for Entry(entry) in entries {
+            *self.queue.sqe(self.tail & self.ring_mask) = *entry;
+            self.tail = self.tail.wrapping_add(1);
+        }
+
+        Ok(())
+    }
+
+    /// Push entries from an iterator until the queue is full,
+    /// and return the number of entries pushed.
+    ///
+    /// No more entries than fit are taken from the iterator,
+    /// so the remaining ones can be pushed after the queue has been submitted.
+    ///
+    /// # Safety
+    ///
+    /// Developers must ensure that parameters of all the [Entry] (such as buffer) are valid,
+    /// otherwise it may cause memory problems.
+    pub unsafe fn push_iter(&mut self, entries: impl Iterator<Item = Entry>) -> usize {
+        let mut n = 0;
+
+        self.has_space(self.capacity());
+        for Entry(entry) in entries.take(self.space_left()) {
+            *self.queue.sqe(self.tail & self.ring_mask) = entry;
+            self.tail = self.tail.wrapping_add(1);
+            n += 1;
+        }
+
+        n
+    }
+}
+
+impl<'b> AvailableQueue<'b> {
+    /// Start a chain of entries, each linked to the next one with [Flags::IO_LINK].
+    ///
+    /// The entries are written into the free space of the queue as they are added,
+    /// but only become visible once the whole chain is pushed with [Chain::push].
+    pub fn chain(&mut self) -> Chain<'_, 'b> {
+        Chain {
+            queue: self,
+            len: 0,
+            link: Flags::IO_LINK,
+            overflow: false,
         }
     }
 }
 
+impl<'b> AvailableQueue<'b> {
+    /// Get the next free slot, to prepare an entry directly in the queue
+    /// instead of building it and copying it in.
+    ///
+    /// The slot is zeroed, and is only pushed once [Slot::commit] is called.
+    /// Returns `None` if the queue is full.
+    pub fn slot(&mut self) -> Option<Slot<'_, 'b>> {
+        if !self.has_space(1) {
+            return None;
+        }
+
+        unsafe {
+            let sqe = self.queue.sqe(self.tail & self.ring_mask);
+            sqe.write_bytes(0, 1 << self.queue.sqe_shift);
+        }
+
+        Some(Slot { queue: self })
+    }
+}
+
+impl Slot<'_, '_> {
+    /// The raw entry in the slot.
+    #[inline]
+    pub fn sqe(&mut self) -> &mut io_uring_sqe {
+        unsafe { &mut *self.queue.queue.sqe(self.queue.tail & self.queue.ring_mask) }
+    }
+
+    #[cfg(feature = "unstable")]
+    /// The entry in the slot as an [Entry128], whose command area can be written in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ring was not created with [Builder::setup_sqe128](crate::Builder::setup_sqe128).
+    pub fn entry128(&mut self) -> &mut Entry128 {
+        assert!(
+            self.queue.queue.is_sqe128(),
+            "the ring does not use 128-byte entries"
+        );
+
+        unsafe { &mut *(self.sqe() as *mut io_uring_sqe as *mut Entry128) }
+    }
+
+    /// Push the prepared entry into the queue.
+    ///
+    /// # Safety
+    ///
+    /// Developers must ensure that the entry is well-formed for its opcode
+    /// and that the resources it refers to are valid, otherwise it may cause memory problems.
+    #[inline]
+    pub unsafe fn commit(self) {
+        self.queue.tail = self.queue.tail.wrapping_add(1);
+    }
+}
+
+impl Chain<'_, '_> {
+    /// Link the entries with [Flags::IO_HARDLINK] instead,
+    /// so that the chain is not severed when an entry fails.
+    pub fn hardlink(mut self) -> Self {
+        self.link = Flags::IO_HARDLINK;
+        self
+    }
+
+    /// Append an [Entry] to the chain.
+    pub fn link(mut self, Entry(entry): Entry) -> Self {
+        if !self.queue.has_space(self.len as usize + 1) {
+            self.overflow = true;
+        } else if !self.overflow {
+            let tail = self.queue.tail.wrapping_add(self.len);
+            unsafe {
+                *self.queue.queue.sqe(tail & self.queue.ring_mask) = entry;
+            }
+            self.len += 1;
+        }
+
+        self
+    }
+
+    /// The number of entries in the chain.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Push the whole chain into the queue.
+    /// If the queue could not hold all entries, nothing is pushed.
+    ///
+    /// Link flags are set on every entry except the last one, which has them cleared,
+    /// so that the chain does not accidentally extend into the following entries.
+    ///
+    /// # Safety
+    ///
+    /// Developers must ensure that parameters of all the [Entry] (such as buffer) are valid,
+    /// otherwise it may cause memory problems.
+    pub unsafe fn push(self) -> Result<(), PushError> {
+        if self.overflow {
+            #[cfg(feature = "stats")]
+            self.queue.queue.stats.full();
+            return Err(PushError::Full(()));
+        }
+
+        let links = (Flags::IO_LINK | Flags::IO_HARDLINK).bits();
+
+        for i in 0..self.len {
+            let tail = self.queue.tail.wrapping_add(i);
+            let sqe = &mut *self.queue.queue.sqe(tail & self.queue.ring_mask);
+
+            sqe.flags &= !links;
+            if i + 1 != self.len {
+                sqe.flags |= self.link.bits();
+            }
+        }
+
+        self.queue.tail = self.queue.tail.wrapping_add(self.len);
+
+        Ok(())
+    }
+}
+
 impl Drop for AvailableQueue<'_> {
     fn drop(&mut self) {
+        #[cfg(any(feature = "tracing", feature = "stats"))]
+        self.record_push();
+
+        self.queue.cached_head = self.head;
         unsafe {
             (*self.queue.tail).store(self.tail, atomic::Ordering::Release);
         }
@@ -208,15 +747,332 @@ impl Drop for AvailableQueue<'_> {
 
 impl Entry {
     /// Set [Submission flags](Flags)
+    ///
+    /// In debug builds, this panics on combinations the kernel would reject,
+    /// i.e. [IO_DRAIN](Flags::IO_DRAIN) with `CQE_SKIP_SUCCESS`.
     pub fn flags(mut self, flags: Flags) -> Entry {
         self.0.flags |= flags.bits();
+
+        // IO_HARDLINK implies IO_LINK, so setting both is fine.
+        #[cfg(feature = "unstable")]
+        debug_assert!(
+            !Flags::from_bits_truncate(self.0.flags)
+                .contains(Flags::IO_DRAIN | Flags::CQE_SKIP_SUCCESS),
+            "IO_DRAIN cannot be combined with CQE_SKIP_SUCCESS"
+        );
+
         self
     }
 
     /// The `user_data` is an application-supplied value that will be copied into the completion queue
     /// entry (see below).
-    pub fn user_data(mut self, user_data: u64) -> Entry {
-        self.0.user_data = user_data;
+    ///
+    /// It accepts a `u64` or a [UserData].
+    pub fn user_data(mut self, user_data: impl Into<UserData>) -> Entry {
+        self.0.user_data = user_data.into().0;
         self
     }
+
+    /// Wrap a raw submission entry,
+    /// for operations that have no builder in [opcode](crate::opcode) yet.
+    #[inline]
+    pub const fn from_raw(entry: io_uring_sqe) -> Entry {
+        Entry(entry)
+    }
+
+    /// Get the raw submission entry back.
+    #[inline]
+    pub const fn into_raw(self) -> io_uring_sqe {
+        self.0
+    }
+
+    /// The operation code, see [OpCode::from_raw] to decode it.
+    #[inline]
+    pub const fn opcode(&self) -> u8 {
+        self.0.opcode
+    }
+
+    /// The file descriptor, or the index into the registered files
+    /// if [Flags::FIXED_FILE] is set.
+    #[inline]
+    pub const fn fd(&self) -> i32 {
+        self.0.fd
+    }
+
+    /// Get the `user_data` set with [Entry::user_data].
+    #[inline]
+    pub const fn get_user_data(&self) -> u64 {
+        self.0.user_data
+    }
+
+    /// Get the `user_data` set with [Entry::user_data], as a [UserData].
+    #[inline]
+    pub const fn get_user_data_typed(&self) -> UserData {
+        UserData(self.0.user_data)
+    }
+
+    /// Get the flags set with [Entry::flags].
+    #[inline]
+    pub const fn get_flags(&self) -> Flags {
+        Flags::from_bits_truncate(self.0.flags)
+    }
+
+    /// Run the request with registered credentials, see [Personalities](crate::personality::Personalities).
+    #[cfg(feature = "unstable")]
+    #[inline]
+    pub fn personality(mut self, personality: crate::personality::PersonalityId) -> Entry {
+        self.0.__bindgen_anon_4.__bindgen_anon_1.personality = personality.get();
+        self
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl Entry128 {
+    /// Set [Submission flags](Flags)
+    pub fn flags(self, flags: Flags) -> Entry128 {
+        Entry128(self.0.flags(flags), self.1)
+    }
+
+    /// See [Entry::user_data].
+    pub fn user_data(self, user_data: impl Into<UserData>) -> Entry128 {
+        Entry128(self.0.user_data(user_data), self.1)
+    }
+
+    /// See [Entry::personality].
+    #[cfg(feature = "unstable")]
+    pub fn personality(self, personality: crate::personality::PersonalityId) -> Entry128 {
+        Entry128(self.0.personality(personality), self.1)
+    }
+
+    /// The command area, which starts at byte 48 of the entry and runs to its end.
+    pub fn cmd(&self) -> &[u8; 80] {
+        unsafe {
+            &*(self as *const Entry128 as *const u8)
+                .add(48)
+                .cast::<[u8; 80]>()
+        }
+    }
+
+    /// See [Entry128::cmd].
+    pub fn cmd_mut(&mut self) -> &mut [u8; 80] {
+        unsafe {
+            &mut *(self as *mut Entry128 as *mut u8)
+                .add(48)
+                .cast::<[u8; 80]>()
+        }
+    }
+}
+
+impl From<io_uring_sqe> for Entry {
+    /// See [Entry::from_raw].
+    fn from(entry: io_uring_sqe) -> Entry {
+        Entry::from_raw(entry)
+    }
+}
+
+impl From<Entry> for io_uring_sqe {
+    fn from(entry: Entry) -> io_uring_sqe {
+        entry.into_raw()
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl From<Entry> for Entry128 {
+    /// Extend an [Entry] with a zeroed second half.
+    fn from(entry: Entry) -> Entry128 {
+        Entry128(entry, [0; 64])
+    }
+}
+
+impl UserData {
+    #[inline]
+    pub const fn from_u64(value: u64) -> UserData {
+        UserData(value)
+    }
+
+    /// Store a pointer, for example to per-request state owned by the application.
+    #[inline]
+    pub fn from_ptr<T>(ptr: *const T) -> UserData {
+        UserData(ptr as usize as u64)
+    }
+
+    #[inline]
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    /// Get back the pointer stored with [UserData::from_ptr].
+    ///
+    /// The pointer is only meaningful if this value was created from a pointer to `T`.
+    #[inline]
+    pub fn as_ptr<T>(self) -> *mut T {
+        self.0 as usize as *mut T
+    }
+}
+
+impl From<u64> for UserData {
+    #[inline]
+    fn from(value: u64) -> UserData {
+        UserData(value)
+    }
+}
+
+impl From<UserData> for u64 {
+    #[inline]
+    fn from(value: UserData) -> u64 {
+        value.0
+    }
+}
+
+impl fmt::Debug for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("Entry");
+        match OpCode::from_raw(self.0.opcode) {
+            Some(op) => d.field("opcode", &op),
+            None => d.field("opcode", &self.0.opcode),
+        };
+        d.field("fd", &self.0.fd)
+            .field("user_data", &self.0.user_data)
+            .field("flags", &self.get_flags())
+            .finish()
+    }
+}
+
+impl fmt::Debug for SubmissionQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        unsafe {
+            f.debug_struct("SubmissionQueue")
+                .field("head", &self.head())
+                .field("tail", &self.tail())
+                .field("entries", &self.capacity())
+                .field("flags", &(*self.flags).load(atomic::Ordering::Acquire))
+                .field("dropped", &self.dropped())
+                .finish()
+        }
+    }
+}
+
+impl fmt::Debug for AvailableQueue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AvailableQueue")
+            .field("head", &self.head)
+            .field("tail", &self.tail)
+            .field("entries", &self.ring_entries)
+            .finish()
+    }
+}
+
+impl<T> PushError<T> {
+    /// Get back the value that could not be pushed.
+    pub fn into_inner(self) -> T {
+        match self {
+            PushError::Full(value) => value,
+        }
+    }
+}
+
+impl<T> fmt::Debug for PushError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushError::Full(_) => f.write_str("Full(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for PushError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushError::Full(_) => f.write_str("submission queue is full"),
+        }
+    }
+}
+
+impl<T> error::Error for PushError<T> {}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use crate::mock::MockRing;
+    use crate::opcode;
+
+    fn nop(user_data: u64) -> super::Entry {
+        opcode::Nop::new().build().user_data(user_data)
+    }
+
+    #[test]
+    fn test_link_and_hardlink() {
+        use super::Flags;
+
+        let entry = nop(1).flags(Flags::IO_LINK).flags(Flags::IO_HARDLINK);
+        assert_eq!(entry.get_flags(), Flags::IO_LINK | Flags::IO_HARDLINK);
+    }
+
+    #[cfg(all(debug_assertions, feature = "unstable"))]
+    #[test]
+    #[should_panic(expected = "IO_DRAIN cannot be combined with CQE_SKIP_SUCCESS")]
+    fn test_drain_with_skip_success() {
+        use super::Flags;
+
+        let _ = nop(1).flags(Flags::IO_DRAIN | Flags::CQE_SKIP_SUCCESS);
+    }
+
+    #[test]
+    fn test_full_queue_after_submit() {
+        let mut ring = MockRing::new(4);
+
+        for round in 0..3 {
+            unsafe {
+                let mut sq = ring.submission().available();
+                for i in 0..4 {
+                    sq.push(nop(round * 4 + i)).unwrap();
+                }
+                assert!(sq.is_full());
+                assert_eq!(sq.space_left(), 0);
+                assert!(sq.push(nop(0)).is_err());
+            }
+
+            assert!(ring.submission().available().is_full());
+            assert_eq!(ring.submit().unwrap(), 4);
+
+            // The kernel consumed the entries, so the queue must not look full anymore.
+            let sq = ring.submission().available();
+            assert!(!sq.is_full());
+            assert_eq!(sq.len(), 0);
+            assert_eq!(sq.space_left(), 4);
+        }
+    }
+
+    #[test]
+    fn test_tail_advanced_through_alias() {
+        let mut ring = MockRing::new(4);
+        drop(ring.submission().available());
+
+        let mut alias = unsafe { ring.submission().alias() };
+        for _ in 0..2 {
+            unsafe {
+                let mut sq = alias.available();
+                for i in 0..4 {
+                    sq.push(nop(i)).unwrap();
+                }
+            }
+            ring.submit().unwrap();
+        }
+        unsafe {
+            alias.available().push(nop(8)).unwrap();
+        }
+
+        // The tail is 9 entries past the head the queue cached.
+        let mut sq = ring.submission().available();
+        assert_eq!(sq.len(), 1);
+        assert_eq!(sq.space_left(), 3);
+        unsafe {
+            for i in 0..3 {
+                sq.push(nop(9 + i)).unwrap();
+            }
+            assert!(sq.push(nop(12)).is_err());
+        }
+        drop(sq);
+
+        assert_eq!(ring.submit().unwrap(), 4);
+        assert_eq!(ring.submitted().len(), 12);
+    }
 }
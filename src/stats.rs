@@ -0,0 +1,153 @@
+//! Counters of the activity of a ring.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::sys;
+use crate::{CompletionQueue, SubmissionQueue};
+
+/// The errnos counted one by one, larger ones are counted together under 0.
+const ERRNOS: usize = 256;
+
+/// A snapshot of the counters of a ring, see [IoUring::stats](crate::IoUring::stats).
+///
+/// The counters only grow from the creation of the ring,
+/// so rates are derived from the difference between two snapshots.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    submitted: u64,
+    completed: u64,
+    cq_overflow: u32,
+    sq_dropped: u32,
+    sq_full: u64,
+    syscalls: u64,
+    failures: Vec<(i32, u64)>,
+}
+
+/// The counters updated through the submission queue and the submitter.
+#[derive(Default)]
+pub(crate) struct SubmissionCounters {
+    pushed: AtomicU64,
+    full: AtomicU64,
+    syscalls: AtomicU64,
+}
+
+/// The counters updated through the completion queue.
+pub(crate) struct CompletionCounters {
+    reaped: AtomicU64,
+    failures: Box<[AtomicU64]>,
+}
+
+impl Stats {
+    pub(crate) fn new(sq: &SubmissionQueue, cq: &CompletionQueue) -> Stats {
+        let failures = cq
+            .stats
+            .failures
+            .iter()
+            .enumerate()
+            .map(|(errno, count)| (errno as i32, count.load(Ordering::Relaxed)))
+            .filter(|&(_, count)| count != 0)
+            .collect();
+
+        Stats {
+            submitted: sq.stats.pushed.load(Ordering::Relaxed),
+            completed: cq.stats.reaped.load(Ordering::Relaxed),
+            cq_overflow: cq.overflow(),
+            sq_dropped: sq.dropped(),
+            sq_full: sq.stats.full.load(Ordering::Relaxed),
+            syscalls: sq.stats.syscalls.load(Ordering::Relaxed),
+            failures,
+        }
+    }
+
+    /// The number of entries pushed into the submission queue.
+    #[inline]
+    pub fn submitted(&self) -> u64 {
+        self.submitted
+    }
+
+    /// The number of completion events reaped from the completion queue.
+    #[inline]
+    pub fn completed(&self) -> u64 {
+        self.completed
+    }
+
+    /// The number of completion events the kernel dropped because the completion queue
+    /// was full, see [CompletionQueue::overflow].
+    #[inline]
+    pub fn cq_overflow(&self) -> u32 {
+        self.cq_overflow
+    }
+
+    /// The number of invalid entries the kernel dropped, see [SubmissionQueue::dropped].
+    #[inline]
+    pub fn sq_dropped(&self) -> u32 {
+        self.sq_dropped
+    }
+
+    /// The number of pushes that failed because the submission queue was full.
+    #[inline]
+    pub fn sq_full(&self) -> u64 {
+        self.sq_full
+    }
+
+    /// The number of `io_uring_enter(2)` and `io_uring_register(2)` calls issued by submitters.
+    #[inline]
+    pub fn syscalls(&self) -> u64 {
+        self.syscalls
+    }
+
+    /// The number of reaped completion events that failed, by errno, in ascending order.
+    ///
+    /// Errnos above 255, which Linux does not define, are counted under 0.
+    #[inline]
+    pub fn failures(&self) -> &[(i32, u64)] {
+        &self.failures
+    }
+
+    /// The number of reaped completion events that failed with `errno`, e.g. `libc::ECANCELED`.
+    pub fn failures_of(&self, errno: i32) -> u64 {
+        self.failures
+            .iter()
+            .find(|&&(e, _)| e == errno)
+            .map_or(0, |&(_, count)| count)
+    }
+}
+
+impl SubmissionCounters {
+    #[inline]
+    pub(crate) fn push(&self) {
+        self.pushed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn full(&self) {
+        self.full.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn syscall(&self) {
+        self.syscalls.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl CompletionCounters {
+    #[inline]
+    pub(crate) fn reap(&self, cqe: &sys::io_uring_cqe) {
+        self.reaped.fetch_add(1, Ordering::Relaxed);
+
+        if cqe.res < 0 {
+            let errno = cqe.res.unsigned_abs() as usize;
+            let index = if errno < ERRNOS { errno } else { 0 };
+            self.failures[index].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for CompletionCounters {
+    fn default() -> CompletionCounters {
+        CompletionCounters {
+            reaped: AtomicU64::new(0),
+            failures: (0..ERRNOS).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+}
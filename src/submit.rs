@@ -1,50 +1,149 @@
 use std::os::unix::io::{AsRawFd, RawFd};
-use std::sync::atomic;
-use std::{io, ptr};
+use std::{error, fmt, io, ptr};
 
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "unstable")]
+use std::cmp;
+#[cfg(feature = "unstable")]
+use std::mem;
+
+use crate::cqueue::CompletionQueue;
+#[cfg(feature = "unstable")]
+use crate::opcode::types;
 use crate::register::execute;
 use crate::register::Probe;
 use crate::squeue::SubmissionQueue;
+#[cfg(feature = "stats")]
+use crate::stats::SubmissionCounters;
+use crate::sync::atomic;
 use crate::sys;
-use crate::util::{cast_ptr, unsync_load, Fd};
+use crate::util::{cast_ptr, Fd};
+use crate::Parameters;
+
+/// Error returned by the submitting and waiting methods of [Submitter].
+#[derive(Debug)]
+pub enum SubmitError {
+    /// The completion queue is overflowing (`EBUSY`),
+    /// completion events must be reaped before more entries can be submitted.
+    CompletionQueueOverflow,
+
+    /// The kernel ran out of resources to handle the request (`EAGAIN`),
+    /// the call can be retried later.
+    ResourceExhausted,
+
+    /// Any other error, such as `ETIME` for an expired wait.
+    Other(io::Error),
+}
 
 /// Submitter
+///
+/// A submitter only borrows the ring, it can be copied cheaply and shared between threads,
+/// which may all submit and wait on the same ring at the same time.
+/// Note that a ring file descriptor registered with
+/// `IoUring::register_ring_fd` is only valid
+/// for the thread that registered it.
+#[derive(Clone, Copy)]
 pub struct Submitter<'a> {
     fd: &'a Fd,
-    flags: u32,
+    params: &'a Parameters,
+    ring_fd_index: Option<u32>,
+    retry_eintr: bool,
 
     sq_head: *const atomic::AtomicU32,
     sq_tail: *const atomic::AtomicU32,
     sq_flags: *const atomic::AtomicU32,
+    cq_head: *const atomic::AtomicU32,
+    cq_tail: *const atomic::AtomicU32,
+
+    #[cfg(feature = "stats")]
+    stats: *const SubmissionCounters,
 }
 
+// The queue pointers are only used for atomic loads.
+unsafe impl Send for Submitter<'_> {}
+unsafe impl Sync for Submitter<'_> {}
+
 impl<'a> Submitter<'a> {
     #[inline]
-    pub(crate) const fn new(fd: &'a Fd, flags: u32, sq: &SubmissionQueue) -> Submitter<'a> {
+    pub(crate) fn new(
+        fd: &'a Fd,
+        params: &'a Parameters,
+        ring_fd_index: Option<u32>,
+        retry_eintr: bool,
+        sq: &SubmissionQueue,
+        cq: &CompletionQueue,
+    ) -> Submitter<'a> {
         Submitter {
             fd,
-            flags,
+            params,
+            ring_fd_index,
+            retry_eintr,
             sq_head: sq.head,
             sq_tail: sq.tail,
             sq_flags: sq.flags,
+            cq_head: cq.head,
+            cq_tail: cq.tail,
+            #[cfg(feature = "stats")]
+            stats: &*sq.stats,
         }
     }
 
     fn sq_len(&self) -> usize {
         unsafe {
             let head = (*self.sq_head).load(atomic::Ordering::Acquire);
-            let tail = unsync_load(self.sq_tail);
+            // Another thread may be pushing, so the tail can change under us.
+            let tail = (*self.sq_tail).load(atomic::Ordering::Acquire);
+
+            tail.wrapping_sub(head) as usize
+        }
+    }
+
+    fn cq_len(&self) -> usize {
+        unsafe {
+            let head = (*self.cq_head).load(atomic::Ordering::Acquire);
+            let tail = (*self.cq_tail).load(atomic::Ordering::Acquire);
 
             tail.wrapping_sub(head) as usize
         }
     }
 
     fn sq_need_wakeup(&self) -> bool {
+        // The tail store must be visible before the flags are read,
+        // otherwise the poll thread may go to sleep without seeing the new entries.
+        atomic::fence(atomic::Ordering::SeqCst);
+
         unsafe {
             (*self.sq_flags).load(atomic::Ordering::Acquire) & sys::IORING_SQ_NEED_WAKEUP != 0
         }
     }
 
+    fn sq_taskrun(&self) -> bool {
+        unsafe { (*self.sq_flags).load(atomic::Ordering::Acquire) & sys::IORING_SQ_TASKRUN != 0 }
+    }
+
+    fn register(
+        &self,
+        opcode: libc::c_uint,
+        arg: *const libc::c_void,
+        len: libc::c_uint,
+    ) -> io::Result<i32> {
+        #[cfg(feature = "stats")]
+        unsafe {
+            (*self.stats).syscall()
+        };
+
+        match self.ring_fd_index {
+            Some(index) if self.params.0.features & sys::IORING_FEAT_REG_REG_RING != 0 => execute(
+                index as _,
+                opcode | sys::IORING_REGISTER_USE_REGISTERED_RING,
+                arg,
+                len,
+            ),
+            _ => execute(self.fd.as_raw_fd(), opcode, arg, len),
+        }
+    }
+
     /// Initiate and/or complete asynchronous I/O
     ///
     /// # Safety
@@ -58,31 +157,125 @@ impl<'a> Submitter<'a> {
         sig: Option<&libc::sigset_t>,
     ) -> io::Result<usize> {
         let sig = sig.map(|sig| sig as *const _).unwrap_or_else(ptr::null);
-        let result = sys::io_uring_enter(self.fd.as_raw_fd(), to_submit, min_complete, flag, sig);
-        if result >= 0 {
-            Ok(result as _)
-        } else {
-            Err(io::Error::last_os_error())
+        self.enter_with_arg(
+            to_submit,
+            min_complete,
+            flag,
+            sig as *const _,
+            sys::SIGSET_SIZE,
+        )
+    }
+
+    unsafe fn enter_with_arg(
+        &self,
+        to_submit: u32,
+        min_complete: u32,
+        mut flag: u32,
+        arg: *const libc::c_void,
+        size: usize,
+    ) -> io::Result<usize> {
+        let fd = match self.ring_fd_index {
+            Some(index) => {
+                flag |= sys::IORING_ENTER_REGISTERED_RING;
+                index as _
+            }
+            None => self.fd.as_raw_fd(),
+        };
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("enter", to_submit, min_complete, flags = flag).entered();
+        #[cfg(feature = "stats")]
+        (*self.stats).syscall();
+
+        let result = sys::io_uring_enter(fd, to_submit, min_complete, flag, arg, size)
+            .map(|ret| ret as usize);
+
+        #[cfg(feature = "tracing")]
+        crate::trace::enter(&result);
+
+        result
+    }
+
+    /// Retry `f` if it was interrupted by a signal, unless disabled by
+    /// [Builder::dont_retry_eintr](crate::Builder::dont_retry_eintr).
+    ///
+    /// Nothing is submitted when `io_uring_enter(2)` fails with `EINTR`,
+    /// so the call can simply be repeated.
+    fn retry_eintr<F>(&self, mut f: F) -> Result<usize, SubmitError>
+    where
+        F: FnMut() -> io::Result<usize>,
+    {
+        loop {
+            match f() {
+                Err(ref err) if self.retry_eintr && err.kind() == io::ErrorKind::Interrupted => {
+                    continue
+                }
+                ret => return ret.map_err(SubmitError::from),
+            }
         }
     }
 
     /// Initiate asynchronous I/O.
+    ///
+    /// If the ring was set up with [Builder::setup_sqpoll](crate::Builder::setup_sqpoll),
+    /// this only enters the kernel when the poll thread is asleep and needs a wakeup.
     #[inline]
-    pub fn submit(&self) -> io::Result<usize> {
+    pub fn submit(&self) -> Result<usize, SubmitError> {
         self.submit_and_wait(0)
     }
 
     /// Initiate and/or complete asynchronous I/O
-    pub fn submit_and_wait(&self, want: usize) -> io::Result<usize> {
+    ///
+    /// If `want` completion events are already queued, this does not wait,
+    /// and only enters the kernel if there are entries to submit.
+    #[inline]
+    pub fn submit_and_wait(&self, want: usize) -> Result<usize, SubmitError> {
+        self.retry_eintr(|| self.submit_and_wait_inner(want, None))
+    }
+
+    /// Initiate and/or complete asynchronous I/O,
+    /// replacing the signal mask of the current thread by `sigmask` while waiting.
+    ///
+    /// Like `pselect(2)`, this allows a signal to be atomically unblocked for the duration of the wait,
+    /// a signal delivered during the wait will interrupt it with `EINTR`.
+    /// Unlike other methods, this is never retried.
+    pub fn submit_and_wait_with_sigmask(
+        &self,
+        want: usize,
+        sigmask: &libc::sigset_t,
+    ) -> Result<usize, SubmitError> {
+        self.submit_and_wait_inner(want, Some(sigmask))
+            .map_err(SubmitError::from)
+    }
+
+    fn submit_and_wait_inner(
+        &self,
+        mut want: usize,
+        sig: Option<&libc::sigset_t>,
+    ) -> io::Result<usize> {
         let len = self.sq_len();
 
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("submit", to_submit = len, want).entered();
+
+        // If enough completion events are already queued, there is nothing to wait for,
+        // and with nothing to submit either, no need to enter the kernel at all.
+        if want > 0 && self.cq_len() >= want {
+            want = 0;
+            if len == 0 && !self.sq_taskrun() {
+                return Ok(0);
+            }
+        }
+
         let mut flags = 0;
 
-        if want > 0 {
+        // With `IORING_SETUP_TASKRUN_FLAG`, the kernel asks for an enter with
+        // `IORING_ENTER_GETEVENTS` to run pending task work, even if nothing is awaited.
+        if want > 0 || self.sq_taskrun() {
             flags |= sys::IORING_ENTER_GETEVENTS;
         }
 
-        if self.flags & sys::IORING_SETUP_SQPOLL != 0 {
+        if self.params.is_setup_sqpoll() {
             if self.sq_need_wakeup() {
                 flags |= sys::IORING_ENTER_SQ_WAKEUP;
             } else if want == 0 {
@@ -91,13 +284,206 @@ impl<'a> Submitter<'a> {
             }
         }
 
-        unsafe { self.enter(len as _, want as _, flags, None) }
+        unsafe { self.enter(len as _, want as _, flags, sig) }
+    }
+
+    /// Initiate asynchronous I/O, then busy-poll the completion queue for up to `spin`
+    /// until `want` completion events are available, before blocking in the kernel.
+    ///
+    /// For requests that usually complete within a few microseconds,
+    /// this avoids the latency of going to sleep and being woken up,
+    /// while bounding the CPU time burnt when they do not.
+    ///
+    /// Rings set up with `IORING_SETUP_IOPOLL`, `IORING_SETUP_COOP_TASKRUN` or
+    /// `IORING_SETUP_DEFER_TASKRUN` post completion events once the task enters the kernel,
+    /// so this does not spin for them, and is the same as [Submitter::submit_and_wait].
+    pub fn submit_and_spin_wait(&self, want: usize, spin: Duration) -> Result<usize, SubmitError> {
+        const POST_ON_ENTER: u32 = sys::IORING_SETUP_IOPOLL
+            | sys::IORING_SETUP_COOP_TASKRUN
+            | sys::IORING_SETUP_DEFER_TASKRUN;
+
+        if self.params.0.flags & POST_ON_ENTER != 0 {
+            return self.submit_and_wait(want);
+        }
+
+        let submitted = self.submit()?;
+        let start = Instant::now();
+
+        while self.cq_len() < want {
+            for _ in 0..64 {
+                std::hint::spin_loop();
+            }
+
+            if start.elapsed() >= spin {
+                self.wait(want)?;
+                break;
+            }
+        }
+
+        Ok(submitted)
+    }
+
+    /// Wait for at least `want` completion events, without submitting any entry.
+    ///
+    /// This is useful for threads that only reap completions,
+    /// while the submission queue is driven from elsewhere.
+    pub fn wait(&self, want: usize) -> Result<usize, SubmitError> {
+        self.retry_eintr(|| unsafe { self.enter(0, want as _, sys::IORING_ENTER_GETEVENTS, None) })
+    }
+
+    /// Move completion events that overflowed in the kernel back into the completion queue.
+    ///
+    /// With `IORING_FEAT_NODROP`, events that did not fit into a full completion queue
+    /// are kept by the kernel and flagged by `SubmissionQueue::cq_overflow`.
+    /// They are only copied back on the next wait, so after reaping the completion queue
+    /// this can be called to flush them without waiting for new events.
+    pub fn flush_overflow(&self) -> Result<usize, SubmitError> {
+        self.retry_eintr(|| unsafe { self.enter(0, 0, sys::IORING_ENTER_GETEVENTS, None) })
+    }
+
+    /// Initiate and/or complete asynchronous I/O,
+    /// but wait at most `timeout` for `want` completions.
+    ///
+    /// If the timeout expires before enough events are completed,
+    /// and no entry was submitted by this call, `ETIME` is returned.
+    ///
+    /// This requires `IORING_FEAT_EXT_ARG` (Linux 5.11), older kernels will return `EINVAL`.
+    /// In that case, push a [Timeout](crate::opcode::Timeout) entry with `count` set to `want`
+    /// and use [Submitter::submit_and_wait] instead,
+    /// remembering to skip the completion of the timeout entry itself.
+    #[cfg(feature = "unstable")]
+    pub fn submit_with_timeout(
+        &self,
+        want: usize,
+        timeout: Duration,
+    ) -> Result<usize, SubmitError> {
+        self.submit_with_args(want, &types::ExtArg::new().timeout(timeout))
+    }
+
+    /// Wait at most `timeout` for `want` completion events, without submitting any entry.
+    ///
+    /// If the timeout expires before enough events are completed, `ETIME` is returned.
+    ///
+    /// This requires `IORING_FEAT_EXT_ARG` (Linux 5.11), older kernels will return `EINVAL`.
+    #[cfg(feature = "unstable")]
+    pub fn wait_timeout(&self, want: usize, timeout: Duration) -> Result<usize, SubmitError> {
+        self.wait_with_args(want, &types::ExtArg::new().timeout(timeout))
+    }
+
+    /// Wait until the poll thread has consumed entries and the submission queue has free space.
+    ///
+    /// This is only meaningful when [Builder::setup_sqpoll](crate::Builder::setup_sqpoll) is enabled,
+    /// and saves spinning on a full queue while the kernel catches up.
+    #[cfg(feature = "unstable")]
+    pub fn squeue_wait(&self) -> Result<usize, SubmitError> {
+        self.retry_eintr(|| unsafe { self.enter(0, 0, sys::IORING_ENTER_SQ_WAIT, None) })
+    }
+
+    /// Wait for `want` completion events for at most `max`, without submitting any entry.
+    ///
+    /// Once `min` has elapsed, the wait returns as soon as at least one event is available,
+    /// rather than waiting for all `want` events.
+    /// This lets the kernel batch bursty completions into fewer wakeups.
+    ///
+    /// If no event is available when `max` expires, `ETIME` is returned.
+    ///
+    /// This requires `IORING_FEAT_MIN_TIMEOUT` (Linux 6.12), older kernels will return `EINVAL`.
+    #[cfg(feature = "unstable")]
+    pub fn wait_min_timeout(
+        &self,
+        want: usize,
+        min: Duration,
+        max: Duration,
+    ) -> Result<usize, SubmitError> {
+        self.wait_with_args(want, &types::ExtArg::new().min_wait(min).timeout(max))
+    }
+
+    /// Initiate and/or complete asynchronous I/O, waiting for `want` completions
+    /// as configured by `args`, which combines a signal mask, a timeout and a minimum wait.
+    ///
+    /// The helpers above are shorthands for the common cases.
+    /// The wait is retried on `EINTR` like [Submitter::submit_and_wait],
+    /// with the timeout shortened by the time already waited,
+    /// unless a signal mask is set.
+    #[cfg(feature = "unstable")]
+    pub fn submit_with_args(
+        &self,
+        want: usize,
+        args: &types::ExtArg<'_>,
+    ) -> Result<usize, SubmitError> {
+        self.enter_with_ext_arg(true, want, args)
+    }
+
+    /// Like [Submitter::submit_with_args], without submitting any entry.
+    #[cfg(feature = "unstable")]
+    pub fn wait_with_args(
+        &self,
+        want: usize,
+        args: &types::ExtArg<'_>,
+    ) -> Result<usize, SubmitError> {
+        self.enter_with_ext_arg(false, want, args)
+    }
+
+    #[cfg(feature = "unstable")]
+    fn enter_with_ext_arg(
+        &self,
+        submit: bool,
+        want: usize,
+        args: &types::ExtArg<'_>,
+    ) -> Result<usize, SubmitError> {
+        let deadline = args.timeout.map(|timeout| Instant::now() + timeout);
+        let min_wait_usec = cmp::min(args.min_wait.as_micros(), u32::MAX as u128) as u32;
+
+        let enter = || {
+            let mut to_submit = 0;
+            let mut flags = sys::IORING_ENTER_GETEVENTS | sys::IORING_ENTER_EXT_ARG;
+
+            if submit {
+                to_submit = self.sq_len();
+                if self.params.is_setup_sqpoll() && self.sq_need_wakeup() {
+                    flags |= sys::IORING_ENTER_SQ_WAKEUP;
+                }
+            }
+
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("submit", to_submit, want).entered();
+
+            // The timeout is relative, so it is shortened by the time already waited.
+            let ts = deadline.map(|deadline| {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                sys::__kernel_timespec {
+                    tv_sec: timeout.as_secs() as _,
+                    tv_nsec: timeout.subsec_nanos() as _,
+                }
+            });
+            let arg = sys::io_uring_getevents_arg {
+                sigmask: args.sigmask.map_or(0, |sig| sig as *const _ as _),
+                sigmask_sz: args.sigmask.map_or(0, |_| sys::SIGSET_SIZE as _),
+                min_wait_usec,
+                ts: ts.as_ref().map_or(0, |ts| ts as *const _ as _),
+            };
+
+            unsafe {
+                self.enter_with_arg(
+                    to_submit as _,
+                    want as _,
+                    flags,
+                    cast_ptr::<sys::io_uring_getevents_arg>(&arg) as *const _,
+                    mem::size_of::<sys::io_uring_getevents_arg>(),
+                )
+            }
+        };
+
+        if args.sigmask.is_some() {
+            enter().map_err(SubmitError::from)
+        } else {
+            self.retry_eintr(enter)
+        }
     }
 
     /// Register buffers.
     pub fn register_buffers(&self, bufs: &[libc::iovec]) -> io::Result<()> {
-        execute(
-            self.fd.as_raw_fd(),
+        self.register(
             sys::IORING_REGISTER_BUFFERS,
             bufs.as_ptr() as *const _,
             bufs.len() as _,
@@ -107,8 +493,7 @@ impl<'a> Submitter<'a> {
 
     /// Register files for I/O.
     pub fn register_files(&self, fds: &[RawFd]) -> io::Result<()> {
-        execute(
-            self.fd.as_raw_fd(),
+        self.register(
             sys::IORING_REGISTER_FILES,
             fds.as_ptr() as *const _,
             fds.len() as _,
@@ -118,8 +503,7 @@ impl<'a> Submitter<'a> {
 
     /// It’s possible to use `eventfd(2)` to get notified of completion events on an io_uring instance.
     pub fn register_eventfd(&self, eventfd: RawFd) -> io::Result<()> {
-        execute(
-            self.fd.as_raw_fd(),
+        self.register(
             sys::IORING_REGISTER_EVENTFD,
             cast_ptr::<RawFd>(&eventfd) as *const _,
             1,
@@ -137,8 +521,7 @@ impl<'a> Submitter<'a> {
             fds: fds.as_ptr() as _,
         };
         let fu = cast_ptr::<sys::io_uring_files_update>(&fu);
-        let ret = execute(
-            self.fd.as_raw_fd(),
+        let ret = self.register(
             sys::IORING_REGISTER_FILES_UPDATE,
             fu as *const _,
             fds.len() as _,
@@ -146,11 +529,106 @@ impl<'a> Submitter<'a> {
         Ok(ret as _)
     }
 
+    /// Register a table of `nr` files that are all sparse (`-1`),
+    /// to be filled in later with [Submitter::register_files_update_tag] or
+    /// [FilesUpdate](crate::opcode::FilesUpdate). Requires Linux 5.19.
+    #[cfg(feature = "unstable")]
+    pub fn register_files_sparse(&self, nr: u32) -> io::Result<()> {
+        let rr = sys::io_uring_rsrc_register {
+            nr,
+            flags: sys::IORING_RSRC_REGISTER_SPARSE,
+            resv2: 0,
+            data: 0,
+            tags: 0,
+        };
+        let rr = cast_ptr::<sys::io_uring_rsrc_register>(&rr);
+        self.register(
+            sys::IORING_REGISTER_FILES2,
+            rr as *const _,
+            mem::size_of::<sys::io_uring_rsrc_register>() as _,
+        )?;
+        Ok(())
+    }
+
+    /// Like [Submitter::register_files_update], but also set a tag for each new file.
+    ///
+    /// Once a file with a non-zero tag is removed from the table and no request uses it anymore,
+    /// the kernel posts a completion event with the tag as its `user_data`.
+    ///
+    /// `fds` and `tags` must have the same length.
+    #[cfg(feature = "unstable")]
+    pub fn register_files_update_tag(
+        &self,
+        offset: u32,
+        fds: &[RawFd],
+        tags: &[u64],
+    ) -> io::Result<usize> {
+        assert_eq!(fds.len(), tags.len(), "every file needs a tag");
+
+        let rr = sys::io_uring_rsrc_update2 {
+            offset,
+            resv: 0,
+            data: fds.as_ptr() as _,
+            tags: tags.as_ptr() as _,
+            nr: fds.len() as _,
+            resv2: 0,
+        };
+        let rr = cast_ptr::<sys::io_uring_rsrc_update2>(&rr);
+        let ret = self.register(
+            sys::IORING_REGISTER_FILES_UPDATE2,
+            rr as *const _,
+            mem::size_of::<sys::io_uring_rsrc_update2>() as _,
+        )?;
+        Ok(ret as _)
+    }
+
+    /// Register a ring of `ring_entries` provided buffers for the buffer group `bgid`,
+    /// the ring must be page aligned. Requires Linux 5.19.
+    ///
+    /// # Safety
+    ///
+    /// The ring, and the buffers it refers to, must stay valid until it is unregistered.
+    #[cfg(feature = "unstable")]
+    pub unsafe fn register_buf_ring(
+        &self,
+        ring_addr: u64,
+        ring_entries: u16,
+        bgid: u16,
+    ) -> io::Result<()> {
+        let br = sys::io_uring_buf_reg {
+            ring_addr,
+            ring_entries: ring_entries as _,
+            bgid,
+            pad: 0,
+            resv: [0; 3],
+        };
+        self.register(
+            sys::IORING_REGISTER_PBUF_RING,
+            cast_ptr::<sys::io_uring_buf_reg>(&br) as *const _,
+            1,
+        )
+        .map(drop)
+    }
+
+    /// Unregister the buffer ring of the buffer group `bgid`.
+    #[cfg(feature = "unstable")]
+    pub fn unregister_buf_ring(&self, bgid: u16) -> io::Result<()> {
+        let br = sys::io_uring_buf_reg {
+            bgid,
+            ..Default::default()
+        };
+        self.register(
+            sys::IORING_UNREGISTER_PBUF_RING,
+            cast_ptr::<sys::io_uring_buf_reg>(&br) as *const _,
+            1,
+        )
+        .map(drop)
+    }
+
     /// This works just like [Submitter::register_eventfd],
     /// except notifications are only posted for events that complete in an async manner.
     pub fn register_eventfd_async(&self, eventfd: RawFd) -> io::Result<()> {
-        execute(
-            self.fd.as_raw_fd(),
+        self.register(
             sys::IORING_REGISTER_EVENTFD_ASYNC,
             cast_ptr::<RawFd>(&eventfd) as *const _,
             1,
@@ -158,11 +636,43 @@ impl<'a> Submitter<'a> {
         .map(drop)
     }
 
+    /// Bind the io-wq workers of the ring, which run the requests that cannot complete
+    /// without blocking, to the CPUs of `cpus`. Requires Linux 5.14.
+    #[cfg(feature = "unstable")]
+    pub fn register_iowq_aff(&self, cpus: &libc::cpu_set_t) -> io::Result<()> {
+        self.register(
+            sys::IORING_REGISTER_IOWQ_AFF,
+            cast_ptr::<libc::cpu_set_t>(cpus) as *const _,
+            mem::size_of::<libc::cpu_set_t>() as _,
+        )
+        .map(drop)
+    }
+
+    /// Let the io-wq workers of the ring run on any CPU again.
+    #[cfg(feature = "unstable")]
+    pub fn unregister_iowq_aff(&self) -> io::Result<()> {
+        self.register(sys::IORING_UNREGISTER_IOWQ_AFF, ptr::null(), 0)
+            .map(drop)
+    }
+
+    /// Limit the number of io-wq workers of the ring, for bounded work such as regular files
+    /// and for unbounded work such as sockets. Requires Linux 5.15.
+    ///
+    /// A limit of 0 leaves it unchanged, and the previous limits are written back to `max`.
+    #[cfg(feature = "unstable")]
+    pub fn register_iowq_max_workers(&self, max: &mut [u32; 2]) -> io::Result<()> {
+        self.register(
+            sys::IORING_REGISTER_IOWQ_MAX_WORKERS,
+            max.as_mut_ptr() as *const _,
+            2,
+        )
+        .map(drop)
+    }
+
     /// This operation returns a structure `Probe`,
     /// which contains information about the opcodes supported by io_uring on the running kernel.
     pub fn register_probe(&self, probe: &mut Probe) -> io::Result<()> {
-        execute(
-            self.fd.as_raw_fd(),
+        self.register(
             sys::IORING_REGISTER_PROBE,
             probe.as_mut_ptr() as *const _,
             Probe::COUNT as _,
@@ -173,55 +683,232 @@ impl<'a> Submitter<'a> {
     /// This operation registers credentials of the running application with io_uring,
     /// and returns an id associated with these credentials.
     pub fn register_personality(&self) -> io::Result<i32> {
-        execute(
-            self.fd.as_raw_fd(),
-            sys::IORING_REGISTER_PERSONALITY,
-            ptr::null(),
-            0,
-        )
+        self.register(sys::IORING_REGISTER_PERSONALITY, ptr::null(), 0)
     }
 
     /// Unregister buffers.
     pub fn unregister_buffers(&self) -> io::Result<()> {
-        execute(
-            self.fd.as_raw_fd(),
-            sys::IORING_UNREGISTER_BUFFERS,
-            ptr::null(),
-            0,
-        )
-        .map(drop)
+        self.register(sys::IORING_UNREGISTER_BUFFERS, ptr::null(), 0)
+            .map(drop)
     }
 
     /// Unregister files.
     pub fn unregister_files(&self) -> io::Result<()> {
-        execute(
-            self.fd.as_raw_fd(),
-            sys::IORING_UNREGISTER_FILES,
-            ptr::null(),
-            0,
-        )
-        .map(drop)
+        self.register(sys::IORING_UNREGISTER_FILES, ptr::null(), 0)
+            .map(drop)
     }
 
     /// Unregister an eventfd file descriptor to stop notifications.
     pub fn unregister_eventfd(&self) -> io::Result<()> {
-        execute(
-            self.fd.as_raw_fd(),
-            sys::IORING_UNREGISTER_EVENTFD,
-            ptr::null(),
-            0,
-        )
-        .map(drop)
+        self.register(sys::IORING_UNREGISTER_EVENTFD, ptr::null(), 0)
+            .map(drop)
     }
 
     /// This operation unregisters a previously registered personality with io_uring.
     pub fn unregister_personality(&self, id: i32) -> io::Result<()> {
-        execute(
-            self.fd.as_raw_fd(),
-            sys::IORING_UNREGISTER_PERSONALITY,
-            ptr::null(),
-            id as _,
-        )
-        .map(drop)
+        self.register(sys::IORING_UNREGISTER_PERSONALITY, ptr::null(), id as _)
+            .map(drop)
+    }
+}
+
+/// A [Submitter] that decides by itself when to enter the kernel.
+///
+/// Entries are submitted once `max_pending` of them are waiting in the submission queue,
+/// or once `max_delay` (if set) has elapsed since the last submission,
+/// which amortizes the syscall cost for producers pushing entries one at a time.
+pub struct BatchedSubmitter<'a> {
+    submitter: Submitter<'a>,
+    max_pending: usize,
+    max_delay: Option<Duration>,
+    last_submit: Instant,
+}
+
+impl<'a> BatchedSubmitter<'a> {
+    pub fn new(submitter: Submitter<'a>, max_pending: usize) -> BatchedSubmitter<'a> {
+        BatchedSubmitter {
+            submitter,
+            max_pending,
+            max_delay: None,
+            last_submit: Instant::now(),
+        }
+    }
+
+    /// Also submit when the oldest pending entry may have waited for `max_delay`.
+    pub fn max_delay(mut self, max_delay: Duration) -> BatchedSubmitter<'a> {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    /// Get the underlying submitter.
+    pub fn submitter(&self) -> &Submitter<'a> {
+        &self.submitter
+    }
+
+    /// Submit the pending entries if the policy says so,
+    /// otherwise return `Ok(0)` without entering the kernel.
+    ///
+    /// This should be called after entries were pushed and the submission queue was synced.
+    pub fn maybe_submit(&mut self) -> Result<usize, SubmitError> {
+        let len = self.submitter.sq_len();
+
+        if len == 0 {
+            self.last_submit = Instant::now();
+            return Ok(0);
+        }
+
+        let expired = self
+            .max_delay
+            .map(|delay| self.last_submit.elapsed() >= delay)
+            .unwrap_or(false);
+
+        if len >= self.max_pending || expired {
+            self.flush()
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Submit all pending entries now.
+    pub fn flush(&mut self) -> Result<usize, SubmitError> {
+        let ret = self.submitter.submit();
+        self.last_submit = Instant::now();
+        ret
+    }
+}
+
+impl From<io::Error> for SubmitError {
+    fn from(err: io::Error) -> SubmitError {
+        match err.raw_os_error() {
+            Some(libc::EBUSY) => SubmitError::CompletionQueueOverflow,
+            Some(libc::EAGAIN) => SubmitError::ResourceExhausted,
+            _ => SubmitError::Other(err),
+        }
+    }
+}
+
+impl From<SubmitError> for io::Error {
+    fn from(err: SubmitError) -> io::Error {
+        match err {
+            SubmitError::CompletionQueueOverflow => io::Error::from_raw_os_error(libc::EBUSY),
+            SubmitError::ResourceExhausted => io::Error::from_raw_os_error(libc::EAGAIN),
+            SubmitError::Other(err) => err,
+        }
+    }
+}
+
+impl fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmitError::CompletionQueueOverflow => f.write_str("completion queue overflow"),
+            SubmitError::ResourceExhausted => f.write_str("submission resources exhausted"),
+            SubmitError::Other(err) => err.fmt(f),
+        }
+    }
+}
+
+impl error::Error for SubmitError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SubmitError::Other(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opcode::{self, types};
+    use crate::IoUring;
+
+    fn push_nops(sq: &mut SubmissionQueue, n: usize) {
+        let mut sq = sq.available();
+        for _ in 0..n {
+            unsafe { sq.push(opcode::Nop::new().build()).unwrap() };
+        }
+    }
+
+    #[test]
+    fn test_batched_max_pending() {
+        let mut ring = IoUring::new(8).unwrap();
+        let (submitter, sq, _) = ring.split();
+        let mut batched = BatchedSubmitter::new(submitter, 3);
+
+        for pending in 1..3 {
+            push_nops(sq, 1);
+            assert_eq!(batched.maybe_submit().unwrap(), 0);
+            assert_eq!(batched.submitter().sq_len(), pending);
+        }
+
+        push_nops(sq, 1);
+        assert_eq!(batched.maybe_submit().unwrap(), 3);
+        assert_eq!(batched.submitter().sq_len(), 0);
+
+        push_nops(sq, 4);
+        assert_eq!(batched.maybe_submit().unwrap(), 4);
+        assert_eq!(batched.maybe_submit().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_batched_max_delay() {
+        let delay = Duration::from_secs(1);
+        let mut ring = IoUring::new(8).unwrap();
+        let (submitter, sq, _) = ring.split();
+        let mut batched = BatchedSubmitter::new(submitter, 8).max_delay(delay);
+        let expire = |batched: &mut BatchedSubmitter<'_>| {
+            batched.last_submit = Instant::now().checked_sub(delay).unwrap();
+        };
+
+        push_nops(sq, 1);
+        assert_eq!(batched.maybe_submit().unwrap(), 0);
+        expire(&mut batched);
+        assert_eq!(batched.maybe_submit().unwrap(), 1);
+
+        // An empty queue restarts the delay, which runs from the next pushed entry.
+        expire(&mut batched);
+        assert_eq!(batched.maybe_submit().unwrap(), 0);
+        push_nops(sq, 1);
+        assert_eq!(batched.maybe_submit().unwrap(), 0);
+        assert_eq!(batched.flush().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_spin_wait_ready() {
+        let mut ring = IoUring::new(8).unwrap();
+        push_nops(ring.submission(), 1);
+        ring.submit_and_wait(1).unwrap();
+
+        // The event is already there, so this neither spins nor waits.
+        let start = Instant::now();
+        let submitted = ring
+            .submitter()
+            .submit_and_spin_wait(1, Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(submitted, 0);
+        assert!(start.elapsed() < Duration::from_secs(60));
+        assert_eq!(ring.completion().len(), 1);
+    }
+
+    #[test]
+    fn test_spin_wait_blocks() {
+        let mut ring = IoUring::new(8).unwrap();
+        let ts = types::Timespec {
+            tv_sec: 0,
+            tv_nsec: 20_000_000,
+        };
+        let entry = opcode::Timeout::new(&ts).build();
+        unsafe { ring.submission().available().push(entry).unwrap() };
+
+        // The timeout outlasts the spin, so the event is waited for in the kernel.
+        let start = Instant::now();
+        let submitted = ring
+            .submitter()
+            .submit_and_spin_wait(1, Duration::ZERO)
+            .unwrap();
+        assert_eq!(submitted, 1);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        let cqe = ring.completion().available().next().unwrap();
+        assert_eq!(cqe.result(), -libc::ETIME);
     }
 }
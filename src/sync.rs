@@ -0,0 +1,15 @@
+//! The atomics used by the queues.
+//!
+//! When building with `--cfg loom`, they are replaced by the `loom` types,
+//! so that the head and tail protocol can be model checked.
+//! Such a build only works with the in-memory rings of the loom tests.
+
+#[cfg(not(loom))]
+pub(crate) mod atomic {
+    pub(crate) use std::sync::atomic::{fence, AtomicU32, Ordering};
+}
+
+#[cfg(loom)]
+pub(crate) mod atomic {
+    pub(crate) use loom::sync::atomic::{fence, AtomicU32, Ordering};
+}
@@ -1,3 +1,10 @@
+//! The raw kernel interface, as generated from the `io_uring` kernel headers.
+//!
+//! It is meant for reaching kernel features the crate has no API for yet,
+//! the entry and parameter types convert from and into their raw layouts with `From`.
+//! Unlike the rest of the crate, it follows the kernel headers and may change
+//! with any release.
+
 #![allow(
     non_camel_case_types,
     non_upper_case_globals,
@@ -6,6 +13,7 @@
 )]
 #![allow(clippy::unreadable_literal, clippy::missing_safety_doc)]
 
+#[cfg(not(feature = "rustix"))]
 use libc::*;
 
 #[cfg(all(feature = "bindgen", not(feature = "overwrite")))]
@@ -17,43 +25,92 @@ include!(concat!(env!("OUT_DIR"), "/sys.rs"));
 ))]
 include!("sys.rs");
 
-pub unsafe fn io_uring_register(
+/// The size of the kernel `sigset_t`, which is smaller than the one defined by libc.
+#[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+pub(crate) const SIGSET_SIZE: usize = 128 / 8;
+
+/// The size of the kernel `sigset_t`, which is smaller than the one defined by libc.
+#[cfg(not(any(target_arch = "mips", target_arch = "mips64")))]
+pub(crate) const SIGSET_SIZE: usize = 64 / 8;
+
+impl io_sqring_offsets {
+    /// The field was named `resv2` before the kernel headers named it `user_addr`.
+    #[deprecated(note = "the field is now `user_addr`")]
+    #[inline]
+    pub fn resv2(&self) -> __u64 {
+        self.user_addr
+    }
+}
+
+impl io_cqring_offsets {
+    /// The field was named `resv2` before the kernel headers named it `user_addr`.
+    #[deprecated(note = "the field is now `user_addr`")]
+    #[inline]
+    pub fn resv2(&self) -> __u64 {
+        self.user_addr
+    }
+}
+
+#[cfg(feature = "rustix")]
+mod rustix_backend;
+
+#[cfg(feature = "rustix")]
+pub(crate) use rustix_backend::{io_uring_enter, io_uring_register, io_uring_setup};
+
+/// Map the result of a raw system call, `-1` with `errno` set on failure.
+#[cfg(not(feature = "rustix"))]
+fn syscall_result(ret: c_long) -> std::io::Result<c_int> {
+    if ret >= 0 {
+        Ok(ret as _)
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(feature = "rustix"))]
+pub(crate) unsafe fn io_uring_register(
     fd: c_int,
     opcode: c_uint,
     arg: *const c_void,
     nr_args: c_uint,
-) -> c_int {
-    syscall(
+) -> std::io::Result<c_int> {
+    syscall_result(syscall(
         __NR_io_uring_register as c_long,
         fd as c_long,
         opcode as c_long,
         arg as c_long,
         nr_args as c_long,
-    ) as _
+    ))
 }
 
-pub unsafe fn io_uring_setup(entries: c_uint, p: *mut io_uring_params) -> c_int {
-    syscall(
+#[cfg(not(feature = "rustix"))]
+pub(crate) unsafe fn io_uring_setup(
+    entries: c_uint,
+    p: *mut io_uring_params,
+) -> std::io::Result<c_int> {
+    syscall_result(syscall(
         __NR_io_uring_setup as c_long,
         entries as c_long,
         p as c_long,
-    ) as _
+    ))
 }
 
-pub unsafe fn io_uring_enter(
+#[cfg(not(feature = "rustix"))]
+pub(crate) unsafe fn io_uring_enter(
     fd: c_int,
     to_submit: c_uint,
     min_complete: c_uint,
     flags: c_uint,
-    sig: *const sigset_t,
-) -> c_int {
-    syscall(
+    arg: *const c_void,
+    size: usize,
+) -> std::io::Result<c_int> {
+    syscall_result(syscall(
         __NR_io_uring_enter as c_long,
         fd as c_long,
         to_submit as c_long,
         min_complete as c_long,
         flags as c_long,
-        sig as c_long,
-        core::mem::size_of::<sigset_t>() as c_long,
-    ) as _
+        arg as c_long,
+        size as c_long,
+    ))
 }
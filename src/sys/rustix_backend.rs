@@ -0,0 +1,83 @@
+//! The system calls through `rustix`, which makes them without libc
+//! on the architectures it supports.
+
+use std::io;
+use std::os::unix::io::{BorrowedFd, IntoRawFd};
+
+use libc::{c_int, c_uint, c_void};
+use rustix::io_uring::{self as uring, IoringEnterFlags, IoringRegisterFlags, IoringRegisterOp};
+
+use super::*;
+
+pub unsafe fn io_uring_register(
+    fd: c_int,
+    opcode: c_uint,
+    arg: *const c_void,
+    nr_args: c_uint,
+) -> io::Result<c_int> {
+    let flags = IoringRegisterFlags::from_bits_retain(opcode & IORING_REGISTER_USE_REGISTERED_RING);
+    let opcode = register_op(opcode & !IORING_REGISTER_USE_REGISTERED_RING)?;
+
+    // A registered ring is referred to by its index, which is passed in place of the fd.
+    let fd = BorrowedFd::borrow_raw(fd);
+    let ret = uring::io_uring_register_with(fd, opcode, flags, arg, nr_args)?;
+    Ok(ret as _)
+}
+
+pub unsafe fn io_uring_setup(entries: c_uint, p: *mut io_uring_params) -> io::Result<c_int> {
+    // Both are the `struct io_uring_params` of the kernel.
+    let params = &mut *(p as *mut uring::io_uring_params);
+    let fd = uring::io_uring_setup(entries, params)?;
+    Ok(fd.into_raw_fd())
+}
+
+pub unsafe fn io_uring_enter(
+    fd: c_int,
+    to_submit: c_uint,
+    min_complete: c_uint,
+    flags: c_uint,
+    arg: *const c_void,
+    _size: usize,
+) -> io::Result<c_int> {
+    let fd = BorrowedFd::borrow_raw(fd);
+    let flags = IoringEnterFlags::from_bits_retain(flags);
+
+    // The argument is either the extended argument or a signal mask of the kernel size,
+    // whose size rustix passes itself.
+    let ret = if flags.contains(IoringEnterFlags::EXT_ARG) {
+        let arg = (arg as *const uring::io_uring_getevents_arg).as_ref();
+        uring::io_uring_enter_arg(fd, to_submit, min_complete, flags, arg)?
+    } else {
+        let sigmask = (arg as *const uring::KernelSigSet).as_ref();
+        uring::io_uring_enter_sigmask(fd, to_submit, min_complete, flags, sigmask)?
+    };
+    Ok(ret as _)
+}
+
+/// The register operations known to this crate, rustix only takes known ones.
+fn register_op(opcode: c_uint) -> io::Result<IoringRegisterOp> {
+    let op = match opcode {
+        IORING_REGISTER_BUFFERS => IoringRegisterOp::RegisterBuffers,
+        IORING_UNREGISTER_BUFFERS => IoringRegisterOp::UnregisterBuffers,
+        IORING_REGISTER_FILES => IoringRegisterOp::RegisterFiles,
+        IORING_UNREGISTER_FILES => IoringRegisterOp::UnregisterFiles,
+        IORING_REGISTER_EVENTFD => IoringRegisterOp::RegisterEventfd,
+        IORING_UNREGISTER_EVENTFD => IoringRegisterOp::UnregisterEventfd,
+        IORING_REGISTER_FILES_UPDATE => IoringRegisterOp::RegisterFilesUpdate,
+        IORING_REGISTER_EVENTFD_ASYNC => IoringRegisterOp::RegisterEventfdAsync,
+        IORING_REGISTER_PROBE => IoringRegisterOp::RegisterProbe,
+        IORING_REGISTER_PERSONALITY => IoringRegisterOp::RegisterPersonality,
+        IORING_UNREGISTER_PERSONALITY => IoringRegisterOp::UnregisterPersonality,
+        IORING_REGISTER_FILES2 => IoringRegisterOp::RegisterFiles2,
+        IORING_REGISTER_FILES_UPDATE2 => IoringRegisterOp::RegisterFilesUpdate2,
+        IORING_REGISTER_IOWQ_AFF => IoringRegisterOp::RegisterIowqAff,
+        IORING_UNREGISTER_IOWQ_AFF => IoringRegisterOp::UnregisterIowqAff,
+        IORING_REGISTER_IOWQ_MAX_WORKERS => IoringRegisterOp::RegisterIowqMaxWorkers,
+        IORING_REGISTER_RING_FDS => IoringRegisterOp::RegisterRingFds,
+        IORING_UNREGISTER_RING_FDS => IoringRegisterOp::UnregisterRingFds,
+        IORING_REGISTER_PBUF_RING => IoringRegisterOp::RegisterPbufRing,
+        IORING_UNREGISTER_PBUF_RING => IoringRegisterOp::UnregisterPbufRing,
+        _ => return Err(io::Error::from_raw_os_error(libc::EINVAL)),
+    };
+    Ok(op)
+}
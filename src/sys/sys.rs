@@ -39,24 +39,59 @@ pub const IORING_SETUP_SQ_AFF: u32 = 4;
 pub const IORING_SETUP_CQSIZE: u32 = 8;
 pub const IORING_SETUP_CLAMP: u32 = 16;
 pub const IORING_SETUP_ATTACH_WQ: u32 = 32;
+pub const IORING_SETUP_COOP_TASKRUN: u32 = 256;
+pub const IORING_SETUP_TASKRUN_FLAG: u32 = 512;
+pub const IORING_SETUP_SQE128: u32 = 1024;
+pub const IORING_SETUP_CQE32: u32 = 2048;
+pub const IORING_SETUP_DEFER_TASKRUN: u32 = 8192;
+pub const IORING_SETUP_NO_MMAP: u32 = 16384;
+pub const IORING_SETUP_NO_SQARRAY: u32 = 65536;
 pub const IORING_FSYNC_DATASYNC: u32 = 1;
 pub const IORING_TIMEOUT_ABS: u32 = 1;
+pub const IORING_TIMEOUT_UPDATE: u32 = 2;
+pub const IORING_ACCEPT_MULTISHOT: u32 = 1;
+pub const IORING_RECVSEND_FIXED_BUF: u32 = 4;
+pub const IORING_SEND_ZC_REPORT_USAGE: u32 = 8;
+pub const IORING_NOTIF_USAGE_ZC_COPIED: u32 = 2147483648;
+pub const IORING_ASYNC_CANCEL_ALL: u32 = 1;
+pub const IORING_ASYNC_CANCEL_FD: u32 = 2;
+pub const IORING_ASYNC_CANCEL_ANY: u32 = 4;
+pub const IORING_ASYNC_CANCEL_FD_FIXED: u32 = 8;
 pub const SPLICE_F_FD_IN_FIXED: u32 = 2147483648;
+pub const IORING_FILE_INDEX_ALLOC: u32 = 4294967295;
+pub const IORING_MSG_DATA: u32 = 0;
+pub const IORING_MSG_SEND_FD: u32 = 1;
+pub const IORING_FIXED_FD_NO_CLOEXEC: u32 = 1;
+pub const SOCKET_URING_OP_SIOCINQ: u32 = 0;
+pub const SOCKET_URING_OP_SIOCOUTQ: u32 = 1;
+pub const SOCKET_URING_OP_GETSOCKOPT: u32 = 2;
+pub const SOCKET_URING_OP_SETSOCKOPT: u32 = 3;
 pub const IORING_CQE_F_BUFFER: u32 = 1;
+pub const IORING_CQE_F_MORE: u32 = 2;
+pub const IORING_CQE_F_SOCK_NONEMPTY: u32 = 4;
+pub const IORING_CQE_F_NOTIF: u32 = 8;
 pub const IORING_OFF_SQ_RING: u32 = 0;
 pub const IORING_OFF_CQ_RING: u32 = 134217728;
 pub const IORING_OFF_SQES: u32 = 268435456;
 pub const IORING_SQ_NEED_WAKEUP: u32 = 1;
 pub const IORING_SQ_CQ_OVERFLOW: u32 = 2;
+pub const IORING_SQ_TASKRUN: u32 = 4;
 pub const IORING_CQ_EVENTFD_DISABLED: u32 = 1;
 pub const IORING_ENTER_GETEVENTS: u32 = 1;
 pub const IORING_ENTER_SQ_WAKEUP: u32 = 2;
+pub const IORING_ENTER_SQ_WAIT: u32 = 4;
+pub const IORING_ENTER_EXT_ARG: u32 = 8;
+pub const IORING_ENTER_REGISTERED_RING: u32 = 16;
 pub const IORING_FEAT_SINGLE_MMAP: u32 = 1;
 pub const IORING_FEAT_NODROP: u32 = 2;
 pub const IORING_FEAT_SUBMIT_STABLE: u32 = 4;
 pub const IORING_FEAT_RW_CUR_POS: u32 = 8;
 pub const IORING_FEAT_CUR_PERSONALITY: u32 = 16;
 pub const IORING_FEAT_FAST_POLL: u32 = 32;
+pub const IORING_FEAT_EXT_ARG: u32 = 256;
+pub const IORING_FEAT_CQE_SKIP: u32 = 2048;
+pub const IORING_FEAT_REG_REG_RING: u32 = 8192;
+pub const IORING_FEAT_MIN_TIMEOUT: u32 = 32768;
 pub const IORING_REGISTER_BUFFERS: u32 = 0;
 pub const IORING_UNREGISTER_BUFFERS: u32 = 1;
 pub const IORING_REGISTER_FILES: u32 = 2;
@@ -68,6 +103,17 @@ pub const IORING_REGISTER_EVENTFD_ASYNC: u32 = 7;
 pub const IORING_REGISTER_PROBE: u32 = 8;
 pub const IORING_REGISTER_PERSONALITY: u32 = 9;
 pub const IORING_UNREGISTER_PERSONALITY: u32 = 10;
+pub const IORING_REGISTER_FILES2: u32 = 13;
+pub const IORING_REGISTER_FILES_UPDATE2: u32 = 14;
+pub const IORING_REGISTER_IOWQ_AFF: u32 = 17;
+pub const IORING_UNREGISTER_IOWQ_AFF: u32 = 18;
+pub const IORING_REGISTER_IOWQ_MAX_WORKERS: u32 = 19;
+pub const IORING_REGISTER_RING_FDS: u32 = 20;
+pub const IORING_UNREGISTER_RING_FDS: u32 = 21;
+pub const IORING_REGISTER_PBUF_RING: u32 = 22;
+pub const IORING_UNREGISTER_PBUF_RING: u32 = 23;
+pub const IORING_REGISTER_USE_REGISTERED_RING: u32 = 2147483648;
+pub const IORING_RSRC_REGISTER_SPARSE: u32 = 1;
 pub const IO_URING_OP_SUPPORTED: u32 = 1;
 pub type __u8 = libc::c_uchar;
 pub type __u16 = libc::c_ushort;
@@ -94,7 +140,7 @@ fn bindgen_test_layout___kernel_timespec() {
         concat!("Alignment of ", stringify!(__kernel_timespec))
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<__kernel_timespec>())).tv_sec as *const _ as usize },
+        ::core::mem::offset_of!(__kernel_timespec, tv_sec),
         0usize,
         concat!(
             "Offset of field: ",
@@ -104,7 +150,7 @@ fn bindgen_test_layout___kernel_timespec() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<__kernel_timespec>())).tv_nsec as *const _ as usize },
+        ::core::mem::offset_of!(__kernel_timespec, tv_nsec),
         8usize,
         concat!(
             "Offset of field: ",
@@ -134,7 +180,7 @@ fn bindgen_test_layout_open_how() {
         concat!("Alignment of ", stringify!(open_how))
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<open_how>())).flags as *const _ as usize },
+        ::core::mem::offset_of!(open_how, flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -144,7 +190,7 @@ fn bindgen_test_layout_open_how() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<open_how>())).mode as *const _ as usize },
+        ::core::mem::offset_of!(open_how, mode),
         8usize,
         concat!(
             "Offset of field: ",
@@ -154,7 +200,7 @@ fn bindgen_test_layout_open_how() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<open_how>())).resolve as *const _ as usize },
+        ::core::mem::offset_of!(open_how, resolve),
         16usize,
         concat!(
             "Offset of field: ",
@@ -199,7 +245,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_1() {
         concat!("Alignment of ", stringify!(io_uring_sqe__bindgen_ty_1))
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_1>())).off as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_1, off),
         0usize,
         concat!(
             "Offset of field: ",
@@ -209,9 +255,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_1>())).addr2 as *const _ as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_1, addr2),
         0usize,
         concat!(
             "Offset of field: ",
@@ -246,9 +290,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_2() {
         concat!("Alignment of ", stringify!(io_uring_sqe__bindgen_ty_2))
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_2>())).addr as *const _ as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_2, addr),
         0usize,
         concat!(
             "Offset of field: ",
@@ -258,10 +300,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_2() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_2>())).splice_off_in as *const _
-                as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_2, splice_off_in),
         0usize,
         concat!(
             "Offset of field: ",
@@ -306,9 +345,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_3() {
         concat!("Alignment of ", stringify!(io_uring_sqe__bindgen_ty_3))
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_3>())).rw_flags as *const _ as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_3, rw_flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -318,9 +355,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_3() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_3>())).fsync_flags as *const _ as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_3, fsync_flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -330,9 +365,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_3() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_3>())).poll_events as *const _ as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_3, poll_events),
         0usize,
         concat!(
             "Offset of field: ",
@@ -342,10 +375,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_3() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_3>())).sync_range_flags as *const _
-                as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_3, sync_range_flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -355,9 +385,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_3() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_3>())).msg_flags as *const _ as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_3, msg_flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -367,10 +395,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_3() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_3>())).timeout_flags as *const _
-                as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_3, timeout_flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -380,10 +405,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_3() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_3>())).accept_flags as *const _
-                as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_3, accept_flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -393,10 +415,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_3() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_3>())).cancel_flags as *const _
-                as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_3, cancel_flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -406,9 +425,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_3() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_3>())).open_flags as *const _ as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_3, open_flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -418,9 +435,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_3() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_3>())).statx_flags as *const _ as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_3, statx_flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -430,10 +445,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_3() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_3>())).fadvise_advice as *const _
-                as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_3, fadvise_advice),
         0usize,
         concat!(
             "Offset of field: ",
@@ -443,10 +455,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_3() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_3>())).splice_flags as *const _
-                as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_3, splice_flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -501,10 +510,10 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_4__bindgen_ty_1__bindgen_ty_1()
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_4__bindgen_ty_1__bindgen_ty_1>()))
-                .buf_index as *const _ as usize
-        },
+        ::core::mem::offset_of!(
+            io_uring_sqe__bindgen_ty_4__bindgen_ty_1__bindgen_ty_1,
+            buf_index
+        ),
         0usize,
         concat!(
             "Offset of field: ",
@@ -514,10 +523,10 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_4__bindgen_ty_1__bindgen_ty_1()
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_4__bindgen_ty_1__bindgen_ty_1>()))
-                .buf_group as *const _ as usize
-        },
+        ::core::mem::offset_of!(
+            io_uring_sqe__bindgen_ty_4__bindgen_ty_1__bindgen_ty_1,
+            buf_group
+        ),
         0usize,
         concat!(
             "Offset of field: ",
@@ -551,10 +560,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_4__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_4__bindgen_ty_1>())).personality
-                as *const _ as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_4__bindgen_ty_1, personality),
         2usize,
         concat!(
             "Offset of field: ",
@@ -564,10 +570,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_4__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_4__bindgen_ty_1>())).splice_fd_in
-                as *const _ as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_4__bindgen_ty_1, splice_fd_in),
         4usize,
         concat!(
             "Offset of field: ",
@@ -595,9 +598,7 @@ fn bindgen_test_layout_io_uring_sqe__bindgen_ty_4() {
         concat!("Alignment of ", stringify!(io_uring_sqe__bindgen_ty_4))
     );
     assert_eq!(
-        unsafe {
-            &(*(::core::ptr::null::<io_uring_sqe__bindgen_ty_4>())).__pad2 as *const _ as usize
-        },
+        ::core::mem::offset_of!(io_uring_sqe__bindgen_ty_4, __pad2),
         0usize,
         concat!(
             "Offset of field: ",
@@ -625,7 +626,7 @@ fn bindgen_test_layout_io_uring_sqe() {
         concat!("Alignment of ", stringify!(io_uring_sqe))
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_sqe>())).opcode as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_sqe, opcode),
         0usize,
         concat!(
             "Offset of field: ",
@@ -635,7 +636,7 @@ fn bindgen_test_layout_io_uring_sqe() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_sqe>())).flags as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_sqe, flags),
         1usize,
         concat!(
             "Offset of field: ",
@@ -645,7 +646,7 @@ fn bindgen_test_layout_io_uring_sqe() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_sqe>())).ioprio as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_sqe, ioprio),
         2usize,
         concat!(
             "Offset of field: ",
@@ -655,7 +656,7 @@ fn bindgen_test_layout_io_uring_sqe() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_sqe>())).fd as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_sqe, fd),
         4usize,
         concat!(
             "Offset of field: ",
@@ -665,7 +666,7 @@ fn bindgen_test_layout_io_uring_sqe() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_sqe>())).len as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_sqe, len),
         24usize,
         concat!(
             "Offset of field: ",
@@ -675,7 +676,7 @@ fn bindgen_test_layout_io_uring_sqe() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_sqe>())).user_data as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_sqe, user_data),
         32usize,
         concat!(
             "Offset of field: ",
@@ -696,6 +697,7 @@ pub const IOSQE_IO_LINK_BIT: _bindgen_ty_4 = 2;
 pub const IOSQE_IO_HARDLINK_BIT: _bindgen_ty_4 = 3;
 pub const IOSQE_ASYNC_BIT: _bindgen_ty_4 = 4;
 pub const IOSQE_BUFFER_SELECT_BIT: _bindgen_ty_4 = 5;
+pub const IOSQE_CQE_SKIP_SUCCESS_BIT: _bindgen_ty_4 = 6;
 pub type _bindgen_ty_4 = u32;
 pub const IORING_OP_NOP: _bindgen_ty_5 = 0;
 pub const IORING_OP_READV: _bindgen_ty_5 = 1;
@@ -731,7 +733,28 @@ pub const IORING_OP_SPLICE: _bindgen_ty_5 = 30;
 pub const IORING_OP_PROVIDE_BUFFERS: _bindgen_ty_5 = 31;
 pub const IORING_OP_REMOVE_BUFFERS: _bindgen_ty_5 = 32;
 pub const IORING_OP_TEE: _bindgen_ty_5 = 33;
-pub const IORING_OP_LAST: _bindgen_ty_5 = 34;
+pub const IORING_OP_SHUTDOWN: _bindgen_ty_5 = 34;
+pub const IORING_OP_RENAMEAT: _bindgen_ty_5 = 35;
+pub const IORING_OP_UNLINKAT: _bindgen_ty_5 = 36;
+pub const IORING_OP_MKDIRAT: _bindgen_ty_5 = 37;
+pub const IORING_OP_SYMLINKAT: _bindgen_ty_5 = 38;
+pub const IORING_OP_LINKAT: _bindgen_ty_5 = 39;
+pub const IORING_OP_MSG_RING: _bindgen_ty_5 = 40;
+pub const IORING_OP_FSETXATTR: _bindgen_ty_5 = 41;
+pub const IORING_OP_SETXATTR: _bindgen_ty_5 = 42;
+pub const IORING_OP_FGETXATTR: _bindgen_ty_5 = 43;
+pub const IORING_OP_GETXATTR: _bindgen_ty_5 = 44;
+pub const IORING_OP_SOCKET: _bindgen_ty_5 = 45;
+pub const IORING_OP_URING_CMD: _bindgen_ty_5 = 46;
+pub const IORING_OP_SEND_ZC: _bindgen_ty_5 = 47;
+pub const IORING_OP_SENDMSG_ZC: _bindgen_ty_5 = 48;
+pub const IORING_OP_READ_MULTISHOT: _bindgen_ty_5 = 49;
+pub const IORING_OP_WAITID: _bindgen_ty_5 = 50;
+pub const IORING_OP_FUTEX_WAIT: _bindgen_ty_5 = 51;
+pub const IORING_OP_FUTEX_WAKE: _bindgen_ty_5 = 52;
+pub const IORING_OP_FUTEX_WAITV: _bindgen_ty_5 = 53;
+pub const IORING_OP_FIXED_FD_INSTALL: _bindgen_ty_5 = 54;
+pub const IORING_OP_LAST: _bindgen_ty_5 = 55;
 pub type _bindgen_ty_5 = u32;
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone)]
@@ -753,7 +776,7 @@ fn bindgen_test_layout_io_uring_cqe() {
         concat!("Alignment of ", stringify!(io_uring_cqe))
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_cqe>())).user_data as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_cqe, user_data),
         0usize,
         concat!(
             "Offset of field: ",
@@ -763,7 +786,7 @@ fn bindgen_test_layout_io_uring_cqe() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_cqe>())).res as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_cqe, res),
         8usize,
         concat!(
             "Offset of field: ",
@@ -773,7 +796,7 @@ fn bindgen_test_layout_io_uring_cqe() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_cqe>())).flags as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_cqe, flags),
         12usize,
         concat!(
             "Offset of field: ",
@@ -796,7 +819,7 @@ pub struct io_sqring_offsets {
     pub dropped: __u32,
     pub array: __u32,
     pub resv1: __u32,
-    pub resv2: __u64,
+    pub user_addr: __u64,
 }
 #[test]
 fn bindgen_test_layout_io_sqring_offsets() {
@@ -811,7 +834,7 @@ fn bindgen_test_layout_io_sqring_offsets() {
         concat!("Alignment of ", stringify!(io_sqring_offsets))
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_sqring_offsets>())).head as *const _ as usize },
+        ::core::mem::offset_of!(io_sqring_offsets, head),
         0usize,
         concat!(
             "Offset of field: ",
@@ -821,7 +844,7 @@ fn bindgen_test_layout_io_sqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_sqring_offsets>())).tail as *const _ as usize },
+        ::core::mem::offset_of!(io_sqring_offsets, tail),
         4usize,
         concat!(
             "Offset of field: ",
@@ -831,7 +854,7 @@ fn bindgen_test_layout_io_sqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_sqring_offsets>())).ring_mask as *const _ as usize },
+        ::core::mem::offset_of!(io_sqring_offsets, ring_mask),
         8usize,
         concat!(
             "Offset of field: ",
@@ -841,7 +864,7 @@ fn bindgen_test_layout_io_sqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_sqring_offsets>())).ring_entries as *const _ as usize },
+        ::core::mem::offset_of!(io_sqring_offsets, ring_entries),
         12usize,
         concat!(
             "Offset of field: ",
@@ -851,7 +874,7 @@ fn bindgen_test_layout_io_sqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_sqring_offsets>())).flags as *const _ as usize },
+        ::core::mem::offset_of!(io_sqring_offsets, flags),
         16usize,
         concat!(
             "Offset of field: ",
@@ -861,7 +884,7 @@ fn bindgen_test_layout_io_sqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_sqring_offsets>())).dropped as *const _ as usize },
+        ::core::mem::offset_of!(io_sqring_offsets, dropped),
         20usize,
         concat!(
             "Offset of field: ",
@@ -871,7 +894,7 @@ fn bindgen_test_layout_io_sqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_sqring_offsets>())).array as *const _ as usize },
+        ::core::mem::offset_of!(io_sqring_offsets, array),
         24usize,
         concat!(
             "Offset of field: ",
@@ -881,7 +904,7 @@ fn bindgen_test_layout_io_sqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_sqring_offsets>())).resv1 as *const _ as usize },
+        ::core::mem::offset_of!(io_sqring_offsets, resv1),
         28usize,
         concat!(
             "Offset of field: ",
@@ -891,13 +914,13 @@ fn bindgen_test_layout_io_sqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_sqring_offsets>())).resv2 as *const _ as usize },
+        ::core::mem::offset_of!(io_sqring_offsets, user_addr),
         32usize,
         concat!(
             "Offset of field: ",
             stringify!(io_sqring_offsets),
             "::",
-            stringify!(resv2)
+            stringify!(user_addr)
         )
     );
 }
@@ -912,7 +935,7 @@ pub struct io_cqring_offsets {
     pub cqes: __u32,
     pub flags: __u32,
     pub resv1: __u32,
-    pub resv2: __u64,
+    pub user_addr: __u64,
 }
 #[test]
 fn bindgen_test_layout_io_cqring_offsets() {
@@ -927,7 +950,7 @@ fn bindgen_test_layout_io_cqring_offsets() {
         concat!("Alignment of ", stringify!(io_cqring_offsets))
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_cqring_offsets>())).head as *const _ as usize },
+        ::core::mem::offset_of!(io_cqring_offsets, head),
         0usize,
         concat!(
             "Offset of field: ",
@@ -937,7 +960,7 @@ fn bindgen_test_layout_io_cqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_cqring_offsets>())).tail as *const _ as usize },
+        ::core::mem::offset_of!(io_cqring_offsets, tail),
         4usize,
         concat!(
             "Offset of field: ",
@@ -947,7 +970,7 @@ fn bindgen_test_layout_io_cqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_cqring_offsets>())).ring_mask as *const _ as usize },
+        ::core::mem::offset_of!(io_cqring_offsets, ring_mask),
         8usize,
         concat!(
             "Offset of field: ",
@@ -957,7 +980,7 @@ fn bindgen_test_layout_io_cqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_cqring_offsets>())).ring_entries as *const _ as usize },
+        ::core::mem::offset_of!(io_cqring_offsets, ring_entries),
         12usize,
         concat!(
             "Offset of field: ",
@@ -967,7 +990,7 @@ fn bindgen_test_layout_io_cqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_cqring_offsets>())).overflow as *const _ as usize },
+        ::core::mem::offset_of!(io_cqring_offsets, overflow),
         16usize,
         concat!(
             "Offset of field: ",
@@ -977,7 +1000,7 @@ fn bindgen_test_layout_io_cqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_cqring_offsets>())).cqes as *const _ as usize },
+        ::core::mem::offset_of!(io_cqring_offsets, cqes),
         20usize,
         concat!(
             "Offset of field: ",
@@ -987,7 +1010,7 @@ fn bindgen_test_layout_io_cqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_cqring_offsets>())).flags as *const _ as usize },
+        ::core::mem::offset_of!(io_cqring_offsets, flags),
         24usize,
         concat!(
             "Offset of field: ",
@@ -997,7 +1020,7 @@ fn bindgen_test_layout_io_cqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_cqring_offsets>())).resv1 as *const _ as usize },
+        ::core::mem::offset_of!(io_cqring_offsets, resv1),
         28usize,
         concat!(
             "Offset of field: ",
@@ -1007,13 +1030,13 @@ fn bindgen_test_layout_io_cqring_offsets() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_cqring_offsets>())).resv2 as *const _ as usize },
+        ::core::mem::offset_of!(io_cqring_offsets, user_addr),
         32usize,
         concat!(
             "Offset of field: ",
             stringify!(io_cqring_offsets),
             "::",
-            stringify!(resv2)
+            stringify!(user_addr)
         )
     );
 }
@@ -1044,7 +1067,7 @@ fn bindgen_test_layout_io_uring_params() {
         concat!("Alignment of ", stringify!(io_uring_params))
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_params>())).sq_entries as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_params, sq_entries),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1054,7 +1077,7 @@ fn bindgen_test_layout_io_uring_params() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_params>())).cq_entries as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_params, cq_entries),
         4usize,
         concat!(
             "Offset of field: ",
@@ -1064,7 +1087,7 @@ fn bindgen_test_layout_io_uring_params() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_params>())).flags as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_params, flags),
         8usize,
         concat!(
             "Offset of field: ",
@@ -1074,7 +1097,7 @@ fn bindgen_test_layout_io_uring_params() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_params>())).sq_thread_cpu as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_params, sq_thread_cpu),
         12usize,
         concat!(
             "Offset of field: ",
@@ -1084,7 +1107,7 @@ fn bindgen_test_layout_io_uring_params() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_params>())).sq_thread_idle as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_params, sq_thread_idle),
         16usize,
         concat!(
             "Offset of field: ",
@@ -1094,7 +1117,7 @@ fn bindgen_test_layout_io_uring_params() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_params>())).features as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_params, features),
         20usize,
         concat!(
             "Offset of field: ",
@@ -1104,7 +1127,7 @@ fn bindgen_test_layout_io_uring_params() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_params>())).wq_fd as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_params, wq_fd),
         24usize,
         concat!(
             "Offset of field: ",
@@ -1114,7 +1137,7 @@ fn bindgen_test_layout_io_uring_params() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_params>())).resv as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_params, resv),
         28usize,
         concat!(
             "Offset of field: ",
@@ -1124,7 +1147,7 @@ fn bindgen_test_layout_io_uring_params() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_params>())).sq_off as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_params, sq_off),
         40usize,
         concat!(
             "Offset of field: ",
@@ -1134,7 +1157,7 @@ fn bindgen_test_layout_io_uring_params() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_params>())).cq_off as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_params, cq_off),
         80usize,
         concat!(
             "Offset of field: ",
@@ -1164,7 +1187,7 @@ fn bindgen_test_layout_io_uring_files_update() {
         concat!("Alignment of ", stringify!(io_uring_files_update))
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_files_update>())).offset as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_files_update, offset),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1174,7 +1197,7 @@ fn bindgen_test_layout_io_uring_files_update() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_files_update>())).resv as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_files_update, resv),
         4usize,
         concat!(
             "Offset of field: ",
@@ -1184,7 +1207,7 @@ fn bindgen_test_layout_io_uring_files_update() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_files_update>())).fds as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_files_update, fds),
         8usize,
         concat!(
             "Offset of field: ",
@@ -1196,6 +1219,344 @@ fn bindgen_test_layout_io_uring_files_update() {
 }
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone)]
+pub struct io_uring_rsrc_update {
+    pub offset: __u32,
+    pub resv: __u32,
+    pub data: __u64,
+}
+#[test]
+fn bindgen_test_layout_io_uring_rsrc_update() {
+    assert_eq!(
+        ::core::mem::size_of::<io_uring_rsrc_update>(),
+        16usize,
+        concat!("Size of: ", stringify!(io_uring_rsrc_update))
+    );
+    assert_eq!(
+        ::core::mem::align_of::<io_uring_rsrc_update>(),
+        8usize,
+        concat!("Alignment of ", stringify!(io_uring_rsrc_update))
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_rsrc_update, offset),
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_rsrc_update),
+            "::",
+            stringify!(offset)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_rsrc_update, resv),
+        4usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_rsrc_update),
+            "::",
+            stringify!(resv)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_rsrc_update, data),
+        8usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_rsrc_update),
+            "::",
+            stringify!(data)
+        )
+    );
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct io_uring_rsrc_register {
+    pub nr: __u32,
+    pub flags: __u32,
+    pub resv2: __u64,
+    pub data: __u64,
+    pub tags: __u64,
+}
+#[test]
+fn bindgen_test_layout_io_uring_rsrc_register() {
+    assert_eq!(
+        ::core::mem::size_of::<io_uring_rsrc_register>(),
+        32usize,
+        concat!("Size of: ", stringify!(io_uring_rsrc_register))
+    );
+    assert_eq!(
+        ::core::mem::align_of::<io_uring_rsrc_register>(),
+        8usize,
+        concat!("Alignment of ", stringify!(io_uring_rsrc_register))
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_rsrc_register, nr),
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_rsrc_register),
+            "::",
+            stringify!(nr)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_rsrc_register, flags),
+        4usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_rsrc_register),
+            "::",
+            stringify!(flags)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_rsrc_register, resv2),
+        8usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_rsrc_register),
+            "::",
+            stringify!(resv2)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_rsrc_register, data),
+        16usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_rsrc_register),
+            "::",
+            stringify!(data)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_rsrc_register, tags),
+        24usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_rsrc_register),
+            "::",
+            stringify!(tags)
+        )
+    );
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct io_uring_rsrc_update2 {
+    pub offset: __u32,
+    pub resv: __u32,
+    pub data: __u64,
+    pub tags: __u64,
+    pub nr: __u32,
+    pub resv2: __u32,
+}
+#[test]
+fn bindgen_test_layout_io_uring_rsrc_update2() {
+    assert_eq!(
+        ::core::mem::size_of::<io_uring_rsrc_update2>(),
+        32usize,
+        concat!("Size of: ", stringify!(io_uring_rsrc_update2))
+    );
+    assert_eq!(
+        ::core::mem::align_of::<io_uring_rsrc_update2>(),
+        8usize,
+        concat!("Alignment of ", stringify!(io_uring_rsrc_update2))
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_rsrc_update2, offset),
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_rsrc_update2),
+            "::",
+            stringify!(offset)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_rsrc_update2, resv),
+        4usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_rsrc_update2),
+            "::",
+            stringify!(resv)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_rsrc_update2, data),
+        8usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_rsrc_update2),
+            "::",
+            stringify!(data)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_rsrc_update2, tags),
+        16usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_rsrc_update2),
+            "::",
+            stringify!(tags)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_rsrc_update2, nr),
+        24usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_rsrc_update2),
+            "::",
+            stringify!(nr)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_rsrc_update2, resv2),
+        28usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_rsrc_update2),
+            "::",
+            stringify!(resv2)
+        )
+    );
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct io_uring_buf {
+    pub addr: __u64,
+    pub len: __u32,
+    pub bid: __u16,
+    pub resv: __u16,
+}
+#[test]
+fn bindgen_test_layout_io_uring_buf() {
+    assert_eq!(
+        ::core::mem::size_of::<io_uring_buf>(),
+        16usize,
+        concat!("Size of: ", stringify!(io_uring_buf))
+    );
+    assert_eq!(
+        ::core::mem::align_of::<io_uring_buf>(),
+        8usize,
+        concat!("Alignment of ", stringify!(io_uring_buf))
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_buf, addr),
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_buf),
+            "::",
+            stringify!(addr)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_buf, len),
+        8usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_buf),
+            "::",
+            stringify!(len)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_buf, bid),
+        12usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_buf),
+            "::",
+            stringify!(bid)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_buf, resv),
+        14usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_buf),
+            "::",
+            stringify!(resv)
+        )
+    );
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct io_uring_buf_reg {
+    pub ring_addr: __u64,
+    pub ring_entries: __u32,
+    pub bgid: __u16,
+    pub pad: __u16,
+    pub resv: [__u64; 3usize],
+}
+#[test]
+fn bindgen_test_layout_io_uring_buf_reg() {
+    assert_eq!(
+        ::core::mem::size_of::<io_uring_buf_reg>(),
+        40usize,
+        concat!("Size of: ", stringify!(io_uring_buf_reg))
+    );
+    assert_eq!(
+        ::core::mem::align_of::<io_uring_buf_reg>(),
+        8usize,
+        concat!("Alignment of ", stringify!(io_uring_buf_reg))
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_buf_reg, ring_addr),
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_buf_reg),
+            "::",
+            stringify!(ring_addr)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_buf_reg, ring_entries),
+        8usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_buf_reg),
+            "::",
+            stringify!(ring_entries)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_buf_reg, bgid),
+        12usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_buf_reg),
+            "::",
+            stringify!(bgid)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_buf_reg, pad),
+        14usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_buf_reg),
+            "::",
+            stringify!(pad)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_buf_reg, resv),
+        16usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_buf_reg),
+            "::",
+            stringify!(resv)
+        )
+    );
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
 pub struct io_uring_probe_op {
     pub op: __u8,
     pub resv: __u8,
@@ -1215,7 +1576,7 @@ fn bindgen_test_layout_io_uring_probe_op() {
         concat!("Alignment of ", stringify!(io_uring_probe_op))
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_probe_op>())).op as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_probe_op, op),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1225,7 +1586,7 @@ fn bindgen_test_layout_io_uring_probe_op() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_probe_op>())).resv as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_probe_op, resv),
         1usize,
         concat!(
             "Offset of field: ",
@@ -1235,7 +1596,7 @@ fn bindgen_test_layout_io_uring_probe_op() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_probe_op>())).flags as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_probe_op, flags),
         2usize,
         concat!(
             "Offset of field: ",
@@ -1245,7 +1606,7 @@ fn bindgen_test_layout_io_uring_probe_op() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_probe_op>())).resv2 as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_probe_op, resv2),
         4usize,
         concat!(
             "Offset of field: ",
@@ -1277,7 +1638,7 @@ fn bindgen_test_layout_io_uring_probe() {
         concat!("Alignment of ", stringify!(io_uring_probe))
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_probe>())).last_op as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_probe, last_op),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1287,7 +1648,7 @@ fn bindgen_test_layout_io_uring_probe() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_probe>())).ops_len as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_probe, ops_len),
         1usize,
         concat!(
             "Offset of field: ",
@@ -1297,7 +1658,7 @@ fn bindgen_test_layout_io_uring_probe() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_probe>())).resv as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_probe, resv),
         2usize,
         concat!(
             "Offset of field: ",
@@ -1307,7 +1668,7 @@ fn bindgen_test_layout_io_uring_probe() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_probe>())).resv2 as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_probe, resv2),
         4usize,
         concat!(
             "Offset of field: ",
@@ -1317,7 +1678,7 @@ fn bindgen_test_layout_io_uring_probe() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::core::ptr::null::<io_uring_probe>())).ops as *const _ as usize },
+        ::core::mem::offset_of!(io_uring_probe, ops),
         16usize,
         concat!(
             "Offset of field: ",
@@ -1327,3 +1688,64 @@ fn bindgen_test_layout_io_uring_probe() {
         )
     );
 }
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct io_uring_getevents_arg {
+    pub sigmask: __u64,
+    pub sigmask_sz: __u32,
+    pub min_wait_usec: __u32,
+    pub ts: __u64,
+}
+#[test]
+fn bindgen_test_layout_io_uring_getevents_arg() {
+    assert_eq!(
+        ::core::mem::size_of::<io_uring_getevents_arg>(),
+        24usize,
+        concat!("Size of: ", stringify!(io_uring_getevents_arg))
+    );
+    assert_eq!(
+        ::core::mem::align_of::<io_uring_getevents_arg>(),
+        8usize,
+        concat!("Alignment of ", stringify!(io_uring_getevents_arg))
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_getevents_arg, sigmask),
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_getevents_arg),
+            "::",
+            stringify!(sigmask)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_getevents_arg, sigmask_sz),
+        8usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_getevents_arg),
+            "::",
+            stringify!(sigmask_sz)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_getevents_arg, min_wait_usec),
+        12usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_getevents_arg),
+            "::",
+            stringify!(min_wait_usec)
+        )
+    );
+    assert_eq!(
+        ::core::mem::offset_of!(io_uring_getevents_arg, ts),
+        16usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(io_uring_getevents_arg),
+            "::",
+            stringify!(ts)
+        )
+    );
+}
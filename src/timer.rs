@@ -0,0 +1,309 @@
+//! Timers sharing a single timeout request.
+
+use std::time::{Duration, Instant};
+
+use crate::cqueue;
+use crate::opcode::{self, types};
+use crate::squeue;
+use crate::tracker::OpTracker;
+
+/// A hashed timer wheel, driving any number of timers with one
+/// [Timeout](opcode::Timeout) request at a time.
+///
+/// Time is divided into ticks, and each timer is stored in the slot of the tick it expires at,
+/// modulo the number of slots. The timeout request is armed for the next tick whose slot
+/// is not empty, and moved earlier with [TimeoutUpdate](opcode::TimeoutUpdate)
+/// when a timer is inserted before it. Timers expire at the end of their tick,
+/// so the tick bounds how late they may be.
+///
+/// After inserting or canceling timers, the entry returned by [TimerWheel::arm]
+/// must be pushed, and the completion events of the wheel are passed to
+/// [TimerWheel::complete], which returns the `user_data` of the expired timers.
+pub struct TimerWheel {
+    key: u64,
+    tick: Duration,
+    start: Instant,
+    /// The last tick whose timers expired.
+    current: u64,
+    slots: Box<[Vec<u64>]>,
+    timers: OpTracker<Timer>,
+    /// The tick the timeout request is armed for.
+    armed: Option<u64>,
+    timespec: Box<types::Timespec>,
+}
+
+#[derive(Clone, Copy)]
+struct Timer {
+    tick: u64,
+    user_data: u64,
+}
+
+impl TimerWheel {
+    /// Create a wheel of `slots` slots (rounded up to a power of two), each `tick` long,
+    /// whose requests have `key` as `user_data`, which must not be used by other requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tick` is zero.
+    pub fn new(key: u64, tick: Duration, slots: usize) -> TimerWheel {
+        assert!(tick > Duration::from_secs(0), "the tick must not be zero");
+
+        let slots = (0..slots.max(1).next_power_of_two())
+            .map(|_| Vec::new())
+            .collect();
+
+        TimerWheel {
+            key,
+            tick,
+            start: Instant::now(),
+            current: 0,
+            slots,
+            timers: OpTracker::new(),
+            armed: None,
+            timespec: Box::new(types::Timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            }),
+        }
+    }
+
+    /// The `user_data` of the requests of the wheel.
+    #[inline]
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    /// The number of pending timers.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    /// Insert a timer expiring at `deadline`, and return its key.
+    ///
+    /// The expiration is reported with `user_data`, which may be shared by several timers.
+    pub fn insert(&mut self, deadline: Instant, user_data: u64) -> u64 {
+        let elapsed = deadline.saturating_duration_since(self.start);
+        let tick = elapsed.as_nanos().div_ceil(self.tick.as_nanos());
+        let tick = (tick as u64).max(self.current + 1);
+
+        let key = self.timers.insert(Timer { tick, user_data });
+        self.slot(tick).push(key);
+        key
+    }
+
+    /// Cancel a pending timer, returns `false` if it already expired.
+    pub fn cancel(&mut self, key: u64) -> bool {
+        // The key is removed from its slot once the slot is visited.
+        self.timers.remove(key).is_some()
+    }
+
+    /// Return the entry arming or moving the timeout request for the next pending timer,
+    /// if the request is not armed early enough already.
+    ///
+    /// The entry refers to memory of the wheel, which must outlive its submission.
+    pub fn arm(&mut self) -> Option<squeue::Entry> {
+        let next = self.next_tick()?;
+
+        let entry = match self.armed {
+            Some(armed) if armed <= next => return None,
+            Some(_) => {
+                self.set_timespec(next);
+                opcode::TimeoutUpdate::new(self.key, &*self.timespec).build()
+            }
+            None => {
+                self.set_timespec(next);
+                opcode::Timeout::new(&*self.timespec).build()
+            }
+        };
+
+        self.armed = Some(next);
+        Some(entry.user_data(self.key))
+    }
+
+    /// Record a completion event, returns `None` if it does not belong to the wheel,
+    /// or the `user_data` of the timers that expired.
+    ///
+    /// [TimerWheel::arm] must be called afterwards to wait for the remaining timers.
+    pub fn complete(&mut self, entry: &cqueue::Entry) -> Option<Vec<u64>> {
+        self.complete_at(entry, Instant::now())
+    }
+
+    /// [TimerWheel::complete], with the timers expiring up to `now`.
+    fn complete_at(&mut self, entry: &cqueue::Entry, now: Instant) -> Option<Vec<u64>> {
+        if entry.user_data() != self.key {
+            return None;
+        }
+
+        match -entry.result() {
+            // The timeout request expired, or was canceled.
+            libc::ETIME | libc::ECANCELED => self.armed = None,
+            // The update of the timeout request, which may have expired before.
+            _ => return Some(Vec::new()),
+        }
+
+        let now = now.saturating_duration_since(self.start).as_nanos() / self.tick.as_nanos();
+        let now = (now as u64).max(self.current);
+        let mut expired = Vec::new();
+
+        // Each slot is visited at most once, even if many ticks have passed.
+        let visit = (now - self.current).min(self.slots.len() as u64);
+        for tick in self.current + 1..=self.current + visit {
+            let timers = &mut self.timers;
+            let slot = index(&self.slots, tick);
+
+            self.slots[slot].retain(|&key| match timers.get(key) {
+                Some(timer) if timer.tick <= now => {
+                    expired.push(timer.user_data);
+                    timers.remove(key);
+                    false
+                }
+                Some(_) => true,
+                None => false,
+            });
+        }
+
+        self.current = now;
+        Some(expired)
+    }
+
+    /// The first tick after the current one whose slot has pending timers.
+    fn next_tick(&mut self) -> Option<u64> {
+        if self.timers.is_empty() {
+            return None;
+        }
+
+        for tick in self.current + 1..=self.current + self.slots.len() as u64 {
+            let timers = &self.timers;
+            let slot = index(&self.slots, tick);
+
+            self.slots[slot].retain(|&key| timers.get(key).is_some());
+            if !self.slots[slot].is_empty() {
+                return Some(tick);
+            }
+        }
+
+        None
+    }
+
+    fn slot(&mut self, tick: u64) -> &mut Vec<u64> {
+        let slot = index(&self.slots, tick);
+        &mut self.slots[slot]
+    }
+
+    fn set_timespec(&mut self, tick: u64) {
+        let deadline =
+            self.start + Duration::from_nanos((self.tick.as_nanos() * tick as u128) as u64);
+        let timeout = deadline.saturating_duration_since(Instant::now());
+
+        self.timespec.tv_sec = timeout.as_secs() as _;
+        self.timespec.tv_nsec = timeout.subsec_nanos() as _;
+    }
+}
+
+#[inline]
+fn index(slots: &[Vec<u64>], tick: u64) -> usize {
+    (tick & (slots.len() as u64 - 1)) as usize
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::{MockRing, Reply};
+
+    const KEY: u64 = 0x100;
+    const TICK: Duration = Duration::from_millis(10);
+
+    fn submit(ring: &mut MockRing, entry: squeue::Entry) {
+        unsafe { ring.submission().available().push(entry).unwrap() };
+        ring.submit().unwrap();
+    }
+
+    /// Expire the timeout request, as seen `ticks` ticks after the start of the wheel.
+    fn expire(ring: &mut MockRing, wheel: &mut TimerWheel, ticks: u32) -> Option<Vec<u64>> {
+        assert!(ring.complete(KEY, Reply::new(-libc::ETIME)));
+        let cqe = ring.completion().available().next().unwrap();
+        wheel.complete_at(&cqe, wheel.start + TICK * ticks)
+    }
+
+    #[test]
+    fn test_expire() {
+        let mut ring = MockRing::new(4);
+        let mut wheel = TimerWheel::new(KEY, TICK, 8);
+
+        wheel.insert(wheel.start, 1);
+        wheel.insert(wheel.start + TICK / 2, 2);
+        wheel.insert(wheel.start + TICK * 2, 3);
+        let entry = wheel.arm().unwrap();
+        assert_eq!(entry.opcode(), opcode::Timeout::CODE);
+        assert_eq!(entry.get_user_data(), KEY);
+        assert!(wheel.arm().is_none());
+        submit(&mut ring, entry);
+
+        // Timers expire at the end of their tick.
+        assert_eq!(expire(&mut ring, &mut wheel, 1), Some(vec![1, 2]));
+        assert_eq!(wheel.len(), 1);
+
+        submit(&mut ring, wheel.arm().unwrap());
+        assert_eq!(expire(&mut ring, &mut wheel, 2), Some(vec![3]));
+        assert!(wheel.is_empty());
+        assert!(wheel.arm().is_none());
+
+        let other = cqueue::Entry::from(crate::sys::io_uring_cqe {
+            user_data: KEY + 1,
+            res: -libc::ETIME,
+            flags: 0,
+        });
+        assert_eq!(wheel.complete(&other), None);
+    }
+
+    #[test]
+    fn test_expire_late() {
+        let mut ring = MockRing::new(4);
+        let mut wheel = TimerWheel::new(KEY, TICK, 4);
+
+        // Past the span of the wheel, the timer shares its slot with an earlier tick.
+        wheel.insert(wheel.start + TICK * 6, 1);
+        wheel.insert(wheel.start + TICK * 2, 2);
+        submit(&mut ring, wheel.arm().unwrap());
+
+        assert_eq!(expire(&mut ring, &mut wheel, 2), Some(vec![2]));
+        submit(&mut ring, wheel.arm().unwrap());
+        assert_eq!(expire(&mut ring, &mut wheel, 5), Some(vec![]));
+
+        // Many ticks later, each slot is visited once.
+        submit(&mut ring, wheel.arm().unwrap());
+        assert_eq!(expire(&mut ring, &mut wheel, 100), Some(vec![1]));
+        assert!(wheel.is_empty());
+    }
+
+    #[test]
+    fn test_move_earlier_and_cancel() {
+        let mut ring = MockRing::new(4);
+        let mut wheel = TimerWheel::new(KEY, TICK, 8);
+
+        let late = wheel.insert(wheel.start + TICK * 5, 1);
+        submit(&mut ring, wheel.arm().unwrap());
+
+        // An earlier timer moves the armed request.
+        wheel.insert(wheel.start + TICK, 2);
+        let entry = wheel.arm().unwrap();
+        assert_eq!(entry.opcode(), opcode::TimeoutUpdate::CODE);
+        submit(&mut ring, entry);
+        assert!(ring.complete(KEY, Reply::new(0)));
+        let cqe = ring.completion().available().next().unwrap();
+        assert_eq!(wheel.complete(&cqe), Some(Vec::new()));
+
+        assert!(wheel.cancel(late));
+        assert!(!wheel.cancel(late));
+
+        assert_eq!(expire(&mut ring, &mut wheel, 1), Some(vec![2]));
+        assert!(wheel.is_empty());
+        assert!(wheel.arm().is_none());
+    }
+}
@@ -0,0 +1,129 @@
+//! Waiting for completion events from a Tokio runtime.
+//!
+//! [TokioRing] registers an eventfd with its ring and waits for it with the reactor of Tokio,
+//! so a task can drive the ring without blocking the thread.
+//!
+//! ```no_run
+//! use io_uring::tokio::TokioRing;
+//! use io_uring::{opcode, IoUring};
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let mut ring = TokioRing::new(IoUring::new(8)?)?;
+//!
+//! unsafe {
+//!     ring.submission()
+//!         .available()
+//!         .push(opcode::Nop::new().build().user_data(0x42))
+//!         .expect("queue is full");
+//! }
+//! ring.submitter().submit()?;
+//! ring.wait_completions().await?;
+//!
+//! let cqe = ring.completion().available().next().expect("completion queue is empty");
+//! assert_eq!(cqe.user_data(), 0x42);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io;
+
+use ::tokio::io::unix::AsyncFd;
+
+use crate::eventfd::EventFd;
+use crate::{CompletionQueue, IoUring, SubmissionQueue, Submitter};
+
+/// A ring whose completion events can be awaited in a Tokio runtime.
+pub struct TokioRing {
+    ring: IoUring,
+    eventfd: AsyncFd<EventFd>,
+}
+
+impl TokioRing {
+    /// Register an eventfd with `ring` and add it to the reactor of the current runtime.
+    ///
+    /// This must be called within a Tokio runtime with IO enabled,
+    /// and fails with `EBUSY` if the ring already has an eventfd.
+    pub fn new(ring: IoUring) -> io::Result<TokioRing> {
+        let eventfd = AsyncFd::new(EventFd::new()?)?;
+        eventfd.get_ref().register(&ring.submitter())?;
+
+        Ok(TokioRing { ring, eventfd })
+    }
+
+    #[inline]
+    pub fn submitter(&self) -> Submitter<'_> {
+        self.ring.submitter()
+    }
+
+    #[inline]
+    pub fn submission(&mut self) -> &mut SubmissionQueue {
+        self.ring.submission()
+    }
+
+    #[inline]
+    pub fn completion(&mut self) -> &mut CompletionQueue {
+        self.ring.completion()
+    }
+
+    /// Wait until the completion queue is not empty.
+    ///
+    /// This returns at once if completion events are already available.
+    /// Only the requests that have been submitted are waited for,
+    /// so the queue has to be submitted first.
+    pub async fn wait_completions(&mut self) -> io::Result<()> {
+        loop {
+            if !self.ring.completion().is_empty() {
+                return Ok(());
+            }
+
+            let mut guard = self.eventfd.readable().await?;
+
+            // The counter is reset before the queue is checked again,
+            // so an event posted after the check makes the eventfd readable again.
+            guard.get_inner().read()?;
+            guard.clear_ready();
+        }
+    }
+
+    /// Unregister the eventfd and return the ring.
+    pub fn into_inner(self) -> IoUring {
+        // This can only fail if the eventfd was unregistered through the submitter.
+        let _ = self.ring.submitter().unregister_eventfd();
+        self.ring
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opcode;
+
+    #[test]
+    fn test_wait_completions() -> io::Result<()> {
+        let rt = ::tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()?;
+
+        rt.block_on(async {
+            let mut ring = TokioRing::new(IoUring::new(8)?)?;
+
+            for user_data in 0..2 {
+                unsafe {
+                    ring.submission()
+                        .available()
+                        .push(opcode::Nop::new().build().user_data(user_data))
+                        .expect("queue is full");
+                }
+                ring.submitter().submit()?;
+                ring.wait_completions().await?;
+
+                let cqe = ring.completion().available().next();
+                assert_eq!(cqe.map(|cqe| cqe.user_data()), Some(user_data));
+            }
+
+            let mut ring = ring.into_inner();
+            assert!(ring.completion().is_empty());
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,59 @@
+//! Instrumentation with `tracing`.
+//!
+//! Entries are traced when they become visible to the kernel,
+//! and completion events when they are released back to it,
+//! so that every push and reap path is covered by the same few hooks.
+
+use std::{fmt, io};
+
+use crate::opcode::OpCode;
+use crate::sys;
+
+/// An entry was pushed into the submission queue.
+pub(crate) fn push(sqe: &sys::io_uring_sqe) {
+    tracing::trace!(
+        opcode = ?Op(sqe.opcode),
+        user_data = sqe.user_data,
+        flags = sqe.flags,
+        "push"
+    );
+}
+
+/// A completion event was reaped, failures are reported at the debug level.
+pub(crate) fn reap(cqe: &sys::io_uring_cqe) {
+    if cqe.res < 0 {
+        tracing::debug!(
+            user_data = cqe.user_data,
+            result = cqe.res,
+            flags = cqe.flags,
+            "reap"
+        );
+    } else {
+        tracing::trace!(
+            user_data = cqe.user_data,
+            result = cqe.res,
+            flags = cqe.flags,
+            "reap"
+        );
+    }
+}
+
+/// `io_uring_enter(2)` returned, within the `enter` span.
+pub(crate) fn enter(result: &io::Result<usize>) {
+    match result {
+        Ok(n) => tracing::trace!(result = n, "enter"),
+        Err(err) => tracing::debug!(error = %err, "enter failed"),
+    }
+}
+
+/// The name of an operation code, or its value if it is unknown.
+struct Op(u8);
+
+impl fmt::Debug for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match OpCode::from_raw(self.0) {
+            Some(op) => op.fmt(f),
+            None => self.0.fmt(f),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! Tracking of operations in flight.
+
+use std::{mem, slice};
+
+use crate::cqueue;
+use crate::squeue::{self, AvailableQueue, PushError};
+
+/// The state of the operations in flight, keyed by the `user_data` of their requests.
+///
+/// A key is made of a slot index, which is reused once the operation completed,
+/// and the generation of the slot, so that the stale key of a completed operation
+/// never resolves to the operation that reuses its slot.
+///
+/// Dropping the tracker while operations are in flight leaks their state,
+/// because it may own memory the kernel still uses.
+/// [OpTracker::pending] lists them, to find the operations that never completed.
+pub struct OpTracker<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+}
+
+struct Slot<T> {
+    generation: u32,
+    state: Option<T>,
+}
+
+impl<T> OpTracker<T> {
+    pub fn new() -> OpTracker<T> {
+        OpTracker {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Create a tracker with space for `capacity` operations.
+    pub fn with_capacity(capacity: usize) -> OpTracker<T> {
+        OpTracker {
+            slots: Vec::with_capacity(capacity),
+            free: Vec::new(),
+        }
+    }
+
+    /// The number of operations in flight.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Store the state of a new operation, and return its key.
+    pub fn insert(&mut self, state: T) -> u64 {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    state: None,
+                });
+                (self.slots.len() - 1) as u32
+            }
+        };
+
+        let slot = &mut self.slots[index as usize];
+        slot.state = Some(state);
+        key(index, slot.generation)
+    }
+
+    /// Push an entry into the submission queue with the key of its state as `user_data`.
+    ///
+    /// # Safety
+    ///
+    /// Developers must ensure that parameters of the entry (such as buffer) are valid,
+    /// otherwise it may cause memory problems.
+    pub unsafe fn push(
+        &mut self,
+        sq: &mut AvailableQueue<'_>,
+        entry: squeue::Entry,
+        state: T,
+    ) -> Result<u64, PushError<(squeue::Entry, T)>> {
+        let key = self.insert(state);
+
+        match sq.push(entry.user_data(key)) {
+            Ok(()) => Ok(key),
+            Err(PushError::Full(entry)) => {
+                let state = self.remove(key).expect("the state was just inserted");
+                Err(PushError::Full((entry, state)))
+            }
+        }
+    }
+
+    /// The state of an operation in flight.
+    pub fn get(&self, key: u64) -> Option<&T> {
+        let (index, generation) = split(key);
+
+        self.slots
+            .get(index as usize)
+            .filter(|slot| slot.generation == generation)
+            .and_then(|slot| slot.state.as_ref())
+    }
+
+    /// The state of an operation in flight.
+    pub fn get_mut(&mut self, key: u64) -> Option<&mut T> {
+        let (index, generation) = split(key);
+
+        self.slots
+            .get_mut(index as usize)
+            .filter(|slot| slot.generation == generation)
+            .and_then(|slot| slot.state.as_mut())
+    }
+
+    /// Remove the state of an operation, returns `None` if the key is unknown or stale.
+    pub fn remove(&mut self, key: u64) -> Option<T> {
+        let (index, generation) = split(key);
+
+        let slot = self
+            .slots
+            .get_mut(index as usize)
+            .filter(|slot| slot.generation == generation)?;
+        let state = slot.state.take()?;
+
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(index);
+        Some(state)
+    }
+
+    /// Remove the state of the operation a completion event belongs to.
+    #[inline]
+    pub fn complete(&mut self, entry: &cqueue::Entry) -> Option<T> {
+        self.remove(entry.user_data())
+    }
+
+    /// The operations in flight, with their keys.
+    pub fn pending(&self) -> Pending<'_, T> {
+        Pending {
+            slots: self.slots.iter().enumerate(),
+        }
+    }
+}
+
+impl<T> Default for OpTracker<T> {
+    fn default() -> OpTracker<T> {
+        OpTracker::new()
+    }
+}
+
+impl<T> Drop for OpTracker<T> {
+    fn drop(&mut self) {
+        for slot in self.slots.drain(..) {
+            mem::forget(slot.state);
+        }
+    }
+}
+
+/// An iterator over the operations in flight of an [OpTracker].
+pub struct Pending<'a, T> {
+    slots: std::iter::Enumerate<slice::Iter<'a, Slot<T>>>,
+}
+
+impl<'a, T> Iterator for Pending<'a, T> {
+    type Item = (u64, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.slots.find_map(|(index, slot)| {
+            let state = slot.state.as_ref()?;
+            Some((key(index as u32, slot.generation), state))
+        })
+    }
+}
+
+#[inline]
+fn key(index: u32, generation: u32) -> u64 {
+    (generation as u64) << 32 | index as u64
+}
+
+#[inline]
+fn split(key: u64) -> (u32, u32) {
+    (key as u32, (key >> 32) as u32)
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::{MockRing, Reply};
+    use crate::opcode::{self, OpCode};
+
+    fn nop() -> squeue::Entry {
+        opcode::Nop::new().build()
+    }
+
+    #[test]
+    fn test_push_full() {
+        let mut ring = MockRing::new(2);
+        let mut ops = OpTracker::new();
+
+        let keys: Vec<_> = (0..2)
+            .map(|i| unsafe { ops.push(&mut ring.submission().available(), nop(), i) }.unwrap())
+            .collect();
+
+        // The state of a rejected entry is handed back, and nothing is left tracked.
+        match unsafe { ops.push(&mut ring.submission().available(), nop(), 2) } {
+            Err(PushError::Full((_, state))) => assert_eq!(state, 2),
+            Ok(_) => panic!("pushed into a full queue"),
+        }
+        assert_eq!(ops.len(), 2);
+
+        ring.reply_opcode(OpCode::Nop, Reply::new(0));
+        ring.submit().unwrap();
+        let states: Vec<_> = ring
+            .completion()
+            .available()
+            .map(|cqe| ops.complete(&cqe))
+            .collect();
+        assert_eq!(states, [Some(0), Some(1)]);
+        assert!(ops.is_empty());
+
+        // The slots of the completed operations are reused.
+        let key = unsafe { ops.push(&mut ring.submission().available(), nop(), 3) }.unwrap();
+        assert!(keys.contains(&(key & u32::MAX as u64)));
+    }
+
+    #[test]
+    fn test_stale_key() {
+        let mut ops = OpTracker::new();
+
+        let stale = ops.insert("first");
+        assert_eq!(ops.remove(stale), Some("first"));
+
+        let key = ops.insert("second");
+        assert_ne!(key, stale);
+        assert_eq!(ops.get(stale), None);
+        assert_eq!(ops.remove(stale), None);
+        assert_eq!(ops.get(key), Some(&"second"));
+
+        let pending: Vec<_> = ops.pending().collect();
+        assert_eq!(pending, [(key, &"second")]);
+        assert_eq!(ops.remove(key), Some("second"));
+    }
+}
@@ -0,0 +1,426 @@
+//! Commands of the ublk driver, which serves block devices from user space.
+//!
+//! The device is set up with control commands on `/dev/ublk-control`, which need a ring with
+//! [128-byte entries](crate::Builder::setup_sqe128), then each of its queues is served with
+//! I/O commands on `/dev/ublkcN`: [IoCmd::fetch_req] waits for the next request of a tag,
+//! and [IoCmd::commit_and_fetch_req] completes it and waits for the next one.
+//! The requests are described by the [IoDesc] array the driver maps at the start of the
+//! character device, one per tag and queue.
+//!
+//! The commands use the `ioctl` encoding of the opcodes by default, and the plain
+//! opcodes of kernels before Linux 6.1 with [IoCmd::legacy] and [CtrlCmd::legacy].
+
+use std::mem;
+
+use crate::opcode::{self, sealed};
+use crate::squeue::{Entry, Entry128};
+
+/// The `ioctl` type of the ublk commands.
+const IOCTL_TYPE: u32 = b'u' as u32;
+
+/// The maximum depth of a queue.
+pub const MAX_QUEUE_DEPTH: u16 = 4096;
+
+/// The byte offset of the [IoDesc] array of queue `q_id` in the character device,
+/// to map it with `mmap(2)`.
+pub fn io_desc_offset(q_id: u16) -> libc::off_t {
+    // The arrays are spaced for the maximum depth, rounded up to whole pages.
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let size = (MAX_QUEUE_DEPTH as usize * mem::size_of::<IoDesc>()).div_ceil(page) * page;
+    (q_id as usize * size) as _
+}
+
+/// An I/O command of a queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoOp {
+    /// Wait for a request of the tag.
+    FetchReq,
+    /// Complete the request of the tag, and wait for the next one.
+    CommitAndFetchReq,
+    /// Get the data of a write request into the buffer, with `UBLK_F_NEED_GET_DATA`.
+    NeedGetData,
+}
+
+/// A control command of a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CtrlOp {
+    GetQueueAffinity,
+    GetDevInfo,
+    AddDev,
+    DelDev,
+    StartDev,
+    StopDev,
+    SetParams,
+    GetParams,
+    StartUserRecovery,
+    EndUserRecovery,
+    GetDevInfo2,
+}
+
+/// An I/O command, issued with a [UringCmd16](opcode::UringCmd16) on the character device.
+#[derive(Debug, Clone, Copy)]
+pub struct IoCmd {
+    op: IoOp,
+    legacy: bool,
+    cmd: ublksrv_io_cmd,
+}
+
+/// A control command, issued with a [UringCmd80](opcode::UringCmd80) on `/dev/ublk-control`.
+#[derive(Debug, Clone, Copy)]
+pub struct CtrlCmd {
+    op: CtrlOp,
+    legacy: bool,
+    cmd: ublksrv_ctrl_cmd,
+}
+
+/// The description of a request, in the array mapped from the character device.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IoDesc {
+    /// The operation in the low 8 bits, and its flags above.
+    pub op_flags: u32,
+    pub nr_sectors: u32,
+    pub start_sector: u64,
+    /// The buffer of the request, as given to the last fetch of its tag.
+    pub addr: u64,
+}
+
+/// The information of a device, passed with [CtrlOp::AddDev] and [CtrlOp::GetDevInfo].
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DevInfo {
+    pub nr_hw_queues: u16,
+    pub queue_depth: u16,
+    pub state: u16,
+    pub pad0: u16,
+    pub max_io_buf_bytes: u32,
+    pub dev_id: u32,
+    pub ublksrv_pid: i32,
+    pub pad1: u32,
+    pub flags: u64,
+    pub ublksrv_flags: u64,
+    pub owner_uid: u32,
+    pub owner_gid: u32,
+    pub reserved1: u64,
+    pub reserved2: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+#[allow(non_camel_case_types)]
+struct ublksrv_io_cmd {
+    q_id: u16,
+    tag: u16,
+    result: i32,
+    addr: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+#[allow(non_camel_case_types)]
+struct ublksrv_ctrl_cmd {
+    dev_id: u32,
+    queue_id: u16,
+    len: u16,
+    addr: u64,
+    data: [u64; 1],
+    dev_path_len: u16,
+    pad: u16,
+    reserved: u32,
+}
+
+impl IoDesc {
+    /// The operation of the request, such as [IoDesc::OP_READ].
+    #[inline]
+    pub fn op(&self) -> u8 {
+        self.op_flags as u8
+    }
+
+    pub const OP_READ: u8 = 0;
+    pub const OP_WRITE: u8 = 1;
+    pub const OP_FLUSH: u8 = 2;
+    pub const OP_DISCARD: u8 = 3;
+    pub const OP_WRITE_SAME: u8 = 4;
+    pub const OP_WRITE_ZEROES: u8 = 5;
+}
+
+impl IoOp {
+    /// The opcode of kernels before Linux 6.1.
+    pub const fn legacy(self) -> u32 {
+        match self {
+            IoOp::FetchReq => 0x20,
+            IoOp::CommitAndFetchReq => 0x21,
+            IoOp::NeedGetData => 0x22,
+        }
+    }
+
+    /// The `ioctl` encoding of the opcode.
+    pub const fn ioctl(self) -> u32 {
+        iowr(self.legacy(), mem::size_of::<ublksrv_io_cmd>())
+    }
+}
+
+impl CtrlOp {
+    /// The opcode of kernels before Linux 6.1.
+    pub const fn legacy(self) -> u32 {
+        match self {
+            CtrlOp::GetQueueAffinity => 0x01,
+            CtrlOp::GetDevInfo => 0x02,
+            CtrlOp::AddDev => 0x04,
+            CtrlOp::DelDev => 0x05,
+            CtrlOp::StartDev => 0x06,
+            CtrlOp::StopDev => 0x07,
+            CtrlOp::SetParams => 0x08,
+            CtrlOp::GetParams => 0x09,
+            CtrlOp::StartUserRecovery => 0x10,
+            CtrlOp::EndUserRecovery => 0x11,
+            CtrlOp::GetDevInfo2 => 0x12,
+        }
+    }
+
+    /// The `ioctl` encoding of the opcode.
+    pub const fn ioctl(self) -> u32 {
+        let size = mem::size_of::<ublksrv_ctrl_cmd>();
+
+        match self {
+            CtrlOp::GetQueueAffinity
+            | CtrlOp::GetDevInfo
+            | CtrlOp::GetParams
+            | CtrlOp::GetDevInfo2 => ior(self.legacy(), size),
+            _ => iowr(self.legacy(), size),
+        }
+    }
+}
+
+impl IoCmd {
+    /// Wait for a request of `tag`, whose data is read from or written to `addr`.
+    pub fn fetch_req(q_id: u16, tag: u16, addr: *mut u8) -> IoCmd {
+        IoCmd::new(IoOp::FetchReq, q_id, tag, 0, addr)
+    }
+
+    /// Complete the request of `tag` with `result`, the number of bytes
+    /// or a negated errno, and wait for the next one, into `addr`.
+    pub fn commit_and_fetch_req(q_id: u16, tag: u16, result: i32, addr: *mut u8) -> IoCmd {
+        IoCmd::new(IoOp::CommitAndFetchReq, q_id, tag, result, addr)
+    }
+
+    /// Get the data of the write request of `tag` into `addr`.
+    pub fn need_get_data(q_id: u16, tag: u16, addr: *mut u8) -> IoCmd {
+        IoCmd::new(IoOp::NeedGetData, q_id, tag, 0, addr)
+    }
+
+    fn new(op: IoOp, q_id: u16, tag: u16, result: i32, addr: *mut u8) -> IoCmd {
+        IoCmd {
+            op,
+            legacy: false,
+            cmd: ublksrv_io_cmd {
+                q_id,
+                tag,
+                result,
+                addr: addr as _,
+            },
+        }
+    }
+
+    /// Use the opcode of kernels before Linux 6.1.
+    pub fn legacy(mut self) -> IoCmd {
+        self.legacy = true;
+        self
+    }
+
+    /// Build the entry of the command on the character device `fd`.
+    ///
+    /// The buffer must stay valid until the request of the tag completes.
+    pub fn build(self, fd: impl sealed::UseFixed) -> Entry {
+        let cmd_op = if self.legacy {
+            self.op.legacy()
+        } else {
+            self.op.ioctl()
+        };
+
+        // The command is plain data of the size of the command area.
+        let cmd = unsafe { mem::transmute::<ublksrv_io_cmd, [u8; 16]>(self.cmd) };
+        opcode::UringCmd16::new(fd, cmd_op).cmd(cmd).build()
+    }
+}
+
+impl CtrlCmd {
+    /// A command of the device `dev_id`, which is `u32::MAX` to allocate one with
+    /// [CtrlOp::AddDev].
+    pub fn new(op: CtrlOp, dev_id: u32) -> CtrlCmd {
+        CtrlCmd {
+            op,
+            legacy: false,
+            cmd: ublksrv_ctrl_cmd {
+                dev_id,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Add a device described by `info`, which the driver updates, e.g. with the id it allocated.
+    pub fn add_dev(info: &mut DevInfo) -> CtrlCmd {
+        CtrlCmd::new(CtrlOp::AddDev, info.dev_id).buffer(
+            info as *mut DevInfo as *mut u8,
+            mem::size_of::<DevInfo>() as _,
+        )
+    }
+
+    /// Get the information of the device `dev_id` into `info`.
+    pub fn get_dev_info(dev_id: u32, info: &mut DevInfo) -> CtrlCmd {
+        CtrlCmd::new(CtrlOp::GetDevInfo, dev_id).buffer(
+            info as *mut DevInfo as *mut u8,
+            mem::size_of::<DevInfo>() as _,
+        )
+    }
+
+    /// Start the device `dev_id` served by the process `pid`, once every tag of every queue
+    /// has been fetched.
+    pub fn start_dev(dev_id: u32, pid: libc::pid_t) -> CtrlCmd {
+        CtrlCmd::new(CtrlOp::StartDev, dev_id).data(pid as _)
+    }
+
+    pub fn stop_dev(dev_id: u32) -> CtrlCmd {
+        CtrlCmd::new(CtrlOp::StopDev, dev_id)
+    }
+
+    pub fn del_dev(dev_id: u32) -> CtrlCmd {
+        CtrlCmd::new(CtrlOp::DelDev, dev_id)
+    }
+
+    /// The queue of [CtrlOp::GetQueueAffinity].
+    pub fn queue_id(mut self, queue_id: u16) -> CtrlCmd {
+        self.cmd.queue_id = queue_id;
+        self
+    }
+
+    /// The buffer the command reads from or writes to, such as the parameters of
+    /// [CtrlOp::SetParams] and [CtrlOp::GetParams].
+    pub fn buffer(mut self, addr: *mut u8, len: u16) -> CtrlCmd {
+        self.cmd.addr = addr as _;
+        self.cmd.len = len;
+        self
+    }
+
+    /// The inline argument of the command.
+    pub fn data(mut self, data: u64) -> CtrlCmd {
+        self.cmd.data = [data];
+        self
+    }
+
+    /// The length of the path of the character device at the start of the buffer,
+    /// for devices that can be served without privileges.
+    pub fn dev_path_len(mut self, len: u16) -> CtrlCmd {
+        self.cmd.dev_path_len = len;
+        self
+    }
+
+    /// Use the opcode of kernels before Linux 6.1.
+    pub fn legacy(mut self) -> CtrlCmd {
+        self.legacy = true;
+        self
+    }
+
+    /// Build the entry of the command on the control device `fd`.
+    ///
+    /// The buffer must stay valid until the command completes.
+    pub fn build(self, fd: impl sealed::UseFixed) -> Entry128 {
+        let cmd_op = if self.legacy {
+            self.op.legacy()
+        } else {
+            self.op.ioctl()
+        };
+
+        let mut cmd = [0; 80];
+        // The command is plain data, and fits in the command area.
+        let bytes = unsafe {
+            mem::transmute::<ublksrv_ctrl_cmd, [u8; mem::size_of::<ublksrv_ctrl_cmd>()]>(self.cmd)
+        };
+        cmd[..bytes.len()].copy_from_slice(&bytes);
+        opcode::UringCmd80::new(fd, cmd_op).cmd(cmd).build()
+    }
+}
+
+// The direction bits of `ioctl` numbers are laid out differently on a few architectures.
+#[cfg(any(
+    target_arch = "powerpc",
+    target_arch = "powerpc64",
+    target_arch = "sparc",
+    target_arch = "sparc64",
+    target_arch = "mips",
+    target_arch = "mips64"
+))]
+const IOC: (u32, u32, u32) = (29, 2, 4);
+#[cfg(not(any(
+    target_arch = "powerpc",
+    target_arch = "powerpc64",
+    target_arch = "sparc",
+    target_arch = "sparc64",
+    target_arch = "mips",
+    target_arch = "mips64"
+)))]
+const IOC: (u32, u32, u32) = (30, 2, 1);
+
+const fn ior(nr: u32, size: usize) -> u32 {
+    ioc(IOC.1, nr, size)
+}
+
+const fn iowr(nr: u32, size: usize) -> u32 {
+    ioc(IOC.1 | IOC.2, nr, size)
+}
+
+/// Encode an `ioctl` number, like the `_IOC` macro.
+const fn ioc(dir: u32, nr: u32, size: usize) -> u32 {
+    dir << IOC.0 | (size as u32) << 16 | IOCTL_TYPE << 8 | nr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::{offset_of, size_of};
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(size_of::<ublksrv_io_cmd>(), 16);
+        assert_eq!(offset_of!(ublksrv_io_cmd, result), 4);
+        assert_eq!(offset_of!(ublksrv_io_cmd, addr), 8);
+
+        assert_eq!(size_of::<ublksrv_ctrl_cmd>(), 32);
+        assert_eq!(offset_of!(ublksrv_ctrl_cmd, len), 6);
+        assert_eq!(offset_of!(ublksrv_ctrl_cmd, addr), 8);
+        assert_eq!(offset_of!(ublksrv_ctrl_cmd, data), 16);
+        assert_eq!(offset_of!(ublksrv_ctrl_cmd, dev_path_len), 24);
+        assert_eq!(offset_of!(ublksrv_ctrl_cmd, reserved), 28);
+
+        assert_eq!(size_of::<DevInfo>(), 64);
+        assert_eq!(offset_of!(DevInfo, max_io_buf_bytes), 8);
+        assert_eq!(offset_of!(DevInfo, ublksrv_pid), 16);
+        assert_eq!(offset_of!(DevInfo, flags), 24);
+        assert_eq!(offset_of!(DevInfo, ublksrv_flags), 32);
+        assert_eq!(offset_of!(DevInfo, owner_uid), 40);
+        assert_eq!(offset_of!(DevInfo, reserved2), 56);
+
+        assert_eq!(size_of::<IoDesc>(), 24);
+    }
+
+    /// The values of `UBLK_U_IO_*` and `UBLK_U_CMD_*` in the kernel headers.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    #[test]
+    fn test_ioctl() {
+        assert_eq!(IoOp::FetchReq.ioctl(), 0xc010_7520);
+        assert_eq!(IoOp::CommitAndFetchReq.ioctl(), 0xc010_7521);
+        assert_eq!(IoOp::NeedGetData.ioctl(), 0xc010_7522);
+
+        assert_eq!(CtrlOp::GetQueueAffinity.ioctl(), 0x8020_7501);
+        assert_eq!(CtrlOp::GetDevInfo.ioctl(), 0x8020_7502);
+        assert_eq!(CtrlOp::AddDev.ioctl(), 0xc020_7504);
+        assert_eq!(CtrlOp::DelDev.ioctl(), 0xc020_7505);
+        assert_eq!(CtrlOp::StartDev.ioctl(), 0xc020_7506);
+        assert_eq!(CtrlOp::StopDev.ioctl(), 0xc020_7507);
+        assert_eq!(CtrlOp::SetParams.ioctl(), 0xc020_7508);
+        assert_eq!(CtrlOp::GetParams.ioctl(), 0x8020_7509);
+        assert_eq!(CtrlOp::StartUserRecovery.ioctl(), 0xc020_7510);
+        assert_eq!(CtrlOp::EndUserRecovery.ioctl(), 0xc020_7511);
+        assert_eq!(CtrlOp::GetDevInfo2.ioctl(), 0x8020_7512);
+    }
+}
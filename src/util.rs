@@ -1,8 +1,9 @@
 use std::convert::TryFrom;
 use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
-use std::sync::atomic;
 use std::{io, mem, ptr};
 
+use crate::sync::atomic;
+
 macro_rules! mmap_offset {
     ( $mmap:ident + $offset:expr => $ty:ty ) => {
         $mmap.as_mut_ptr().add($offset as _) as $ty
@@ -40,6 +41,98 @@ impl Mmap {
         }
     }
 
+    /// Allocate a private anonymous mapping of `len` bytes.
+    #[cfg(feature = "unstable")]
+    pub fn new_anon(len: usize) -> io::Result<Mmap> {
+        unsafe {
+            match libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_POPULATE,
+                -1,
+                0,
+            ) {
+                libc::MAP_FAILED => Err(io::Error::last_os_error()),
+                addr => Ok(Mmap {
+                    addr: ptr::NonNull::new_unchecked(addr),
+                    len,
+                }),
+            }
+        }
+    }
+
+    /// Allocate a private anonymous mapping backed by a single huge page,
+    /// on the given NUMA node if any.
+    #[cfg(feature = "unstable")]
+    pub fn new_huge_page(numa_node: Option<u32>) -> io::Result<Mmap> {
+        let len = huge_page_size()?;
+
+        // The pages must only be faulted in once the memory policy is set.
+        let mut flags = libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_HUGETLB;
+        if numa_node.is_none() {
+            flags |= libc::MAP_POPULATE;
+        }
+
+        let mmap = unsafe {
+            match libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                flags,
+                -1,
+                0,
+            ) {
+                libc::MAP_FAILED => return Err(io::Error::last_os_error()),
+                addr => Mmap {
+                    addr: ptr::NonNull::new_unchecked(addr),
+                    len,
+                },
+            }
+        };
+
+        if let Some(node) = numa_node {
+            mmap.bind(node)?;
+
+            // Unlike touching the memory, this fails instead of raising `SIGBUS`
+            // if there is no huge page left on the node.
+            match unsafe { libc::madvise(mmap.as_mut_ptr(), len, libc::MADV_POPULATE_WRITE) } {
+                0 => (),
+                _ => return Err(io::Error::last_os_error()),
+            }
+        }
+
+        Ok(mmap)
+    }
+
+    /// Bind the memory to a NUMA node with `mbind(2)`.
+    #[cfg(feature = "unstable")]
+    fn bind(&self, node: u32) -> io::Result<()> {
+        const MPOL_BIND: libc::c_int = 2;
+        const MPOL_MF_STRICT: libc::c_uint = 1;
+
+        let bits = mem::size_of::<libc::c_ulong>() * 8;
+        let mut nodemask = vec![0 as libc::c_ulong; node as usize / bits + 1];
+        nodemask[node as usize / bits] |= 1 << (node as usize % bits);
+
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_mbind,
+                self.addr.as_ptr(),
+                self.len,
+                MPOL_BIND,
+                nodemask.as_ptr(),
+                nodemask.len() * bits + 1,
+                MPOL_MF_STRICT,
+            )
+        };
+
+        match ret {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
     pub fn dontfork(&self) -> io::Result<()> {
         match unsafe { libc::madvise(self.addr.as_ptr(), self.len, libc::MADV_DONTFORK) } {
             0 => Ok(()),
@@ -53,6 +146,20 @@ impl Mmap {
     }
 }
 
+/// The default huge page size, as reported by `/proc/meminfo`.
+#[cfg(feature = "unstable")]
+fn huge_page_size() -> io::Result<usize> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo")?;
+
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("Hugepagesize:"))
+        .and_then(|size| size.trim().strip_suffix("kB"))
+        .and_then(|size| size.trim().parse::<usize>().ok())
+        .map(|size| size * 1024)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "huge pages are not supported"))
+}
+
 impl Drop for Mmap {
     fn drop(&mut self) {
         unsafe {
@@ -107,12 +214,80 @@ impl Drop for Fd {
     }
 }
 
+/// Duplicate a file descriptor, e.g. to keep a ring open, with `FD_CLOEXEC` set.
+#[cfg(feature = "unstable")]
+pub fn dup(fd: RawFd) -> io::Result<Fd> {
+    Fd::try_from(unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) })
+        .map_err(|_| io::Error::last_os_error())
+}
+
+#[cfg(not(loom))]
 #[inline(always)]
 pub unsafe fn unsync_load(u: *const atomic::AtomicU32) -> u32 {
     u.cast::<u32>().read()
 }
 
+#[cfg(loom)]
+#[inline(always)]
+pub unsafe fn unsync_load(u: *const atomic::AtomicU32) -> u32 {
+    (*u).unsync_load()
+}
+
 #[inline]
 pub fn cast_ptr<T>(n: &T) -> *const T {
     n as *const T
 }
+
+/// Convert a socket address to its C representation.
+#[cfg(feature = "unstable")]
+pub fn sockaddr(addr: std::net::SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    use std::net::SocketAddr;
+
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+
+    let len = match addr {
+        SocketAddr::V4(addr) => {
+            let sin = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+            sin.sin_family = libc::AF_INET as _;
+            sin.sin_port = addr.port().to_be();
+            sin.sin_addr.s_addr = u32::from_ne_bytes(addr.ip().octets());
+            mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(addr) => {
+            let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
+            sin6.sin6_family = libc::AF_INET6 as _;
+            sin6.sin6_port = addr.port().to_be();
+            sin6.sin6_flowinfo = addr.flowinfo();
+            sin6.sin6_addr.s6_addr = addr.ip().octets();
+            sin6.sin6_scope_id = addr.scope_id();
+            mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+
+    (storage, len as _)
+}
+
+/// Convert the C representation of a socket address back,
+/// fails for families other than `AF_INET` and `AF_INET6`.
+#[cfg(feature = "unstable")]
+pub fn socket_addr(storage: &libc::sockaddr_storage) -> io::Result<std::net::SocketAddr> {
+    use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
+
+    match storage.ss_family as libc::c_int {
+        libc::AF_INET => {
+            let sin = unsafe { &*(storage as *const _ as *const libc::sockaddr_in) };
+            let ip = Ipv4Addr::from(sin.sin_addr.s_addr.to_ne_bytes());
+            Ok(SocketAddrV4::new(ip, u16::from_be(sin.sin_port)).into())
+        }
+        libc::AF_INET6 => {
+            let sin6 = unsafe { &*(storage as *const _ as *const libc::sockaddr_in6) };
+            let ip = Ipv6Addr::from(sin6.sin6_addr.s6_addr);
+            let port = u16::from_be(sin6.sin6_port);
+            Ok(SocketAddrV6::new(ip, port, sin6.sin6_flowinfo, sin6.sin6_scope_id).into())
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not an internet socket address",
+        )),
+    }
+}
@@ -0,0 +1,185 @@
+//! Buffers of zero-copy sends.
+
+use crate::cqueue;
+use crate::squeue::{self, AvailableQueue, PushError};
+use crate::sys;
+use crate::tracker::OpTracker;
+
+/// The buffers of the [SendZc](crate::opcode::SendZc) requests in flight,
+/// keyed by the `user_data` of their requests.
+///
+/// A zero-copy send completes twice: the kernel first posts the result, and later a
+/// notification once it no longer uses the buffer, which may only be reused afterwards.
+/// The tracker keeps each buffer until its notification, or until the result
+/// if no notification follows, e.g. because the send failed,
+/// and hands it back with [SendZcEvent::Released] to return it to its pool.
+///
+/// Dropping the tracker while sends are in flight leaks their buffers,
+/// because the kernel may still use them.
+pub struct SendZcTracker<B> {
+    sends: OpTracker<Send<B>>,
+}
+
+struct Send<B> {
+    buf: B,
+    sent: bool,
+}
+
+/// What a completion event of a zero-copy send means for its buffer,
+/// see [SendZcTracker::complete].
+#[derive(Debug, PartialEq, Eq)]
+pub enum SendZcEvent<B> {
+    /// The result of the send, the buffer is still used until the notification.
+    Sent(i32),
+
+    /// The result of a send without notification, and its buffer.
+    Completed(i32, B),
+
+    /// The notification of a send, its result has been reported before.
+    ///
+    /// `copied` is set if the data was copied after all,
+    /// which is only reported with [report_usage](crate::opcode::SendZc::report_usage).
+    Released { buf: B, copied: bool },
+}
+
+impl<B> SendZcTracker<B> {
+    pub fn new() -> SendZcTracker<B> {
+        SendZcTracker {
+            sends: OpTracker::new(),
+        }
+    }
+
+    /// The number of sends whose buffer is not released yet.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.sends.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.sends.is_empty()
+    }
+
+    /// Push a zero-copy send into the submission queue,
+    /// and keep its buffer until it is released.
+    ///
+    /// The `user_data` of the entry is set to the returned key.
+    ///
+    /// # Safety
+    ///
+    /// The entry may only refer to the memory of `buf` that does not move with it,
+    /// like the contents of a `Vec` or a registered buffer,
+    /// and the other parameters of the entry must be valid.
+    pub unsafe fn push(
+        &mut self,
+        sq: &mut AvailableQueue<'_>,
+        entry: squeue::Entry,
+        buf: B,
+    ) -> Result<u64, PushError<B>> {
+        let send = Send { buf, sent: false };
+
+        self.sends
+            .push(sq, entry, send)
+            .map_err(|PushError::Full((_, send))| PushError::Full(send.buf))
+    }
+
+    /// Record a completion event, returns `None` if it does not belong to a send in flight.
+    pub fn complete(&mut self, entry: &cqueue::Entry) -> Option<SendZcEvent<B>> {
+        let key = entry.user_data();
+        let send = self.sends.get_mut(key)?;
+
+        if entry.is_notif() {
+            let copied = entry.result() as u32 & sys::IORING_NOTIF_USAGE_ZC_COPIED != 0;
+            let send = self.sends.remove(key)?;
+            return Some(SendZcEvent::Released {
+                buf: send.buf,
+                copied,
+            });
+        }
+
+        // Only one result is posted per send, another event is a stray one.
+        if send.sent {
+            return None;
+        }
+
+        if entry.is_more() {
+            send.sent = true;
+            Some(SendZcEvent::Sent(entry.result()))
+        } else {
+            let send = self.sends.remove(key)?;
+            Some(SendZcEvent::Completed(entry.result(), send.buf))
+        }
+    }
+}
+
+impl<B> Default for SendZcTracker<B> {
+    fn default() -> SendZcTracker<B> {
+        SendZcTracker::new()
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::{MockRing, Reply};
+    use crate::opcode::{self, types};
+
+    fn send(buf: &[u8]) -> squeue::Entry {
+        opcode::SendZc::new(types::Fd(3), buf.as_ptr(), buf.len() as _).build()
+    }
+
+    fn events(
+        ring: &mut MockRing,
+        sends: &mut SendZcTracker<Vec<u8>>,
+    ) -> Vec<SendZcEvent<Vec<u8>>> {
+        ring.completion()
+            .available()
+            .map(|cqe| sends.complete(&cqe).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_release_after_notification() {
+        let mut ring = MockRing::new(2);
+        let mut sends = SendZcTracker::new();
+
+        let buf = vec![1; 8];
+        let entry = send(&buf);
+        let key = unsafe { sends.push(&mut ring.submission().available(), entry, buf) }.unwrap();
+        ring.submit().unwrap();
+
+        assert!(ring.complete(key, Reply::new(8).more()));
+        assert_eq!(events(&mut ring, &mut sends), [SendZcEvent::Sent(8)]);
+        assert_eq!(sends.len(), 1);
+
+        assert!(ring.complete(key, Reply::new(0).notif()));
+        let released = SendZcEvent::Released {
+            buf: vec![1; 8],
+            copied: false,
+        };
+        assert_eq!(events(&mut ring, &mut sends), [released]);
+        assert!(sends.is_empty());
+    }
+
+    #[test]
+    fn test_failed_send_and_full_queue() {
+        let mut ring = MockRing::new(1);
+        let mut sends = SendZcTracker::new();
+
+        let (a, b) = (vec![1; 4], vec![2; 4]);
+        let (entry_a, entry_b) = (send(&a), send(&b));
+        let key = unsafe { sends.push(&mut ring.submission().available(), entry_a, a) }.unwrap();
+
+        // A rejected send hands its buffer back at once.
+        match unsafe { sends.push(&mut ring.submission().available(), entry_b, b) } {
+            Err(PushError::Full(buf)) => assert_eq!(buf, [2; 4]),
+            Ok(_) => panic!("pushed into a full queue"),
+        }
+
+        ring.reply(key, Reply::new(-libc::EPIPE));
+        ring.submit().unwrap();
+        let completed = SendZcEvent::Completed(-libc::EPIPE, vec![1; 4]);
+        assert_eq!(events(&mut ring, &mut sends), [completed]);
+        assert!(sends.is_empty());
+    }
+}